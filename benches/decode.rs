@@ -0,0 +1,100 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Decoding of `poi.txt` records by [PoiDecoder], compared with their
+//! `Deserialize` implementation
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use navitia_poi_model::io::{ByteRecord, ColumnDefaults, PoiDecoder, PoiRecord};
+use std::fmt::Write;
+use std::hint::black_box;
+
+const HEADERS: &str = "poi_id;poi_type_id;poi_name;poi_lat;poi_lon;poi_weight;poi_visible;\
+                       poi_created_at;poi_updated_at;poi_wheelchair;poi_elevation";
+
+/// Content of a `poi.txt` file of `count` POIs, half of them with their
+/// optional values
+fn pois(count: usize) -> String {
+    let mut content = format!("{}\n", HEADERS);
+    for i in 0..count {
+        let lat = 48. + (i % 1000) as f64 / 1000.;
+        let lon = 2. + (i / 1000) as f64 / 1000.;
+        if i % 2 == 0 {
+            writeln!(
+                content,
+                "poi:{};amenity:parking;Parking {};{};{};{};1;\
+                 2020-01-01T00:00:00Z;2021-06-30T12:34:56Z;yes;35.5",
+                i,
+                i,
+                lat,
+                lon,
+                i % 10
+            )
+        } else {
+            writeln!(
+                content,
+                "poi:{};shop;\"Shop; {}\";{};{};0;0;;;;",
+                i, i, lat, lon
+            )
+        }
+        .expect("writing to a string");
+    }
+    content
+}
+
+fn records(content: &str) -> (ByteRecord, Vec<ByteRecord>) {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .from_reader(content.as_bytes());
+    let headers = reader.byte_headers().expect("headers").clone();
+    let records = reader
+        .byte_records()
+        .collect::<Result<_, _>>()
+        .expect("records");
+    (headers, records)
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("poi.txt");
+    for count in [1_000, 100_000].iter() {
+        let content = pois(*count);
+        let (headers, records) = records(&content);
+        group.throughput(Throughput::Elements(*count as u64));
+        group.bench_with_input(
+            BenchmarkId::new("decoder", count),
+            &records,
+            |b, records| {
+                let decoder =
+                    PoiDecoder::new(&headers, &ColumnDefaults::default()).expect("headers");
+                b.iter(|| {
+                    for record in records {
+                        black_box(decoder.decode(record).expect("record"));
+                    }
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("serde", count), &records, |b, records| {
+            b.iter(|| {
+                for record in records {
+                    let record: PoiRecord = record.deserialize(Some(&headers)).expect("record");
+                    black_box(record);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
@@ -0,0 +1,174 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+//! Typed accessibility attributes of POIs
+//!
+//! The [Accessibility] of a POI is stored in the `poi.txt` file of the
+//! archives, in columns written only when used, and mirrored in its
+//! properties (`wheelchair`, `visual_aids`, `audio_aids` and `elevator`) for
+//! the readers that do not know these columns. [Poi::set_accessibility]
+//! mirrors it in the model as well, so that it survives a save and load
+//! round trip. When loading an archive whose `poi.txt` lacks the column of
+//! an attribute, the attribute is parsed from the properties, accepting a
+//! few common aliases of the keys and values (see [Access::parse]).
+
+use crate::Poi;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+
+/// Whether a facility is available
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Access {
+    /// Fully available
+    Yes,
+
+    /// Partially available
+    Limited,
+
+    /// Not available
+    No,
+}
+
+impl Access {
+    /// Parses a value, case insensitive: `yes`, `true`, `1` and
+    /// `designated` are [Access::Yes], `limited` and `partial` are
+    /// [Access::Limited], and `no`, `false` and `0` are [Access::No]
+    pub fn parse(value: &str) -> Option<Access> {
+        match value.trim().to_lowercase().as_str() {
+            "yes" | "true" | "1" | "designated" => Some(Access::Yes),
+            "limited" | "partial" => Some(Access::Limited),
+            "no" | "false" | "0" => Some(Access::No),
+            _ => None,
+        }
+    }
+
+    /// Returns the canonical value: `yes`, `limited` or `no`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Access::Yes => "yes",
+            Access::Limited => "limited",
+            Access::No => "no",
+        }
+    }
+}
+
+impl std::fmt::Display for Access {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Access {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Access {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Access, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Access::parse(&value)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid access '{}'", value)))
+    }
+}
+
+/// The accessibility of a POI. Unknown attributes are `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Accessibility {
+    /// Access for wheelchairs
+    pub wheelchair: Option<Access>,
+
+    /// Aids for visually impaired people, like tactile paving
+    pub visual_aids: Option<Access>,
+
+    /// Aids for hearing impaired people, or audio guidance for visually
+    /// impaired people
+    pub audio_aids: Option<Access>,
+
+    /// Elevator
+    pub elevator: Option<Access>,
+}
+
+/// Property keys of the attributes, canonical key first
+const WHEELCHAIR_KEYS: [&str; 4] = [
+    "wheelchair",
+    "wheelchair_access",
+    "wheelchair_accessible",
+    "accessibility:wheelchair",
+];
+const VISUAL_AIDS_KEYS: [&str; 3] = ["visual_aids", "tactile_paving", "visual_aid"];
+const AUDIO_AIDS_KEYS: [&str; 3] = ["audio_aids", "speech_output", "audio_aid"];
+const ELEVATOR_KEYS: [&str; 3] = ["elevator", "lift", "has_elevator"];
+
+impl Accessibility {
+    /// Parses the accessibility from properties. For each attribute, the
+    /// first of its keys with a valid value is used.
+    pub fn from_properties(properties: &BTreeMap<String, String>) -> Accessibility {
+        let parse = |keys: &[&str]| {
+            keys.iter()
+                .filter_map(|key| properties.get(*key))
+                .find_map(|value| Access::parse(value))
+        };
+        Accessibility {
+            wheelchair: parse(&WHEELCHAIR_KEYS),
+            visual_aids: parse(&VISUAL_AIDS_KEYS),
+            audio_aids: parse(&AUDIO_AIDS_KEYS),
+            elevator: parse(&ELEVATOR_KEYS),
+        }
+    }
+
+    /// Returns the known attributes as properties, with their canonical
+    /// key and value
+    pub fn to_properties(&self) -> impl Iterator<Item = (&'static str, &'static str)> {
+        [
+            (WHEELCHAIR_KEYS[0], self.wheelchair),
+            (VISUAL_AIDS_KEYS[0], self.visual_aids),
+            (AUDIO_AIDS_KEYS[0], self.audio_aids),
+            (ELEVATOR_KEYS[0], self.elevator),
+        ]
+        .iter()
+        .filter_map(|(key, access)| access.map(|access| (*key, access.as_str())))
+        .collect::<Vec<_>>()
+        .into_iter()
+    }
+
+    /// Fills the unknown attributes with those of `other`
+    pub fn or(self, other: Accessibility) -> Accessibility {
+        Accessibility {
+            wheelchair: self.wheelchair.or(other.wheelchair),
+            visual_aids: self.visual_aids.or(other.visual_aids),
+            audio_aids: self.audio_aids.or(other.audio_aids),
+            elevator: self.elevator.or(other.elevator),
+        }
+    }
+}
+
+impl Poi {
+    /// Sets the accessibility of the POI, and mirrors it in its properties
+    pub fn set_accessibility(&mut self, accessibility: Accessibility) {
+        for key in [
+            WHEELCHAIR_KEYS[0],
+            VISUAL_AIDS_KEYS[0],
+            AUDIO_AIDS_KEYS[0],
+            ELEVATOR_KEYS[0],
+        ]
+        .iter()
+        {
+            self.properties.remove(*key);
+        }
+        for (key, value) in accessibility.to_properties() {
+            self.properties.insert(key.to_string(), value.to_string());
+        }
+        self.accessibility = accessibility;
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+//! Assignment of administrative areas to POIs
+//!
+//! An [AdminAssigner] finds the administrative areas (city, region, ...)
+//! containing a coordinate. [Model::assign_admins] stores them in the
+//! properties of the POIs: each [AdminRef] of level `level` is stored as an
+//! `admin:<level>` property holding its id and an `admin:<level>:name`
+//! property holding its name.
+
+use crate::{Coord, Model, Poi};
+use chrono::Utc;
+
+/// Prefix of the properties holding administrative areas
+pub const ADMIN_PREFIX: &str = "admin:";
+
+/// A reference to an administrative area
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AdminRef {
+    /// Level of the area, like `city` or `region`
+    pub level: String,
+
+    /// Id of the area, like an INSEE code
+    pub id: String,
+
+    /// Name of the area
+    pub name: String,
+}
+
+/// Finds the administrative areas containing a coordinate
+pub trait AdminAssigner {
+    /// Returns the administrative areas containing `coord`, at most one
+    /// per level
+    fn admins_for(&self, coord: &Coord) -> Vec<AdminRef>;
+}
+
+impl<F> AdminAssigner for F
+where
+    F: Fn(&Coord) -> Vec<AdminRef>,
+{
+    fn admins_for(&self, coord: &Coord) -> Vec<AdminRef> {
+        self(coord)
+    }
+}
+
+impl Poi {
+    /// Returns the administrative areas assigned to the POI by
+    /// [Model::assign_admins]
+    pub fn admins(&self) -> Vec<AdminRef> {
+        self.properties
+            .iter()
+            .filter_map(|(key, id)| {
+                let level = key.strip_prefix(ADMIN_PREFIX)?;
+                if level.contains(':') {
+                    return None;
+                }
+                let name = self
+                    .properties
+                    .get(&format!("{}{}:name", ADMIN_PREFIX, level))
+                    .cloned()
+                    .unwrap_or_default();
+                Some(AdminRef {
+                    level: level.to_string(),
+                    id: id.clone(),
+                    name,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Model {
+    /// Assigns the administrative areas given by `assigner` to all the
+    /// POIs with valid coordinates, replacing those previously assigned.
+    /// Returns the number of POIs whose areas changed; their `updated_at`
+    /// is set to the current time.
+    pub fn assign_admins<A: AdminAssigner + ?Sized>(&mut self, assigner: &A) -> usize {
+        let now = Utc::now();
+        let mut count = 0;
+        for poi in self.pois.values_mut().filter(|poi| poi.coord.is_valid()) {
+            let mut properties = poi.properties.clone();
+            properties.retain(|key, _| !key.starts_with(ADMIN_PREFIX));
+            for admin in assigner.admins_for(&poi.coord) {
+                let key = format!("{}{}", ADMIN_PREFIX, admin.level);
+                properties.insert(format!("{}:name", key), admin.name);
+                properties.insert(key, admin.id);
+            }
+            if properties != poi.properties {
+                poi.properties = properties;
+                poi.updated_at = Some(now);
+                count += 1;
+            }
+        }
+        count
+    }
+}
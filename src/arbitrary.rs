@@ -0,0 +1,306 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+//! Implementations of [proptest]'s [Arbitrary] for the objects of the model
+//!
+//! Generated values mix plain data with adversarial data: strings with CSV
+//! delimiters, quotes, line breaks and non-ASCII characters, coordinates out
+//! of range or at their default value, fractional and negative weights.
+//! They all survive a save and load round trip: floats are finite, the
+//! POIs of a generated [Model] reference its POI types, and their
+//! accessibility is mirrored in their properties, some of which use the
+//! keys and aliases of the accessibility.
+
+use crate::accessibility::{Access, Accessibility};
+use crate::{Coord, Metadata, Model, Poi, PoiRelation, PoiType, Weight};
+use chrono::{DateTime, TimeZone, Utc};
+use proptest::collection::{btree_map, vec};
+use proptest::option;
+use proptest::prelude::*;
+use std::collections::BTreeSet;
+
+/// Strings, including characters that must be quoted or escaped
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-zA-Z0-9:_ -]{0,16}",
+        "\\PC{0,16}",
+        "[;,\"'\\\\\t\r\n éß€😀]{0,8}",
+    ]
+}
+
+/// Keys of properties, including those parsed as accessibility
+fn property_key() -> impl Strategy<Value = String> {
+    prop_oneof![
+        3 => text(),
+        1 => prop_oneof![
+            Just("wheelchair"),
+            Just("wheelchair_access"),
+            Just("visual_aids"),
+            Just("tactile_paving"),
+            Just("audio_aids"),
+            Just("speech_output"),
+            Just("elevator"),
+            Just("lift"),
+        ]
+        .prop_map(str::to_string),
+    ]
+}
+
+/// Values of properties, including those parsed as an [Access]
+fn property_value() -> impl Strategy<Value = String> {
+    prop_oneof![
+        3 => text(),
+        1 => prop_oneof![Just("yes"), Just("Limited"), Just("no"), Just("1")]
+            .prop_map(str::to_string),
+    ]
+}
+
+/// Ids of POI types, never empty
+fn poi_type_id() -> impl Strategy<Value = String> {
+    prop_oneof!["[a-z_]{1,8}(:[a-z_]{1,8})?", "[;\"' é]{1,4}"]
+}
+
+/// Timestamps between 1970 and 2100, with nanoseconds
+fn timestamp() -> impl Strategy<Value = DateTime<Utc>> {
+    (0i64..4_102_444_800, 0u32..1_000_000_000).prop_map(|(secs, nanos)| {
+        Utc.timestamp_opt(secs, nanos)
+            .single()
+            .expect("timestamp in range")
+    })
+}
+
+fn access() -> impl Strategy<Value = Access> {
+    prop_oneof![Just(Access::Yes), Just(Access::Limited), Just(Access::No)]
+}
+
+fn accessibility() -> impl Strategy<Value = Accessibility> {
+    (
+        option::of(access()),
+        option::of(access()),
+        option::of(access()),
+        option::of(access()),
+    )
+        .prop_map(
+            |(wheelchair, visual_aids, audio_aids, elevator)| Accessibility {
+                wheelchair,
+                visual_aids,
+                audio_aids,
+                elevator,
+            },
+        )
+}
+
+fn metadata() -> impl Strategy<Value = Metadata> {
+    (
+        option::of(text()),
+        option::of(timestamp()),
+        option::of(text()),
+        option::of(text()),
+        option::of((-180f64..=180., -90f64..=90., -180f64..=180., -90f64..=90.)),
+    )
+        .prop_map(
+            |(provider, extracted_at, source_version, license, bbox)| Metadata {
+                provider,
+                extracted_at,
+                source_version,
+                license,
+                bbox: bbox.map(|(a, b, c, d)| [a, b, c, d]),
+                dialect: None,
+            },
+        )
+}
+
+impl Arbitrary for Coord {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Coord>;
+
+    /// Mostly valid coordinates, along with default and out of range ones
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            8 => (-180f64..=180., -90f64..=90.).prop_map(|(lon, lat)| Coord::new(lon, lat)),
+            1 => Just(Coord::default()),
+            1 => (-1e6f64..1e6, -1e6f64..1e6).prop_map(|(lon, lat)| Coord::new(lon, lat)),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Weight {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Weight>;
+
+    /// Mostly integral weights, along with any finite ones
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::num::f64::{NEGATIVE, NORMAL, POSITIVE, SUBNORMAL, ZERO};
+        prop_oneof![
+            4 => (0u32..10_000).prop_map(Weight::from),
+            1 => (POSITIVE | NEGATIVE | NORMAL | SUBNORMAL | ZERO).prop_map(Weight),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for PoiType {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<PoiType>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            poi_type_id(),
+            text(),
+            option::of(poi_type_id()),
+            option::of("[a-z_-]{1,12}(\\.svg)?"),
+            option::of("#[0-9a-f]{6}"),
+            option::of(0u8..=22),
+            option::of(any::<bool>()),
+            btree_map("[a-z]{2}(-[A-Z]{2})?", text(), 0..3),
+        )
+            .prop_map(
+                |(id, name, parent_id, icon, color, zoom_min, visible_by_default, translations)| {
+                    PoiType {
+                        id,
+                        name,
+                        parent_id,
+                        icon,
+                        color,
+                        zoom_min,
+                        visible_by_default,
+                        translations,
+                    }
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for Poi {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Poi>;
+
+    /// POIs of any type: see [Model] for POIs of existing types
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            text(),
+            text(),
+            any::<Coord>(),
+            poi_type_id(),
+            btree_map(property_key(), property_value(), 0..4),
+            any::<bool>(),
+            any::<Weight>(),
+            option::of(timestamp()),
+            option::of(timestamp()),
+            accessibility(),
+            option::of(-500f64..9000.),
+        )
+            .prop_map(
+                |(
+                    id,
+                    name,
+                    coord,
+                    poi_type_id,
+                    properties,
+                    visible,
+                    weight,
+                    created_at,
+                    updated_at,
+                    accessibility,
+                    elevation_m,
+                )| {
+                    let mut poi = Poi {
+                        id,
+                        name,
+                        coord,
+                        poi_type_id,
+                        properties,
+                        visible,
+                        weight,
+                        accessibility: Accessibility::default(),
+                        elevation_m,
+                        created_at,
+                        updated_at,
+                    };
+                    // the accessibility is mirrored in the properties on save
+                    poi.set_accessibility(accessibility);
+                    poi
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for Model {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Model>;
+
+    /// Models of up to 20 POIs, each of them of one of the POI types of the
+    /// model, and of up to 5 relations between these POIs
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        vec(any::<PoiType>(), 1..5)
+            .prop_flat_map(|poi_types| {
+                let ids: Vec<String> = poi_types.iter().map(|t| t.id.clone()).collect();
+                let pois = vec((any::<Poi>(), prop::sample::select(ids)), 0..20);
+                (Just(poi_types), pois, option::of(metadata()))
+            })
+            .prop_flat_map(|(poi_types, pois, metadata)| {
+                let model = Model {
+                    pois: pois
+                        .into_iter()
+                        .map(|(poi, poi_type_id)| Poi { poi_type_id, ..poi })
+                        .map(|poi| (poi.id.clone(), poi))
+                        .collect(),
+                    poi_types: poi_types
+                        .into_iter()
+                        .map(|poi_type| (poi_type.id.clone(), poi_type))
+                        .collect(),
+                    metadata,
+                    relations: BTreeSet::new(),
+                };
+                let ids: Vec<String> = model.pois.keys().cloned().collect();
+                let relations = if ids.is_empty() {
+                    Just(Vec::new()).boxed()
+                } else {
+                    let id = prop::sample::select(ids);
+                    vec((id.clone(), id, text()), 0..5).boxed()
+                };
+                (Just(model), relations)
+            })
+            .prop_map(|(mut model, relations)| {
+                model.relations = relations
+                    .into_iter()
+                    .map(|(parent_id, child_id, relation_type)| PoiRelation {
+                        parent_id,
+                        child_id,
+                        relation_type,
+                    })
+                    .collect();
+                model
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{load_model_from_reader, write_model_to_writer, LoadOptions, WriteOptions};
+
+    proptest! {
+        #[test]
+        fn save_load_round_trip(model in any::<Model>()) {
+            let mut archive = Vec::new();
+            write_model_to_writer(&model, &mut archive, &WriteOptions::default()).unwrap();
+            let loaded = load_model_from_reader(archive.as_slice(), &LoadOptions::default()).unwrap();
+            prop_assert_eq!(loaded, model);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Cancellation of long-running operations
+//!
+//! A [CancellationToken] is handed to an operation (see
+//! [LoadOptions::cancellation](crate::io::LoadOptions::cancellation),
+//! [Model::try_merge_with_cancellable](crate::Model::try_merge_with_cancellable),
+//! ...), and cancelled from another thread. The operation then stops as
+//! soon as possible, failing with a [Cancelled] error.
+//!
+//! ```
+//! use navitia_poi_model::cancel::{CancellationToken, Cancelled};
+//! use navitia_poi_model::Model;
+//!
+//! let token = CancellationToken::new();
+//! token.cancel();
+//! let error = Model::default()
+//!     .find_coordinate_duplicates_cancellable(1., &token)
+//!     .unwrap_err();
+//! assert!(error.is::<Cancelled>());
+//! ```
+
+use crate::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag shared between an operation and the ones that may cancel it.
+/// Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token, not cancelled yet
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancels the operations using this token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true once the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fails with [Cancelled] if the token has been cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Cancelled.into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The error of an operation stopped through its [CancellationToken]. It
+/// can be told from other errors with `error.is::<Cancelled>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Fails with [Cancelled] if there is a token, and it has been cancelled
+pub(crate) fn check(token: Option<&CancellationToken>) -> Result<()> {
+    token.map_or(Ok(()), CancellationToken::check)
+}
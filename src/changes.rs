@@ -0,0 +1,560 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Changes to the POIs of a model, read from a change file
+//!
+//! A change file is a CSV file, delimited by `;`, with the columns
+//! `action;poi_id;field;value`, each row being one change:
+//!
+//! - `update` sets the `field` of the POI `poi_id` to `value`. The fields
+//!   are the columns of `poi.txt` (`poi_name`, `poi_type_id`, `poi_lat`,
+//!   `poi_lon`, `poi_weight` and `poi_visible`), any other field being the
+//!   key of a property, removed when `value` is empty.
+//! - `delete` removes the POI `poi_id`, or only its property `field` if
+//!   given.
+//! - `add` gives the `field` of the new POI `poi_id`, a row per field. The
+//!   `poi_name`, `poi_type_id`, `poi_lat` and `poi_lon` are required. A
+//!   POI deleted before its `add` rows is replaced by the new one, which
+//!   keeps its relations.
+//!
+//! The changes are checked before being applied: a change to an unknown
+//! POI, to an unknown POI type, or to an invalid value is rejected, and
+//! reported along with the line of the change, the other changes being
+//! applied (see [Model::apply_changes]).
+//!
+//! ```
+//! use navitia_poi_model::{Model, PoiType};
+//!
+//! # fn main() -> navitia_poi_model::Result<()> {
+//! let mut model = Model::default();
+//! model.poi_types.insert(
+//!     "amenity:parking".to_string(),
+//!     PoiType { id: "amenity:parking".to_string(), name: "Parking".to_string(), ..PoiType::default() },
+//! );
+//! let changes = "action;poi_id;field;value
+//! add;poi:1;poi_name;Parking Gare
+//! add;poi:1;poi_type_id;amenity:parking
+//! add;poi:1;poi_lat;48.84
+//! add;poi:1;poi_lon;2.37
+//! update;poi:1;capacity;120
+//! update;poi:2;poi_name;Unknown
+//! ";
+//! let report = model.apply_changes(changes.as_bytes())?;
+//! assert_eq!(report.added, vec!["poi:1"]);
+//! assert_eq!(report.updated, vec!["poi:1"]);
+//! assert_eq!(report.rejected.len(), 1);
+//! assert_eq!(model.pois["poi:1"].properties["capacity"], "120");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Coord, Lat, Lon, Model, Poi, PoiType, Result, Weight};
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// A row of a change file
+#[derive(Debug, Deserialize)]
+struct ChangeRecord {
+    action: String,
+    poi_id: String,
+    #[serde(default)]
+    field: String,
+    #[serde(default)]
+    value: String,
+}
+
+/// A change of a change file that has not been applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedChange {
+    /// Line (1-based) of the change in the file, when known
+    pub line: Option<u64>,
+
+    /// Id of the POI changed, empty if the row could not be read
+    pub poi_id: String,
+
+    /// Why the change was rejected
+    pub reason: String,
+}
+
+impl std::fmt::Display for RejectedChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.reason),
+            None => f.write_str(&self.reason),
+        }
+    }
+}
+
+/// What [Model::apply_changes] did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeReport {
+    /// Ids of the POIs added, in the order of the file
+    pub added: Vec<String>,
+
+    /// Ids of the POIs updated, in the order of their first change
+    pub updated: Vec<String>,
+
+    /// Ids of the POIs removed, in the order of the file
+    pub deleted: Vec<String>,
+
+    /// The changes rejected, in the order of the file
+    pub rejected: Vec<RejectedChange>,
+}
+
+/// Records the `update` and `delete` changes into a [ChangeReport]
+#[derive(Default)]
+struct Recorder {
+    report: ChangeReport,
+    /// The POIs of `report.updated`
+    updated: HashSet<String>,
+    /// The POIs removed, along with the line of their deletion
+    deleted: Vec<(Option<u64>, String)>,
+}
+
+impl Recorder {
+    /// Records the `result` of [Model::apply_change], true meaning that
+    /// the POI was removed
+    fn record(&mut self, line: Option<u64>, poi_id: String, result: Result<bool>) {
+        match result {
+            Ok(true) => self.deleted.push((line, poi_id)),
+            Ok(false) => {
+                if !self.updated.contains(&poi_id) {
+                    self.updated.insert(poi_id.clone());
+                    self.report.updated.push(poi_id);
+                }
+            }
+            Err(e) => self.reject(line, poi_id, e),
+        }
+    }
+
+    fn reject(&mut self, line: Option<u64>, poi_id: String, error: anyhow::Error) {
+        self.report.rejected.push(RejectedChange {
+            line,
+            poi_id,
+            reason: error.to_string(),
+        });
+    }
+
+    fn finish(mut self) -> ChangeReport {
+        self.deleted.sort_by_key(|(line, _)| *line);
+        self.report.deleted = self.deleted.into_iter().map(|(_, id)| id).collect();
+        self.report.rejected.sort_by_key(|rejected| rejected.line);
+        self.report
+    }
+}
+
+impl Model {
+    /// Applies the changes of a change file (see the [module
+    /// documentation](crate::changes)) read from `reader`, and reports
+    /// them. The POIs are added first, then the other changes are applied
+    /// in the order of the file. The `created_at` and `updated_at` of the
+    /// POIs added, and the `updated_at` of the POIs updated, are set to the
+    /// current time.
+    ///
+    /// Fails only if the file cannot be read, or lacks a column. The POI
+    /// types are left as they are, even when no POI references them any
+    /// longer.
+    pub fn apply_changes<R: std::io::Read>(&mut self, reader: R) -> Result<ChangeReport> {
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .delimiter(b';')
+            .trim(csv::Trim::All)
+            .from_reader(reader);
+        let headers = csv_reader.headers()?.clone();
+        for column in ["action", "poi_id"].iter() {
+            if !headers.iter().any(|header| header == *column) {
+                bail!("missing column '{}' in the change file", column);
+            }
+        }
+
+        let mut recorder = Recorder::default();
+        let mut additions: BTreeMap<String, Vec<(Option<u64>, ChangeRecord)>> = BTreeMap::new();
+        let mut added_order = Vec::new();
+        let mut others = Vec::new();
+        for row in csv_reader.records() {
+            let line = match &row {
+                Ok(row) => row.position().map(csv::Position::line),
+                Err(e) => e.position().map(csv::Position::line),
+            };
+            let record = match row.and_then(|row| row.deserialize::<ChangeRecord>(Some(&headers))) {
+                Ok(record) => record,
+                Err(e) => {
+                    recorder.reject(line, String::new(), e.into());
+                    continue;
+                }
+            };
+            if record.action.eq_ignore_ascii_case("add") {
+                if !additions.contains_key(&record.poi_id) {
+                    added_order.push(record.poi_id.clone());
+                }
+                additions
+                    .entry(record.poi_id.clone())
+                    .or_default()
+                    .push((line, record));
+            } else {
+                others.push((line, record));
+            }
+        }
+
+        // a POI deleted before its `add` rows is replaced, so its deletion
+        // goes before the additions
+        let (replaced, others): (Vec<_>, Vec<_>) =
+            others.into_iter().partition(|(line, record)| {
+                record.action.eq_ignore_ascii_case("delete")
+                    && record.field.is_empty()
+                    && additions
+                        .get(&record.poi_id)
+                        .and_then(|rows| rows.first())
+                        .is_some_and(|(first, _)| first > line)
+            });
+        let now = Utc::now();
+        for (line, record) in replaced {
+            let result = self.apply_change(&record, now);
+            recorder.record(line, record.poi_id, result);
+        }
+
+        for id in added_order {
+            let rows = additions.remove(&id).unwrap_or_default();
+            let line = rows.first().and_then(|(line, _)| *line);
+            match self.build_poi(&id, &rows) {
+                Ok(mut poi) => {
+                    poi.created_at = Some(now);
+                    poi.updated_at = Some(now);
+                    self.pois.insert(id.clone(), poi);
+                    recorder.report.added.push(id);
+                }
+                Err(e) => recorder.reject(line, id, e),
+            }
+        }
+
+        for (line, record) in others {
+            let result = self.apply_change(&record, now);
+            recorder.record(line, record.poi_id, result);
+        }
+        if !recorder.deleted.is_empty() {
+            self.drop_dangling_relations();
+        }
+        Ok(recorder.finish())
+    }
+
+    /// Applies an `update` or a `delete` row, and returns true if the whole
+    /// POI was removed
+    fn apply_change(&mut self, record: &ChangeRecord, now: DateTime<Utc>) -> Result<bool> {
+        match record.action.to_lowercase().as_str() {
+            "update" => {
+                self.update_field(record)?;
+                if let Some(poi) = self.pois.get_mut(&record.poi_id) {
+                    poi.updated_at = Some(now);
+                }
+                Ok(false)
+            }
+            "delete" => self.delete(record),
+            action => Err(anyhow!("unknown action '{}'", action)),
+        }
+    }
+
+    /// The new POI `id`, with the fields of the `add` rows
+    fn build_poi(&self, id: &str, rows: &[(Option<u64>, ChangeRecord)]) -> Result<Poi> {
+        if self.pois.contains_key(id) {
+            bail!("poi '{}' already exists", id);
+        }
+        let mut poi = Poi {
+            id: id.to_string(),
+            ..Poi::default()
+        };
+        let (mut lat, mut lon) = (None, None);
+        for (_, record) in rows {
+            match record.field.as_str() {
+                "poi_lat" => lat = Some(parse_lat(&record.value)?),
+                "poi_lon" => lon = Some(parse_lon(&record.value)?),
+                field => set_field(&mut poi, field, &record.value, &self.poi_types)?,
+            }
+        }
+        let missing = [
+            ("poi_name", poi.name.is_empty()),
+            ("poi_type_id", poi.poi_type_id.is_empty()),
+            ("poi_lat", lat.is_none()),
+            ("poi_lon", lon.is_none()),
+        ];
+        if let Some((field, _)) = missing.iter().find(|(_, missing)| *missing) {
+            bail!("field '{}' of the new poi '{}' is missing", field, id);
+        }
+        poi.coord = Coord::new(lon.unwrap_or_default(), lat.unwrap_or_default());
+        Ok(poi)
+    }
+
+    /// Applies an `update` row
+    fn update_field(&mut self, record: &ChangeRecord) -> Result<()> {
+        let poi = self
+            .pois
+            .get_mut(&record.poi_id)
+            .ok_or_else(|| anyhow!("unknown poi '{}'", record.poi_id))?;
+        match record.field.as_str() {
+            "poi_lat" => {
+                poi.coord = Coord::new(poi.coord.lon(), parse_lat(&record.value)?);
+                Ok(())
+            }
+            "poi_lon" => {
+                poi.coord = Coord::new(parse_lon(&record.value)?, poi.coord.lat());
+                Ok(())
+            }
+            field => set_field(poi, field, &record.value, &self.poi_types),
+        }
+    }
+
+    /// Applies a `delete` row, and returns true if the whole POI was
+    /// removed, false if only one of its properties. The relations of the
+    /// removed POIs are left to [Model::apply_changes], to be dropped at
+    /// once.
+    fn delete(&mut self, record: &ChangeRecord) -> Result<bool> {
+        if record.field.is_empty() {
+            self.pois
+                .remove(&record.poi_id)
+                .ok_or_else(|| anyhow!("unknown poi '{}'", record.poi_id))?;
+            return Ok(true);
+        }
+        let poi = self
+            .pois
+            .get_mut(&record.poi_id)
+            .ok_or_else(|| anyhow!("unknown poi '{}'", record.poi_id))?;
+        poi.properties
+            .remove(&record.field)
+            .ok_or_else(|| anyhow!("poi '{}' has no property '{}'", record.poi_id, record.field))?;
+        Ok(false)
+    }
+}
+
+/// Sets a field of `poi` other than its coordinates
+fn set_field(
+    poi: &mut Poi,
+    field: &str,
+    value: &str,
+    poi_types: &HashMap<String, PoiType>,
+) -> Result<()> {
+    match field {
+        "" => bail!("no field given"),
+        "poi_id" => bail!("the id of a poi cannot be changed"),
+        "poi_name" if value.is_empty() => bail!("the name of a poi cannot be empty"),
+        "poi_name" => poi.name = value.to_string(),
+        "poi_type_id" if !poi_types.contains_key(value) => {
+            bail!("unknown poi type '{}'", value)
+        }
+        "poi_type_id" => poi.poi_type_id = value.to_string(),
+        "poi_weight" => {
+            poi.weight = Weight(
+                value
+                    .parse()
+                    .map_err(|_| anyhow!("invalid weight '{}'", value))?,
+            )
+        }
+        "poi_visible" => {
+            poi.visible = crate::io::parse_bool(value)
+                .ok_or_else(|| anyhow!("invalid visibility '{}'", value))?
+        }
+        key if value.is_empty() => {
+            poi.properties.remove(key);
+        }
+        key => {
+            poi.properties.insert(key.to_string(), value.to_string());
+        }
+    }
+    Ok(())
+}
+
+fn parse_lat(value: &str) -> Result<f64> {
+    let lat = value
+        .parse()
+        .map_err(|_| anyhow!("invalid latitude '{}'", value))?;
+    Ok(Lat::new(lat)?.value())
+}
+
+fn parse_lon(value: &str) -> Result<f64> {
+    let lon = value
+        .parse()
+        .map_err(|_| anyhow!("invalid longitude '{}'", value))?;
+    Ok(Lon::new(lon)?.value())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Model, Poi, PoiType};
+
+    /// A model with the type `amenity:parking` and the POIs `poi:1` and
+    /// `poi:2`, the latter being a child of the former
+    fn model() -> Model {
+        let mut model = Model::default();
+        model.poi_types.insert(
+            "amenity:parking".to_string(),
+            PoiType {
+                id: "amenity:parking".to_string(),
+                name: "Parking".to_string(),
+                ..PoiType::default()
+            },
+        );
+        for id in &["poi:1", "poi:2"] {
+            model.pois.insert(
+                id.to_string(),
+                Poi {
+                    id: id.to_string(),
+                    name: "Parking".to_string(),
+                    poi_type_id: "amenity:parking".to_string(),
+                    ..Poi::default()
+                },
+            );
+        }
+        model.add_relation("poi:1", "poi:2", "entrance").unwrap();
+        model
+    }
+
+    #[test]
+    fn deleted_pois_lose_their_relations() {
+        let mut model = model();
+        let changes = "action;poi_id;field;value\ndelete;poi:2;;\n";
+        let report = model.apply_changes(changes.as_bytes()).unwrap();
+        assert_eq!(report.deleted, vec!["poi:2"]);
+        assert!(model.relations.is_empty());
+        assert!(model.poi_types.contains_key("amenity:parking"));
+    }
+
+    /// Applies `rows` after the header of a change file, and returns the
+    /// line and the reason of each rejected change
+    fn rejected(model: &mut Model, rows: &str) -> Vec<(Option<u64>, String)> {
+        let changes = format!("action;poi_id;field;value\n{}", rows);
+        model
+            .apply_changes(changes.as_bytes())
+            .unwrap()
+            .rejected
+            .into_iter()
+            .map(|rejected| (rejected.line, rejected.reason))
+            .collect()
+    }
+
+    #[test]
+    fn changes_to_unknown_pois_are_rejected() {
+        let mut model = model();
+        let rejected = rejected(
+            &mut model,
+            "update;poi:3;poi_name;Gare\ndelete;poi:3;;\ndelete;poi:1;capacity;\n",
+        );
+        assert_eq!(
+            rejected,
+            vec![
+                (Some(2), "unknown poi 'poi:3'".to_string()),
+                (Some(3), "unknown poi 'poi:3'".to_string()),
+                (
+                    Some(4),
+                    "poi 'poi:1' has no property 'capacity'".to_string()
+                ),
+            ]
+        );
+        assert_eq!(model, self::model());
+    }
+
+    #[test]
+    fn changes_to_unknown_poi_types_are_rejected() {
+        let mut model = model();
+        let rejected = rejected(&mut model, "update;poi:1;poi_type_id;amenity:bench\n");
+        assert_eq!(
+            rejected,
+            vec![(Some(2), "unknown poi type 'amenity:bench'".to_string())]
+        );
+        assert_eq!(model.pois["poi:1"].poi_type_id, "amenity:parking");
+    }
+
+    #[test]
+    fn invalid_coordinates_are_rejected() {
+        let mut model = model();
+        let rejected = rejected(
+            &mut model,
+            "update;poi:1;poi_lat;91\nupdate;poi:1;poi_lon;east\n",
+        );
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(rejected[0].0, Some(2));
+        assert_eq!(
+            rejected[1],
+            (Some(3), "invalid longitude 'east'".to_string())
+        );
+        assert_eq!(model.pois["poi:1"].coord, self::model().pois["poi:1"].coord);
+    }
+
+    #[test]
+    fn additions_missing_a_required_field_are_rejected() {
+        let mut model = model();
+        let rejected = rejected(
+            &mut model,
+            "add;poi:3;poi_name;Gare\n\
+             add;poi:3;poi_type_id;amenity:parking\n\
+             add;poi:3;poi_lat;48.84\n",
+        );
+        assert_eq!(
+            rejected,
+            vec![(
+                Some(2),
+                "field 'poi_lon' of the new poi 'poi:3' is missing".to_string()
+            )]
+        );
+        assert!(!model.pois.contains_key("poi:3"));
+    }
+
+    #[test]
+    fn unknown_actions_are_rejected() {
+        let mut model = model();
+        let rejected = rejected(&mut model, "move;poi:1;;\n");
+        assert_eq!(
+            rejected,
+            vec![(Some(2), "unknown action 'move'".to_string())]
+        );
+        assert_eq!(model, self::model());
+    }
+
+    #[test]
+    fn pois_deleted_then_added_are_replaced() {
+        let mut model = model();
+        let changes = "action;poi_id;field;value
+delete;poi:1;;
+add;poi:1;poi_name;Parking Gare
+add;poi:1;poi_type_id;amenity:parking
+add;poi:1;poi_lat;48.84
+add;poi:1;poi_lon;2.37
+update;poi:1;capacity;120
+delete;poi:2;;
+";
+        let report = model.apply_changes(changes.as_bytes()).unwrap();
+        assert_eq!(report.deleted, vec!["poi:1", "poi:2"]);
+        assert_eq!(report.added, vec!["poi:1"]);
+        assert_eq!(report.updated, vec!["poi:1"]);
+        assert!(report.rejected.is_empty());
+        let poi = &model.pois["poi:1"];
+        assert_eq!(poi.name, "Parking Gare");
+        assert_eq!(poi.properties["capacity"], "120");
+        assert!(!model.pois.contains_key("poi:2"));
+    }
+
+    #[test]
+    fn pois_added_then_deleted_are_removed() {
+        let mut model = model();
+        let changes = "action;poi_id;field;value
+add;poi:3;poi_name;Parking Gare
+add;poi:3;poi_type_id;amenity:parking
+add;poi:3;poi_lat;48.84
+add;poi:3;poi_lon;2.37
+delete;poi:3;;
+";
+        let report = model.apply_changes(changes.as_bytes()).unwrap();
+        assert_eq!(report.added, vec!["poi:3"]);
+        assert_eq!(report.deleted, vec!["poi:3"]);
+        assert_eq!(model, self::model());
+    }
+}
@@ -0,0 +1,235 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! A model storing its POIs compactly, behind the `compact` feature
+//!
+//! The ids, names, POI type ids, property keys and property values of the
+//! POIs of a [CompactModel] are stored as [CompactString]s: a string of at
+//! most 24 bytes is kept inline, in the 24 bytes of a `String`, instead of
+//! in an allocation of its own. Most of these strings are that short, so
+//! a large model takes far fewer allocations, and less memory. The
+//! properties of a POI are stored in a single boxed slice rather than in a
+//! map.
+//!
+//! The POIs are read back as [Poi]s, built on each access: the model is
+//! meant to hold a large dataset in memory, not to be changed in place. A
+//! [Model] is converted to a [CompactModel] and back with [From].
+//!
+//! ```
+//! use navitia_poi_model::compact::CompactModel;
+//! use navitia_poi_model::{Model, Poi};
+//!
+//! let model: Model = vec![Poi { id: "poi:1".into(), name: "Gare".into(), ..Poi::default() }]
+//!     .into_iter()
+//!     .collect();
+//! let compact = CompactModel::from(model.clone());
+//! assert_eq!(compact.get_poi("poi:1").unwrap().name, "Gare");
+//! assert_eq!(Model::from(compact), model);
+//! ```
+
+use crate::accessibility::Accessibility;
+use crate::{Coord, Metadata, Model, Poi, PoiRelation, PoiType, Weight};
+use chrono::{DateTime, Utc};
+use compact_str::CompactString;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A [Model] whose POIs are stored compactly, see the [module
+/// documentation](crate::compact)
+#[derive(Debug, Clone, Default)]
+pub struct CompactModel {
+    pois: BTreeMap<CompactString, CompactPoi>,
+    poi_types: HashMap<String, PoiType>,
+    metadata: Option<Metadata>,
+    relations: BTreeSet<PoiRelation>,
+}
+
+/// A [Poi] without its id, which is the key of the map of the POIs
+#[derive(Debug, Clone)]
+struct CompactPoi {
+    name: CompactString,
+    coord: Coord,
+    poi_type_id: CompactString,
+    /// Ordered by key
+    properties: Box<[(CompactString, CompactString)]>,
+    visible: bool,
+    weight: Weight,
+    accessibility: Accessibility,
+    elevation_m: Option<f64>,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl CompactPoi {
+    fn to_poi(&self, id: &str) -> Poi {
+        Poi {
+            id: id.to_string(),
+            name: self.name.to_string(),
+            coord: self.coord.clone(),
+            poi_type_id: self.poi_type_id.to_string(),
+            properties: self
+                .properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            visible: self.visible,
+            weight: self.weight,
+            accessibility: self.accessibility,
+            elevation_m: self.elevation_m,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+impl From<Poi> for CompactPoi {
+    fn from(poi: Poi) -> CompactPoi {
+        CompactPoi {
+            name: poi.name.into(),
+            coord: poi.coord,
+            poi_type_id: poi.poi_type_id.into(),
+            properties: poi
+                .properties
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+            visible: poi.visible,
+            weight: poi.weight,
+            accessibility: poi.accessibility,
+            elevation_m: poi.elevation_m,
+            created_at: poi.created_at,
+            updated_at: poi.updated_at,
+        }
+    }
+}
+
+impl CompactModel {
+    /// Returns the number of POIs
+    pub fn len(&self) -> usize {
+        self.pois.len()
+    }
+
+    /// Returns true if there is no POI
+    pub fn is_empty(&self) -> bool {
+        self.pois.is_empty()
+    }
+
+    /// Returns an iterator over the POIs, ordered by id. Each POI is built
+    /// when reached.
+    pub fn pois(&self) -> impl Iterator<Item = Poi> + '_ {
+        self.pois.iter().map(|(id, poi)| poi.to_poi(id))
+    }
+
+    /// Returns an iterator over the POI types, in no particular order
+    pub fn poi_types(&self) -> impl Iterator<Item = &PoiType> {
+        self.poi_types.values()
+    }
+
+    /// Returns an iterator over the relations, ordered by parent
+    pub fn relations(&self) -> impl Iterator<Item = &PoiRelation> {
+        self.relations.iter()
+    }
+
+    /// Returns the information about the dataset, if any
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Returns the POI with the given id, built from its compact storage
+    pub fn get_poi(&self, id: &str) -> Option<Poi> {
+        self.pois.get(id).map(|poi| poi.to_poi(id))
+    }
+
+    /// Returns the POI type with the given id
+    pub fn get_poi_type(&self, id: &str) -> Option<&PoiType> {
+        self.poi_types.get(id)
+    }
+
+    /// Inserts `poi`, and returns the POI it replaces, if any
+    pub fn insert_poi(&mut self, mut poi: Poi) -> Option<Poi> {
+        let id = CompactString::from(std::mem::take(&mut poi.id));
+        let replaced = self.pois.insert(id.clone(), CompactPoi::from(poi));
+        replaced.map(|poi| poi.to_poi(&id))
+    }
+}
+
+impl From<Model> for CompactModel {
+    fn from(model: Model) -> CompactModel {
+        CompactModel {
+            pois: model
+                .pois
+                .into_iter()
+                .map(|(id, poi)| (CompactString::from(id), CompactPoi::from(poi)))
+                .collect(),
+            poi_types: model.poi_types,
+            metadata: model.metadata,
+            relations: model.relations,
+        }
+    }
+}
+
+impl From<CompactModel> for Model {
+    fn from(model: CompactModel) -> Model {
+        Model {
+            pois: model
+                .pois
+                .iter()
+                .map(|(id, poi)| (id.to_string(), poi.to_poi(id)))
+                .collect(),
+            poi_types: model.poi_types,
+            metadata: model.metadata,
+            relations: model.relations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompactModel;
+    use crate::{Model, Poi};
+
+    #[test]
+    fn short_strings_are_inline() {
+        let poi = Poi {
+            id: "poi:1".to_string(),
+            name: "Gare de Lyon".to_string(),
+            poi_type_id: "amenity:railway_station".to_string(),
+            properties: vec![
+                ("operator".to_string(), "SNCF".to_string()),
+                (
+                    "description".to_string(),
+                    "A longer value, well over the 24 bytes kept inline".to_string(),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+            ..Poi::default()
+        };
+        let model: Model = vec![poi.clone()].into_iter().collect();
+        let compact = CompactModel::from(model.clone());
+
+        let (id, stored) = compact.pois.iter().next().unwrap();
+        assert!(!id.is_heap_allocated());
+        assert!(!stored.name.is_heap_allocated());
+        assert!(!stored.poi_type_id.is_heap_allocated());
+        let heap: Vec<bool> = stored
+            .properties
+            .iter()
+            .map(|(_, value)| value.is_heap_allocated())
+            .collect();
+        assert_eq!(heap, vec![true, false]);
+
+        assert_eq!(compact.get_poi("poi:1"), Some(poi));
+        assert_eq!(Model::from(compact), model);
+    }
+}
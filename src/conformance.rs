@@ -0,0 +1,349 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Conformance of a model to the POI chapter of the NTFS specification
+//!
+//! [Model::check_ntfs_conformance] checks what an archive written from the
+//! model would contain against a version of the chapter: the required
+//! values, the format of the ids, the references between files, and the
+//! columns and files unknown to that version. The columns and files are
+//! the ones the writer emits, that is the optional ones only when a value
+//! needs them.
+//!
+//! ```
+//! use navitia_poi_model::conformance::{Rule, SpecVersion};
+//! use navitia_poi_model::Model;
+//!
+//! let report = Model::default().check_ntfs_conformance(SpecVersion::V1);
+//! assert!(!report.is_conformant());
+//! assert!(report.issues.iter().all(|issue| issue.rule == Rule::MissingPoiType));
+//! ```
+
+use crate::io::{OptionalPoiColumns, PoiTypeColumns};
+use crate::Model;
+
+/// POI types that every dataset must define, for the bike sharing stations
+/// and the car parks
+pub const REQUIRED_POI_TYPES: [&str; 2] = ["amenity:bicycle_rental", "amenity:parking"];
+
+/// A version of the POI chapter of the NTFS specification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpecVersion {
+    /// The original chapter: `poi.txt` (`poi_id`, `poi_name`, `poi_type_id`,
+    /// `poi_lat`, `poi_lon`, `poi_weight`, `poi_visible`), `poi_type.txt`
+    /// (`poi_type_id`, `poi_type_name`) and `poi_properties.txt` (`poi_id`,
+    /// `key`, `value`)
+    V1,
+}
+
+/// How serious a [ConformanceIssue] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Readers of the version would ignore some data
+    Warning,
+
+    /// The data does not conform to the version
+    Error,
+}
+
+/// The rule of the specification broken by a [ConformanceIssue]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rule {
+    /// A required value is empty
+    MissingValue,
+
+    /// An id is empty, has leading or trailing spaces, or control
+    /// characters
+    InvalidId,
+
+    /// A value is out of its range
+    InvalidValue,
+
+    /// An id refers to an object that does not exist
+    UnknownReference,
+
+    /// One of the [REQUIRED_POI_TYPES] is not defined
+    MissingPoiType,
+
+    /// A column that the version does not know would be written
+    UnsupportedColumn,
+
+    /// A file that the version does not know would be written
+    UnsupportedFile,
+}
+
+/// A breach of the specification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceIssue {
+    /// How serious the issue is
+    pub severity: Severity,
+
+    /// The rule broken
+    pub rule: Rule,
+
+    /// The file of the archive concerned
+    pub file: &'static str,
+
+    /// The column concerned, if any
+    pub column: Option<&'static str>,
+
+    /// Id of the POI or of the POI type concerned, if any
+    pub id: Option<String>,
+
+    /// Description of the issue
+    pub message: String,
+}
+
+impl std::fmt::Display for ConformanceIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(column) = self.column {
+            write!(f, " ({})", column)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// The issues found by [Model::check_ntfs_conformance]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceReport {
+    /// Version checked against
+    pub version: SpecVersion,
+
+    /// Issues found, ordered by file, then by id
+    pub issues: Vec<ConformanceIssue>,
+}
+
+impl ConformanceReport {
+    /// Returns true if no issue is an [error](Severity::Error)
+    pub fn is_conformant(&self) -> bool {
+        self.issues
+            .iter()
+            .all(|issue| issue.severity != Severity::Error)
+    }
+
+    fn push(
+        &mut self,
+        severity: Severity,
+        rule: Rule,
+        (file, column): (&'static str, Option<&'static str>),
+        id: Option<&str>,
+        message: String,
+    ) {
+        self.issues.push(ConformanceIssue {
+            severity,
+            rule,
+            file,
+            column,
+            id: id.map(str::to_string),
+            message,
+        });
+    }
+
+    /// Reports an error if `id` is not well formed
+    fn check_id(&mut self, location: (&'static str, Option<&'static str>), id: &str) {
+        if !is_valid_id(id) {
+            let message = format!("invalid id {:?}", id);
+            self.push(
+                Severity::Error,
+                Rule::InvalidId,
+                location,
+                Some(id),
+                message,
+            );
+        }
+    }
+
+    /// Reports once a column unknown to the version that would be written
+    fn unsupported_column(&mut self, file: &'static str, column: &'static str) {
+        let message = format!("column '{}' is not part of the version", column);
+        self.push(
+            Severity::Warning,
+            Rule::UnsupportedColumn,
+            (file, Some(column)),
+            None,
+            message,
+        );
+    }
+}
+
+/// Ids are not empty, without leading or trailing spaces, and without
+/// control characters
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.trim() == id && !id.chars().any(char::is_control)
+}
+
+impl Model {
+    /// Checks the model against the `version` of the POI chapter of the
+    /// NTFS specification, see the [module documentation](crate::conformance)
+    pub fn check_ntfs_conformance(&self, version: SpecVersion) -> ConformanceReport {
+        let mut report = ConformanceReport {
+            version,
+            issues: Vec::new(),
+        };
+
+        for poi in self.pois.values() {
+            let id = Some(poi.id.as_str());
+            report.check_id(("poi.txt", Some("poi_id")), &poi.id);
+            if poi.name.trim().is_empty() {
+                let message = format!("poi '{}' has no name", poi.id);
+                let location = ("poi.txt", Some("poi_name"));
+                report.push(Severity::Error, Rule::MissingValue, location, id, message);
+            }
+            if !self.poi_types.contains_key(&poi.poi_type_id) {
+                let message = format!("poi '{}' is of unknown type '{}'", poi.id, poi.poi_type_id);
+                let location = ("poi.txt", Some("poi_type_id"));
+                report.push(
+                    Severity::Error,
+                    Rule::UnknownReference,
+                    location,
+                    id,
+                    message,
+                );
+            }
+            if !poi.coord.is_valid() {
+                let message = format!(
+                    "poi '{}' has invalid coordinates (lon: {}, lat: {})",
+                    poi.id,
+                    poi.coord.lon(),
+                    poi.coord.lat()
+                );
+                let location = ("poi.txt", Some("poi_lat"));
+                report.push(Severity::Error, Rule::InvalidValue, location, id, message);
+            }
+            for key in poi.properties.keys().filter(|key| key.trim().is_empty()) {
+                let message = format!("poi '{}' has a property without key {:?}", poi.id, key);
+                let location = ("poi_properties.txt", Some("key"));
+                report.push(Severity::Error, Rule::MissingValue, location, id, message);
+            }
+        }
+
+        for poi_type in self.poi_types.values() {
+            let id = Some(poi_type.id.as_str());
+            report.check_id(("poi_type.txt", Some("poi_type_id")), &poi_type.id);
+            if poi_type.name.trim().is_empty() {
+                let message = format!("poi type '{}' has no name", poi_type.id);
+                let location = ("poi_type.txt", Some("poi_type_name"));
+                report.push(Severity::Error, Rule::MissingValue, location, id, message);
+            }
+        }
+        for required in REQUIRED_POI_TYPES.iter() {
+            if !self.poi_types.contains_key(*required) {
+                let message = format!("required poi type '{}' is missing", required);
+                let location = ("poi_type.txt", Some("poi_type_id"));
+                report.push(
+                    Severity::Error,
+                    Rule::MissingPoiType,
+                    location,
+                    Some(*required),
+                    message,
+                );
+            }
+        }
+
+        // every optional column and file is an extension of V1
+        let SpecVersion::V1 = version;
+        for column in OptionalPoiColumns::of(self.pois.values()).names() {
+            report.unsupported_column("poi.txt", column);
+        }
+        for column in PoiTypeColumns::of(self.poi_types.values()).names() {
+            report.unsupported_column("poi_type.txt", column);
+        }
+        let files = [
+            (
+                "poi_type_translations.txt",
+                self.poi_types
+                    .values()
+                    .any(|pt| !pt.translations.is_empty()),
+            ),
+            ("poi_relations.txt", !self.relations.is_empty()),
+        ];
+        for (file, _) in files.iter().filter(|(_, written)| *written) {
+            let message = format!("file '{}' is not part of the version", file);
+            let location = (*file, None);
+            report.push(
+                Severity::Warning,
+                Rule::UnsupportedFile,
+                location,
+                None,
+                message,
+            );
+        }
+
+        report
+            .issues
+            .sort_by(|lhs, rhs| (lhs.file, &lhs.id).cmp(&(rhs.file, &rhs.id)));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rule, SpecVersion};
+    use crate::io::{write_model_to_writer, WriteOptions};
+    use crate::{Model, Poi, PoiType};
+    use std::io::Read;
+
+    /// The header of `file` in the archive written from `model`
+    fn header(model: &Model, file: &str) -> Vec<String> {
+        let mut archive = Vec::new();
+        write_model_to_writer(model, &mut archive, &WriteOptions::default()).unwrap();
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive)).unwrap();
+        let mut content = String::new();
+        zip.by_name(file)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        let line = content.lines().next().unwrap();
+        line.split(';').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn unsupported_columns_are_the_written_ones() {
+        let mut model: Model = vec![
+            Poi {
+                id: "poi:1".to_string(),
+                elevation_m: Some(12.),
+                ..Poi::default()
+            },
+            Poi {
+                id: "poi:2".to_string(),
+                ..Poi::default()
+            },
+        ]
+        .into_iter()
+        .collect();
+        model.poi_types.insert(
+            "amenity:parking".to_string(),
+            PoiType {
+                id: "amenity:parking".to_string(),
+                name: "Parking".to_string(),
+                icon: Some("parking".to_string()),
+                ..PoiType::default()
+            },
+        );
+        let report = model.check_ntfs_conformance(SpecVersion::V1);
+        for file in ["poi.txt", "poi_type.txt"] {
+            let unsupported: Vec<&str> = report
+                .issues
+                .iter()
+                .filter(|issue| issue.file == file && issue.rule == Rule::UnsupportedColumn)
+                .filter_map(|issue| issue.column)
+                .collect();
+            let header = header(&model, file);
+            let base = if file == "poi.txt" { 7 } else { 2 };
+            assert_eq!(unsupported, header[base..]);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Constraints on the values of the properties, like a maximal length, so
+//! that oversized or binary values do not reach the consumers of the data
+//!
+//! The constraints are enforced on load with
+//! [LoadOptions::property_constraints](crate::io::LoadOptions::property_constraints),
+//! on save with
+//! [WriteOptions::property_constraints](crate::io::WriteOptions::property_constraints),
+//! and checked with [Model::property_violations].
+//!
+//! ```
+//! use navitia_poi_model::constraints::{Charset, PropertyConstraints, ValueConstraint};
+//!
+//! let mut constraints = PropertyConstraints {
+//!     default: ValueConstraint {
+//!         max_length: Some(256),
+//!         charset: Charset::Printable,
+//!     },
+//!     ..PropertyConstraints::default()
+//! };
+//! constraints.keys.insert(
+//!     "ref".to_string(),
+//!     ValueConstraint {
+//!         max_length: Some(8),
+//!         charset: Charset::Ascii,
+//!     },
+//! );
+//! assert!(constraints.check("ref", "A12").is_none());
+//! assert!(constraints.check("ref", "Bâtiment A").is_some());
+//! assert!(constraints.check("name", "Bâtiment A").is_none());
+//! ```
+
+use crate::trace::Traced;
+use crate::Model;
+use std::collections::HashMap;
+
+/// Characters allowed in a value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Charset {
+    /// Any character
+    #[default]
+    Any,
+
+    /// Any character but the control characters, like line feeds
+    Printable,
+
+    /// The printable ASCII characters, and the space
+    Ascii,
+}
+
+impl Charset {
+    /// Returns true if `c` is allowed
+    pub fn allows(self, c: char) -> bool {
+        match self {
+            Charset::Any => true,
+            Charset::Printable => !c.is_control(),
+            Charset::Ascii => c.is_ascii() && !c.is_ascii_control(),
+        }
+    }
+}
+
+/// Constraint on the values of a property
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValueConstraint {
+    /// Maximal length of the values, in characters, if any
+    pub max_length: Option<usize>,
+
+    /// Characters allowed in the values
+    pub charset: Charset,
+}
+
+impl ValueConstraint {
+    /// Returns how `value` violates the constraint, if it does
+    pub fn check(&self, value: &str) -> Option<Violation> {
+        if let Some(c) = value.chars().find(|c| !self.charset.allows(*c)) {
+            return Some(Violation::ForbiddenCharacter(c));
+        }
+        match self.max_length {
+            Some(max_length) if value.chars().count() > max_length => Some(Violation::TooLong {
+                length: value.chars().count(),
+                max_length,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns `value` without its forbidden characters, truncated to the
+    /// maximal length
+    pub fn fix(&self, value: &str) -> String {
+        value
+            .chars()
+            .filter(|c| self.charset.allows(*c))
+            .take(self.max_length.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// How a value violates its constraint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// The value is longer than allowed
+    TooLong {
+        /// Length of the value, in characters
+        length: usize,
+
+        /// Maximal length of the values, in characters
+        max_length: usize,
+    },
+
+    /// The value contains a character that is not allowed
+    ForbiddenCharacter(char),
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::TooLong { length, max_length } => write!(
+                f,
+                "value of {} characters, longer than {}",
+                length, max_length
+            ),
+            Violation::ForbiddenCharacter(c) => write!(f, "forbidden character {:?}", c),
+        }
+    }
+}
+
+/// What to do with the values violating their constraint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ViolationPolicy {
+    /// Keep the value, the violation being reported: as an issue by
+    /// [load_model_lenient](crate::io::load_model_lenient) on load, and by
+    /// [Model::property_violations] only on save
+    #[default]
+    Report,
+
+    /// Remove the forbidden characters and truncate the value (see
+    /// [ValueConstraint::fix]), the change being reported as an issue by
+    /// [load_model_lenient](crate::io::load_model_lenient) on load
+    Truncate,
+
+    /// Fail the load (or report an issue and skip the property in lenient
+    /// mode) or the save
+    Reject,
+}
+
+/// Constraints on the values of the properties
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PropertyConstraints {
+    /// Constraint on the values of the properties whose key is not in
+    /// `keys`. None by default.
+    pub default: ValueConstraint,
+
+    /// Constraint on the values of the properties, by key
+    pub keys: HashMap<String, ValueConstraint>,
+
+    /// What to do with the values violating their constraint
+    pub policy: ViolationPolicy,
+}
+
+impl PropertyConstraints {
+    /// Returns the constraint on the values of the property `key`
+    pub fn constraint(&self, key: &str) -> &ValueConstraint {
+        self.keys.get(key).unwrap_or(&self.default)
+    }
+
+    /// Returns how `value`, of the property `key`, violates its
+    /// constraint, if it does
+    pub fn check(&self, key: &str, value: &str) -> Option<Violation> {
+        self.constraint(key).check(value)
+    }
+}
+
+impl Model {
+    /// Returns the properties violating `constraints`, as the id of their
+    /// POI, their key and the violation, ordered by POI id and key
+    pub fn property_violations(
+        &self,
+        constraints: &PropertyConstraints,
+    ) -> Vec<(&str, &str, Violation)> {
+        let traced = Traced::start("property_violations", "");
+        let violations: Vec<_> = self
+            .pois
+            .values()
+            .flat_map(|poi| {
+                poi.properties.iter().filter_map(move |(key, value)| {
+                    constraints
+                        .check(key, value)
+                        .map(|violation| (poi.id.as_str(), key.as_str(), violation))
+                })
+            })
+            .collect();
+        traced.finish(violations.len());
+        violations
+    }
+}
@@ -0,0 +1,696 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! A model stored on disk, behind the `disk` feature
+//!
+//! A [DiskModel] keeps its POIs, POI types, relations and metadata in an
+//! embedded [sled] database, and only reads the records a query needs, for
+//! machines that cannot hold the whole model in memory. Its queries mirror
+//! those of [Model], returning owned values read from the database.
+//!
+//! The POIs are indexed by their position on a Z-order curve: the
+//! longitude and latitude are each scaled to 32 bits, and their bits
+//! interleaved into a 64-bit key, followed by the id of the POI. The POIs
+//! of a box have keys between the keys of its corners, so a box query scans
+//! that range of the index only, and skips the parts of the range outside
+//! of the box: when a key falls outside, the scan jumps to the next key of
+//! the curve inside the box, its BIGMIN. POIs without
+//! [valid](crate::Coord::is_valid) coordinates are not indexed.
+//!
+//! The relations are stored twice, keyed by parent then child, and by child
+//! then parent, so the children and the parents of a POI are both read
+//! with a prefix scan.
+//!
+//! ```no_run
+//! # fn main() -> navitia_poi_model::Result<()> {
+//! use navitia_poi_model::disk::DiskModel;
+//! use navitia_poi_model::Model;
+//!
+//! let model = Model::try_from_path("pois.poi")?;
+//! let disk = DiskModel::from_model(&model, "pois.db")?;
+//! drop(model);
+//! let bbox = geo::Rect::new((2.25, 48.8), (2.45, 48.9));
+//! for poi in disk.pois_in_bbox(&bbox)? {
+//!     println!("{}", poi.name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::accessibility::{Access, Accessibility};
+use crate::{Coord, Metadata, Model, Poi, PoiRelation, PoiType, Result, Weight};
+use chrono::{DateTime, Utc};
+use geo::prelude::{BoundingRect, Contains};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Key of the metadata in the default tree of the database
+const METADATA_KEY: &[u8] = b"metadata";
+
+/// Separator of the ids in the keys of the relations
+const SEPARATOR: u8 = 0;
+
+/// A POI as stored in the database
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredPoi {
+    id: String,
+    name: String,
+    /// None when not finite, which JSON cannot represent
+    lon: Option<f64>,
+    lat: Option<f64>,
+    poi_type_id: String,
+    properties: BTreeMap<String, String>,
+    visible: bool,
+    weight: Weight,
+    wheelchair: Option<Access>,
+    visual_aids: Option<Access>,
+    audio_aids: Option<Access>,
+    elevator: Option<Access>,
+    elevation_m: Option<f64>,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<&Poi> for StoredPoi {
+    fn from(poi: &Poi) -> StoredPoi {
+        let finite = |value: f64| Some(value).filter(|value| value.is_finite());
+        StoredPoi {
+            id: poi.id.clone(),
+            name: poi.name.clone(),
+            lon: finite(poi.coord.lon()),
+            lat: finite(poi.coord.lat()),
+            poi_type_id: poi.poi_type_id.clone(),
+            properties: poi.properties.clone(),
+            visible: poi.visible,
+            weight: poi.weight,
+            wheelchair: poi.accessibility.wheelchair,
+            visual_aids: poi.accessibility.visual_aids,
+            audio_aids: poi.accessibility.audio_aids,
+            elevator: poi.accessibility.elevator,
+            elevation_m: poi.elevation_m,
+            created_at: poi.created_at,
+            updated_at: poi.updated_at,
+        }
+    }
+}
+
+impl From<StoredPoi> for Poi {
+    fn from(poi: StoredPoi) -> Poi {
+        Poi {
+            id: poi.id,
+            name: poi.name,
+            coord: Coord::new(poi.lon.unwrap_or(f64::NAN), poi.lat.unwrap_or(f64::NAN)),
+            poi_type_id: poi.poi_type_id,
+            properties: poi.properties,
+            visible: poi.visible,
+            weight: poi.weight,
+            accessibility: Accessibility {
+                wheelchair: poi.wheelchair,
+                visual_aids: poi.visual_aids,
+                audio_aids: poi.audio_aids,
+                elevator: poi.elevator,
+            },
+            elevation_m: poi.elevation_m,
+            created_at: poi.created_at,
+            updated_at: poi.updated_at,
+        }
+    }
+}
+
+/// Spreads the 32 bits of `value` over the even bits of a 64-bit integer
+fn spread(value: u32) -> u64 {
+    let mut x = u64::from(value);
+    x = (x | x << 16) & 0x0000_ffff_0000_ffff;
+    x = (x | x << 8) & 0x00ff_00ff_00ff_00ff;
+    x = (x | x << 4) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | x << 2) & 0x3333_3333_3333_3333;
+    x = (x | x << 1) & 0x5555_5555_5555_5555;
+    x
+}
+
+/// Gathers the even bits of `value` into a 32-bit integer, the inverse of
+/// [spread]
+fn gather(value: u64) -> u32 {
+    let mut x = value & 0x5555_5555_5555_5555;
+    x = (x | x >> 1) & 0x3333_3333_3333_3333;
+    x = (x | x >> 2) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | x >> 4) & 0x00ff_00ff_00ff_00ff;
+    x = (x | x >> 8) & 0x0000_ffff_0000_ffff;
+    x = (x | x >> 16) & 0x0000_0000_ffff_ffff;
+    x as u32
+}
+
+/// Position of (`lon`, `lat`) on the Z-order curve, the coordinates being
+/// clamped to their range
+fn z_order(lon: f64, lat: f64) -> u64 {
+    let scale = |value: f64, min: f64, max: f64| {
+        let ratio = (value.max(min).min(max) - min) / (max - min);
+        (ratio * f64::from(u32::MAX)) as u32
+    };
+    spread(scale(lon, -180., 180.)) | spread(scale(lat, -90., 90.)) << 1
+}
+
+/// Returns true if the position `z` on the Z-order curve lies in the box
+/// whose corners are at `min` and `max`
+fn z_in_box(z: u64, min: u64, max: u64) -> bool {
+    let (x, y) = (gather(z), gather(z >> 1));
+    (gather(min)..=gather(max)).contains(&x) && (gather(min >> 1)..=gather(max >> 1)).contains(&y)
+}
+
+/// The smallest position of the Z-order curve greater than `z` lying in
+/// the box whose corners are at `min` and `max`, `z` being between them but
+/// outside the box (the BIGMIN of Tropf and Herzog)
+fn z_next_in_box(z: u64, mut min: u64, mut max: u64) -> u64 {
+    let mut next = min;
+    for bit in (0..64).rev() {
+        let mask = 1u64 << bit;
+        // the lower bits of the same dimension as `bit`
+        let dimension = if bit % 2 == 0 {
+            0x5555_5555_5555_5555u64
+        } else {
+            0xaaaa_aaaa_aaaa_aaaa
+        };
+        let lower = dimension & (mask - 1);
+        match (z & mask != 0, min & mask != 0, max & mask != 0) {
+            (false, false, true) => {
+                next = (min & !lower) | mask;
+                max = (max & !mask) | lower;
+            }
+            (false, true, true) => return min,
+            (true, false, false) => return next,
+            (true, false, true) => min = (min & !lower) | mask,
+            _ => {}
+        }
+    }
+    next
+}
+
+/// Key of `poi` in the spatial index, if its coordinates are valid
+fn spatial_key(poi: &Poi) -> Option<Vec<u8>> {
+    if !poi.coord.is_valid() {
+        return None;
+    }
+    let mut key = z_order(poi.coord.lon(), poi.coord.lat())
+        .to_be_bytes()
+        .to_vec();
+    key.extend_from_slice(poi.id.as_bytes());
+    Some(key)
+}
+
+/// Key of `relation`, ordered by parent
+fn relation_key(relation: &PoiRelation) -> Vec<u8> {
+    let mut key = relation.parent_id.as_bytes().to_vec();
+    key.push(SEPARATOR);
+    key.extend_from_slice(relation.child_id.as_bytes());
+    key.push(SEPARATOR);
+    key.extend_from_slice(relation.relation_type.as_bytes());
+    key
+}
+
+/// Key of `relation` in the tree of the parents, ordered by child
+fn parent_key(relation: &PoiRelation) -> Vec<u8> {
+    relation_key(&PoiRelation {
+        parent_id: relation.child_id.clone(),
+        child_id: relation.parent_id.clone(),
+        relation_type: relation.relation_type.clone(),
+    })
+}
+
+/// The relation of the key built by [parent_key]
+fn parse_parent_key(key: &[u8]) -> Result<PoiRelation> {
+    let relation = parse_relation_key(key)?;
+    Ok(PoiRelation {
+        parent_id: relation.child_id,
+        child_id: relation.parent_id,
+        relation_type: relation.relation_type,
+    })
+}
+
+/// The relation of the key built by [relation_key]
+fn parse_relation_key(key: &[u8]) -> Result<PoiRelation> {
+    let mut parts = key.split(|byte| *byte == SEPARATOR);
+    let mut next = || -> Result<String> {
+        let part = parts
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("invalid relation key {:?}", key))?;
+        Ok(String::from_utf8(part.to_vec())?)
+    };
+    Ok(PoiRelation {
+        parent_id: next()?,
+        child_id: next()?,
+        relation_type: next()?,
+    })
+}
+
+/// A model stored in a [sled] database, see the [module
+/// documentation](crate::disk)
+///
+/// Cloning a [DiskModel] is cheap, and all the clones share the same
+/// database.
+#[derive(Debug, Clone)]
+pub struct DiskModel {
+    db: sled::Db,
+    pois: sled::Tree,
+    poi_types: sled::Tree,
+    relations: sled::Tree,
+    /// The relations, keyed by child
+    parents: sled::Tree,
+    spatial: sled::Tree,
+}
+
+impl DiskModel {
+    /// Opens the database in the directory `path`, creating it if needed
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<DiskModel> {
+        let db = sled::open(path)?;
+        let disk = DiskModel {
+            pois: db.open_tree("pois")?,
+            poi_types: db.open_tree("poi_types")?,
+            relations: db.open_tree("relations")?,
+            parents: db.open_tree("parents")?,
+            spatial: db.open_tree("spatial")?,
+            db,
+        };
+        // the databases written before the tree of the parents lack it
+        if disk.parents.is_empty() {
+            for relation in disk.relations() {
+                disk.parents.insert(parent_key(&relation?), Vec::new())?;
+            }
+        }
+        Ok(disk)
+    }
+
+    /// Stores `model` in the database in the directory `path`, replacing
+    /// its content, and flushes it to disk
+    pub fn from_model<P: AsRef<Path>>(model: &Model, path: P) -> Result<DiskModel> {
+        let disk = DiskModel::open(path)?;
+        disk.clear()?;
+        for poi_type in model.poi_types.values() {
+            disk.insert_poi_type(poi_type)?;
+        }
+        for poi in model.pois.values() {
+            disk.insert_poi(poi)?;
+        }
+        for relation in &model.relations {
+            disk.insert_relation(relation)?;
+        }
+        disk.set_metadata(model.metadata.as_ref())?;
+        disk.flush()?;
+        Ok(disk)
+    }
+
+    /// Reads the whole model back in memory
+    pub fn to_model(&self) -> Result<Model> {
+        Ok(Model {
+            pois: self
+                .pois()
+                .map(|poi| poi.map(|poi| (poi.id.clone(), poi)))
+                .collect::<Result<_>>()?,
+            poi_types: self
+                .poi_types()
+                .map(|poi_type| poi_type.map(|poi_type| (poi_type.id.clone(), poi_type)))
+                .collect::<Result<_>>()?,
+            metadata: self.metadata()?,
+            relations: self.relations().collect::<Result<_>>()?,
+        })
+    }
+
+    /// Removes everything from the database
+    pub fn clear(&self) -> Result<()> {
+        let trees = [
+            &self.pois,
+            &self.poi_types,
+            &self.relations,
+            &self.parents,
+            &self.spatial,
+        ];
+        for tree in &trees {
+            tree.clear()?;
+        }
+        self.db.remove(METADATA_KEY)?;
+        Ok(())
+    }
+
+    /// Writes the changes to disk, which is otherwise done periodically
+    pub fn flush(&self) -> Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Returns the number of POIs
+    pub fn len(&self) -> usize {
+        self.pois.len()
+    }
+
+    /// Returns true if there is no POI
+    pub fn is_empty(&self) -> bool {
+        self.pois.is_empty()
+    }
+
+    /// Inserts `poi`, replacing the POI with the same id
+    pub fn insert_poi(&self, poi: &Poi) -> Result<()> {
+        let value = serde_json::to_vec(&StoredPoi::from(poi))?;
+        if let Some(previous) = self.pois.insert(poi.id.as_bytes(), value)? {
+            let previous = Poi::from(serde_json::from_slice::<StoredPoi>(&previous)?);
+            if let Some(key) = spatial_key(&previous) {
+                self.spatial.remove(key)?;
+            }
+        }
+        if let Some(key) = spatial_key(poi) {
+            self.spatial.insert(key, Vec::new())?;
+        }
+        Ok(())
+    }
+
+    /// Removes the POI with the given id, along with its relations, and
+    /// returns it
+    pub fn remove_poi(&self, id: &str) -> Result<Option<Poi>> {
+        let poi = match self.pois.remove(id.as_bytes())? {
+            Some(value) => Poi::from(serde_json::from_slice::<StoredPoi>(&value)?),
+            None => return Ok(None),
+        };
+        if let Some(key) = spatial_key(&poi) {
+            self.spatial.remove(key)?;
+        }
+        let mut prefix = id.as_bytes().to_vec();
+        prefix.push(SEPARATOR);
+        let mut relations = Vec::new();
+        for key in self.relations.scan_prefix(&prefix).keys() {
+            relations.push(parse_relation_key(&key?)?);
+        }
+        for key in self.parents.scan_prefix(&prefix).keys() {
+            relations.push(parse_parent_key(&key?)?);
+        }
+        for relation in relations {
+            self.relations.remove(relation_key(&relation))?;
+            self.parents.remove(parent_key(&relation))?;
+        }
+        Ok(Some(poi))
+    }
+
+    /// Inserts `poi_type`, replacing the POI type with the same id
+    pub fn insert_poi_type(&self, poi_type: &PoiType) -> Result<()> {
+        self.poi_types
+            .insert(poi_type.id.as_bytes(), serde_json::to_vec(poi_type)?)?;
+        Ok(())
+    }
+
+    /// Links the POI `child_id` to its parent `parent_id`. Both POIs must be
+    /// in the model.
+    pub fn add_relation<T: Into<String>>(
+        &self,
+        parent_id: &str,
+        child_id: &str,
+        relation_type: T,
+    ) -> Result<()> {
+        for id in &[parent_id, child_id] {
+            if !self.pois.contains_key(id.as_bytes())? {
+                anyhow::bail!("cannot find poi '{}' for relation", id);
+            }
+        }
+        let relation = PoiRelation {
+            parent_id: parent_id.to_string(),
+            child_id: child_id.to_string(),
+            relation_type: relation_type.into(),
+        };
+        self.insert_relation(&relation)
+    }
+
+    /// Stores `relation` in the trees of the children and of the parents
+    fn insert_relation(&self, relation: &PoiRelation) -> Result<()> {
+        self.relations.insert(relation_key(relation), Vec::new())?;
+        self.parents.insert(parent_key(relation), Vec::new())?;
+        Ok(())
+    }
+
+    /// Returns the information about the dataset, if any
+    pub fn metadata(&self) -> Result<Option<Metadata>> {
+        match self.db.get(METADATA_KEY)? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Replaces the information about the dataset
+    pub fn set_metadata(&self, metadata: Option<&Metadata>) -> Result<()> {
+        match metadata {
+            Some(metadata) => self
+                .db
+                .insert(METADATA_KEY, serde_json::to_vec(metadata)?)?,
+            None => self.db.remove(METADATA_KEY)?,
+        };
+        Ok(())
+    }
+
+    /// Returns the POIs, ordered by id
+    pub fn pois(&self) -> impl Iterator<Item = Result<Poi>> {
+        self.pois.iter().values().map(|value| -> Result<Poi> {
+            let stored: StoredPoi = serde_json::from_slice(&value?)?;
+            Ok(Poi::from(stored))
+        })
+    }
+
+    /// Returns the visible POIs, ordered by id
+    pub fn visible_pois(&self) -> impl Iterator<Item = Result<Poi>> {
+        self.pois()
+            .filter(|poi| poi.as_ref().map_or(true, |poi| poi.visible))
+    }
+
+    /// Returns the POI types, ordered by id
+    pub fn poi_types(&self) -> impl Iterator<Item = Result<PoiType>> {
+        self.poi_types
+            .iter()
+            .values()
+            .map(|value| -> Result<PoiType> { Ok(serde_json::from_slice(&value?)?) })
+    }
+
+    /// Returns the relations, ordered by parent
+    pub fn relations(&self) -> impl Iterator<Item = Result<PoiRelation>> {
+        self.relations
+            .iter()
+            .keys()
+            .map(|key| parse_relation_key(&key?))
+    }
+
+    /// Returns the POI with the given id, if any
+    pub fn get_poi(&self, id: &str) -> Result<Option<Poi>> {
+        match self.pois.get(id.as_bytes())? {
+            Some(value) => Ok(Some(Poi::from(serde_json::from_slice::<StoredPoi>(
+                &value,
+            )?))),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the POI type with the given id, if any
+    pub fn get_poi_type(&self, id: &str) -> Result<Option<PoiType>> {
+        match self.poi_types.get(id.as_bytes())? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the name in `lang` of the POI type with the given id, if any
+    /// (see [PoiType::name_in])
+    pub fn poi_type_name(&self, id: &str, lang: &str) -> Result<Option<String>> {
+        Ok(self
+            .get_poi_type(id)?
+            .map(|poi_type| poi_type.name_in(lang).to_string()))
+    }
+
+    /// Returns the children of the POI, of any relation type, ordered by id
+    pub fn children_of(&self, poi_id: &str) -> Result<Vec<Poi>> {
+        let mut prefix = poi_id.as_bytes().to_vec();
+        prefix.push(SEPARATOR);
+        let mut ids = Vec::new();
+        for key in self.relations.scan_prefix(prefix).keys() {
+            ids.push(parse_relation_key(&key?)?.child_id);
+        }
+        self.get_pois(ids)
+    }
+
+    /// Returns the parents of the POI, of any relation type, ordered by id
+    pub fn parents_of(&self, poi_id: &str) -> Result<Vec<Poi>> {
+        let mut prefix = poi_id.as_bytes().to_vec();
+        prefix.push(SEPARATOR);
+        let mut ids = Vec::new();
+        for key in self.parents.scan_prefix(prefix).keys() {
+            ids.push(parse_parent_key(&key?)?.parent_id);
+        }
+        self.get_pois(ids)
+    }
+
+    /// Returns the POIs lying in `bbox`, bounds included, ordered by id
+    pub fn pois_in_bbox(&self, bbox: &geo::Rect<f64>) -> Result<Vec<Poi>> {
+        self.pois_in_shape(Some(*bbox), |_| true)
+    }
+
+    /// Returns the POIs lying inside the polygon, ordered by id. POIs on
+    /// the boundary of the polygon are not part of it.
+    pub fn pois_in_polygon(&self, polygon: &geo::Polygon<f64>) -> Result<Vec<Poi>> {
+        self.pois_in_shape(polygon.bounding_rect(), |point| polygon.contains(point))
+    }
+
+    /// Returns the POIs of the spatial index lying in `bbox` for which
+    /// `contains` returns true, ordered by id
+    fn pois_in_shape<F>(&self, bbox: Option<geo::Rect<f64>>, contains: F) -> Result<Vec<Poi>>
+    where
+        F: Fn(&geo::Point<f64>) -> bool,
+    {
+        let bbox = match bbox {
+            Some(bbox) => bbox,
+            None => return Ok(Vec::new()),
+        };
+        let min = z_order(bbox.min().x, bbox.min().y);
+        let max = z_order(bbox.max().x, bbox.max().y);
+        let mut ids = Vec::new();
+        let mut start = Some(min);
+        while let Some(from) = start.take() {
+            for key in self.spatial.range(from.to_be_bytes()..).keys() {
+                let key = key?;
+                let mut z = [0; 8];
+                z.copy_from_slice(&key[..8]);
+                let z = u64::from_be_bytes(z);
+                if z > max {
+                    break;
+                }
+                if !z_in_box(z, min, max) {
+                    start = Some(z_next_in_box(z, min, max));
+                    break;
+                }
+                ids.push(String::from_utf8(key[8..].to_vec())?);
+            }
+        }
+        let mut pois = self.get_pois(ids)?;
+        pois.retain(|poi| poi.coord.is_in(&bbox) && contains(&geo::Point::from(poi.coord.0)));
+        Ok(pois)
+    }
+
+    /// Reads the POIs with the given ids, ordered by id, skipping the
+    /// unknown ones
+    fn get_pois(&self, mut ids: Vec<String>) -> Result<Vec<Poi>> {
+        ids.sort();
+        ids.dedup();
+        let mut pois = Vec::with_capacity(ids.len());
+        for id in ids {
+            pois.extend(self.get_poi(&id)?);
+        }
+        Ok(pois)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{z_in_box, z_next_in_box, DiskModel};
+    use crate::{Coord, Model, Poi, PoiRelation};
+
+    fn poi(id: &str, lon: f64, lat: f64) -> Poi {
+        Poi {
+            id: id.to_string(),
+            coord: Coord::new(lon, lat),
+            ..Poi::default()
+        }
+    }
+
+    fn ids(pois: Vec<Poi>) -> Vec<String> {
+        pois.into_iter().map(|poi| poi.id).collect()
+    }
+
+    #[test]
+    fn next_position_in_box() {
+        // a 4 by 4 box on a 16 by 16 grid, at x 3..=6 and y 5..=8
+        let z = |x: u64, y: u64| super::spread(x as u32) | super::spread(y as u32) << 1;
+        let (min, max) = (z(3, 5), z(6, 8));
+        let inside: Vec<u64> = (min..=max).filter(|z| z_in_box(*z, min, max)).collect();
+        assert_eq!(inside.len(), 16);
+        for position in (min..=max).filter(|z| !z_in_box(*z, min, max)) {
+            let next = inside.iter().find(|z| **z > position).copied();
+            assert_eq!(Some(z_next_in_box(position, min, max)), next);
+        }
+    }
+
+    #[test]
+    fn pois_in_bbox_are_the_ones_in_the_box() {
+        let mut seed = 42u64;
+        let mut random = move || {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let model: Model = (0..2000)
+            .map(|i| poi(&format!("poi:{}", i), 2. + random(), 48. + random()))
+            .collect();
+        let dir = tempfile::tempdir().unwrap();
+        let disk = DiskModel::from_model(&model, dir.path()).unwrap();
+        let boxes = [
+            ((2.1, 48.1), (2.2, 48.9)),
+            ((2.5, 48.5), (2.5001, 48.6)),
+            ((2.33, 48.12), (2.71, 48.45)),
+            ((1., 47.), (4., 50.)),
+        ];
+        for (min, max) in boxes.iter() {
+            let bbox = geo::Rect::new(*min, *max);
+            let expected: Vec<String> = model
+                .pois
+                .values()
+                .filter(|poi| poi.coord.is_in(&bbox))
+                .map(|poi| poi.id.clone())
+                .collect();
+            assert_eq!(ids(disk.pois_in_bbox(&bbox).unwrap()), expected);
+        }
+    }
+
+    #[test]
+    fn relations_are_indexed_both_ways() {
+        let mut model: Model = vec![
+            poi("station", 2.37, 48.84),
+            poi("entrance", 2.37, 48.84),
+            poi("campus", 2.36, 48.83),
+        ]
+        .into_iter()
+        .collect();
+        let relation = |parent: &str, child: &str, relation_type: &str| PoiRelation {
+            parent_id: parent.to_string(),
+            child_id: child.to_string(),
+            relation_type: relation_type.to_string(),
+        };
+        model
+            .relations
+            .insert(relation("station", "entrance", PoiRelation::ENTRANCE));
+        model
+            .relations
+            .insert(relation("campus", "entrance", PoiRelation::PART));
+        let dir = tempfile::tempdir().unwrap();
+        let disk = DiskModel::from_model(&model, dir.path()).unwrap();
+        assert_eq!(
+            ids(disk.parents_of("entrance").unwrap()),
+            ["campus", "station"]
+        );
+
+        // a database written without the tree of the parents gets it back
+        disk.db.drop_tree("parents").unwrap();
+        drop(disk);
+        let disk = DiskModel::open(dir.path()).unwrap();
+        assert_eq!(
+            ids(disk.parents_of("entrance").unwrap()),
+            ["campus", "station"]
+        );
+
+        disk.remove_poi("station").unwrap();
+        assert_eq!(ids(disk.parents_of("entrance").unwrap()), ["campus"]);
+        disk.remove_poi("entrance").unwrap();
+        assert!(disk.children_of("campus").unwrap().is_empty());
+        assert_eq!(disk.relations().count(), 0);
+        assert_eq!(disk.parents.len(), 0);
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+//! Detection of POIs sharing the same position, and possibly the same name
+
+use crate::cancel::{self, CancellationToken};
+use crate::normalize::phonetic_key;
+use crate::{Model, Poi, Result};
+use geo::prelude::HaversineDistance;
+use std::collections::HashMap;
+
+/// Meters per degree of latitude, and of longitude at the equator, on the
+/// sphere of the haversine distance. Rounded down, so that cells are never
+/// smaller than they should.
+const METERS_PER_DEGREE: f64 = 111_194.;
+
+impl Model {
+    /// Returns the groups of POIs lying within `epsilon_m` meters of each
+    /// other, whatever their name or type.
+    ///
+    /// Grouping is transitive: two POIs further apart are in the same group
+    /// if a chain of close POIs links them. POIs with invalid coordinates
+    /// are ignored. Groups have at least two POIs, ordered by id, and are
+    /// ordered by the id of their first POI.
+    pub fn find_coordinate_duplicates(&self, epsilon_m: f64) -> Vec<Vec<&Poi>> {
+        self.coordinate_duplicates(epsilon_m, None)
+            .expect("cannot be cancelled without a token")
+    }
+
+    /// Same as [Model::find_coordinate_duplicates], stopping with a
+    /// [Cancelled](crate::cancel::Cancelled) error once `token` is cancelled
+    pub fn find_coordinate_duplicates_cancellable(
+        &self,
+        epsilon_m: f64,
+        token: &CancellationToken,
+    ) -> Result<Vec<Vec<&Poi>>> {
+        self.coordinate_duplicates(epsilon_m, Some(token))
+    }
+
+    /// Returns the groups of POIs lying within `epsilon_m` meters of each
+    /// other and having the same normalized name (see
+    /// [normalize](crate::normalize)), like `St Michel` and `Saint-Michel`.
+    ///
+    /// These are the groups of [Model::find_coordinate_duplicates] split by
+    /// normalized name, ordered the same way.
+    pub fn find_name_duplicates(&self, epsilon_m: f64) -> Vec<Vec<&Poi>> {
+        self.coordinate_duplicates_by(epsilon_m, Poi::normalized_name)
+    }
+
+    /// Same as [Model::find_name_duplicates], the names being compared by
+    /// their [phonetic_key], so that misspelled names like `Chatellet` and
+    /// `Châtelet` are duplicates.
+    pub fn find_phonetic_duplicates(&self, epsilon_m: f64) -> Vec<Vec<&Poi>> {
+        self.coordinate_duplicates_by(epsilon_m, |poi| phonetic_key(&poi.name))
+    }
+
+    /// Splits the groups of coordinate duplicates by `key`
+    fn coordinate_duplicates_by<F>(&self, epsilon_m: f64, key: F) -> Vec<Vec<&Poi>>
+    where
+        F: Fn(&Poi) -> String,
+    {
+        let mut groups = Vec::new();
+        for group in self.find_coordinate_duplicates(epsilon_m) {
+            let mut by_key: Vec<(String, Vec<&Poi>)> = Vec::new();
+            for poi in group {
+                let key = key(poi);
+                match by_key.iter_mut().find(|(other, _)| *other == key) {
+                    Some((_, pois)) => pois.push(poi),
+                    None => by_key.push((key, vec![poi])),
+                }
+            }
+            groups.extend(
+                by_key
+                    .into_iter()
+                    .map(|(_, pois)| pois)
+                    .filter(|pois| pois.len() > 1),
+            );
+        }
+        groups.sort_by(|lhs, rhs| lhs[0].id.cmp(&rhs[0].id));
+        groups
+    }
+
+    fn coordinate_duplicates(
+        &self,
+        epsilon_m: f64,
+        token: Option<&CancellationToken>,
+    ) -> Result<Vec<Vec<&Poi>>> {
+        cancel::check(token)?;
+        let pois: Vec<&Poi> = self
+            .pois
+            .values()
+            .filter(|poi| poi.coord.is_valid())
+            .collect();
+        // POIs are bucketed in cells of epsilon_m, so that close POIs are in
+        // the same or adjacent cells. Cells of at least a millimeter keep
+        // their indexes in range.
+        let cell_size = epsilon_m.max(1e-3);
+        // Longitudes are scaled as at the latitude furthest from the
+        // equator, where their degrees are the shortest: cells are then at
+        // least epsilon_m wide everywhere. The columns wrap around at the
+        // antimeridian, the last one being widened to close the circle.
+        let max_lat = pois
+            .iter()
+            .map(|poi| poi.coord.lat().abs())
+            .fold(0., f64::max);
+        let lon_scale = METERS_PER_DEGREE * max_lat.to_radians().cos();
+        let columns = ((360. * lon_scale / cell_size).floor() as i64).max(1);
+        let cell = |poi: &Poi| {
+            let x = (poi.coord.lon() + 180.) * lon_scale;
+            let y = poi.coord.lat() * METERS_PER_DEGREE;
+            (
+                ((x / cell_size).floor() as i64).min(columns - 1),
+                (y / cell_size).floor() as i64,
+            )
+        };
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (index, poi) in pois.iter().enumerate() {
+            cells.entry(cell(poi)).or_default().push(index);
+        }
+
+        let mut parents: Vec<usize> = (0..pois.len()).collect();
+        for (index, poi) in pois.iter().enumerate() {
+            cancel::check(token)?;
+            let (x, y) = cell(poi);
+            let point = geo::Point::from(&poi.coord);
+            let mut neighbour_columns: Vec<i64> =
+                (-1..=1).map(|dx| (x + dx).rem_euclid(columns)).collect();
+            neighbour_columns.sort_unstable();
+            neighbour_columns.dedup();
+            for &column in &neighbour_columns {
+                for dy in -1..=1 {
+                    for &other in cells.get(&(column, y + dy)).into_iter().flatten() {
+                        if other <= index {
+                            continue;
+                        }
+                        let distance =
+                            point.haversine_distance(&geo::Point::from(&pois[other].coord));
+                        if distance <= epsilon_m {
+                            let (root, other_root) =
+                                (find(&mut parents, index), find(&mut parents, other));
+                            parents[root.max(other_root)] = root.min(other_root);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut groups: Vec<Vec<&Poi>> = Vec::new();
+        let mut group_of_root = HashMap::new();
+        for (index, poi) in pois.iter().enumerate() {
+            let root = find(&mut parents, index);
+            let group = *group_of_root.entry(root).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group].push(*poi);
+        }
+        groups.retain(|group| group.len() > 1);
+        Ok(groups)
+    }
+}
+
+/// Finds the root of a union-find forest, compressing the path
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Coord, Model, Poi};
+
+    fn poi(id: &str, lon: f64, lat: f64) -> Poi {
+        Poi {
+            id: id.to_string(),
+            coord: Coord::new(lon, lat),
+            ..Poi::default()
+        }
+    }
+
+    fn ids(groups: Vec<Vec<&Poi>>) -> Vec<Vec<&str>> {
+        groups
+            .iter()
+            .map(|group| group.iter().map(|poi| poi.id.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn duplicates_across_the_antimeridian() {
+        let model: Model = vec![poi("east", 179.9999, -16.), poi("west", -179.9999, -16.)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            ids(model.find_coordinate_duplicates(50.)),
+            vec![vec!["east", "west"]]
+        );
+    }
+
+    #[test]
+    fn duplicates_at_different_latitudes() {
+        // about 33 m apart, north to south, far from the prime meridian
+        let model: Model = vec![
+            poi("north", 170., 60.000_3),
+            poi("south", 170., 60.),
+            poi("pole", 0., 89.),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            ids(model.find_coordinate_duplicates(40.)),
+            vec![vec!["north", "south"]]
+        );
+    }
+}
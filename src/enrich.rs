@@ -0,0 +1,240 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+//! Enrichment of POIs with their country, timezone and elevation
+//!
+//! An [Enricher] gives the country and the timezone of a coordinate.
+//! [Model::enrich] stores them in the `country_code` and `timezone`
+//! properties of the POIs. [AreaEnricher] is an enricher built from areas,
+//! like country boundaries. With the `geojson` feature, it is built as well
+//! from GeoJSON boundaries, like those of timezone-boundary-builder (see
+//! [AreaEnricher::from_geojson]).
+//!
+//! An [ElevationProvider] gives the elevation of a coordinate, stored in
+//! [Poi::elevation_m](crate::Poi::elevation_m) by [Model::assign_elevations].
+
+#[cfg(feature = "geojson")]
+use crate::Result;
+use crate::{Coord, Model};
+use chrono::Utc;
+use geo::prelude::{BoundingRect, Contains};
+
+/// Property holding the ISO 3166-1 alpha-2 code of the country of a POI
+pub const COUNTRY_CODE_KEY: &str = "country_code";
+
+/// Property holding the IANA timezone of a POI, like `Europe/Paris`
+pub const TIMEZONE_KEY: &str = "timezone";
+
+/// Gives the country and the timezone of coordinates. Both are unknown by
+/// default.
+pub trait Enricher {
+    /// Returns the ISO 3166-1 alpha-2 code of the country at `coord`
+    fn country_code(&self, _coord: &Coord) -> Option<String> {
+        None
+    }
+
+    /// Returns the IANA timezone at `coord`
+    fn timezone(&self, _coord: &Coord) -> Option<String> {
+        None
+    }
+}
+
+/// Gives the elevation of coordinates, like a digital elevation model
+pub trait ElevationProvider {
+    /// Returns the elevation above sea level at `coord`, in meters
+    fn elevation_m(&self, coord: &Coord) -> Option<f64>;
+}
+
+impl<F> ElevationProvider for F
+where
+    F: Fn(&Coord) -> Option<f64>,
+{
+    fn elevation_m(&self, coord: &Coord) -> Option<f64> {
+        self(coord)
+    }
+}
+
+/// An area with a country and a timezone
+#[derive(Debug, Clone)]
+pub struct EnrichmentArea {
+    /// Shape of the area
+    pub shape: geo::MultiPolygon<f64>,
+
+    /// ISO 3166-1 alpha-2 code of the country of the area, if any
+    pub country_code: Option<String>,
+
+    /// IANA timezone of the area, if any
+    pub timezone: Option<String>,
+}
+
+/// An [Enricher] giving the country and the timezone of the first area
+/// containing the coordinates (and having them)
+#[derive(Debug, Clone, Default)]
+pub struct AreaEnricher {
+    areas: Vec<(Option<geo::Rect<f64>>, EnrichmentArea)>,
+}
+
+impl AreaEnricher {
+    /// Builds the enricher from the areas, in order of precedence
+    pub fn new(areas: Vec<EnrichmentArea>) -> AreaEnricher {
+        AreaEnricher {
+            areas: areas
+                .into_iter()
+                .map(|area| (area.shape.bounding_rect(), area))
+                .collect(),
+        }
+    }
+
+    fn areas_at<'a>(&'a self, coord: &'a Coord) -> impl Iterator<Item = &'a EnrichmentArea> {
+        let point = geo::Point::from(coord);
+        self.areas
+            .iter()
+            .filter(move |(bbox, _)| bbox.is_some_and(|bbox| coord.is_in(&bbox)))
+            .filter(move |(_, area)| area.shape.contains(&point))
+            .map(|(_, area)| area)
+    }
+}
+
+#[cfg(feature = "geojson")]
+impl AreaEnricher {
+    /// Builds the enricher from the polygons and multipolygons of a GeoJSON
+    /// feature collection, in order of precedence. The country code and the
+    /// timezone of an area are the string values of the given properties of
+    /// its feature, like `ISO_A2` for the country boundaries of Natural
+    /// Earth, and `tzid` for the timezone boundaries of
+    /// timezone-boundary-builder. The features of other geometries are
+    /// skipped.
+    ///
+    /// ```
+    /// use navitia_poi_model::enrich::{AreaEnricher, Enricher};
+    /// use navitia_poi_model::Coord;
+    ///
+    /// let boundaries = r#"{
+    ///     "type": "FeatureCollection",
+    ///     "features": [{
+    ///         "type": "Feature",
+    ///         "properties": { "tzid": "Europe/Paris" },
+    ///         "geometry": {
+    ///             "type": "Polygon",
+    ///             "coordinates": [[[2, 48], [3, 48], [3, 49], [2, 49], [2, 48]]]
+    ///         }
+    ///     }]
+    /// }"#;
+    /// let enricher = AreaEnricher::from_geojson(boundaries.as_bytes(), None, Some("tzid")).unwrap();
+    /// let timezone = enricher.timezone(&Coord::new(2.35, 48.85));
+    /// assert_eq!(timezone.as_deref(), Some("Europe/Paris"));
+    /// ```
+    pub fn from_geojson<R: std::io::Read>(
+        reader: R,
+        country_code_key: Option<&str>,
+        timezone_key: Option<&str>,
+    ) -> Result<AreaEnricher> {
+        let mut areas = Vec::new();
+        for feature in ::geojson::FeatureReader::from_reader(reader).features() {
+            let feature = feature?;
+            let shape = match feature.geometry.as_ref().map(|geometry| &geometry.value) {
+                Some(::geojson::Value::Polygon(polygon)) => {
+                    geo::MultiPolygon(vec![to_polygon(polygon)])
+                }
+                Some(::geojson::Value::MultiPolygon(polygons)) => {
+                    geo::MultiPolygon(polygons.iter().map(to_polygon).collect())
+                }
+                _ => continue,
+            };
+            let string = |key: Option<&str>| {
+                key.and_then(|key| feature.property(key))
+                    .and_then(|value| value.as_str())
+                    .map(str::to_string)
+            };
+            areas.push(EnrichmentArea {
+                shape,
+                country_code: string(country_code_key),
+                timezone: string(timezone_key),
+            });
+        }
+        Ok(AreaEnricher::new(areas))
+    }
+}
+
+/// Converts the rings of a GeoJSON polygon, the first one being its exterior
+#[cfg(feature = "geojson")]
+fn to_polygon(rings: &::geojson::PolygonType) -> geo::Polygon<f64> {
+    let to_line = |ring: &Vec<::geojson::Position>| {
+        ring.iter()
+            .filter(|position| position.len() >= 2)
+            .map(|position| (position[0], position[1]))
+            .collect::<geo::LineString<f64>>()
+    };
+    let mut rings = rings.iter().map(to_line);
+    let exterior = rings.next().unwrap_or_else(|| geo::LineString(Vec::new()));
+    geo::Polygon::new(exterior, rings.collect())
+}
+
+impl Enricher for AreaEnricher {
+    fn country_code(&self, coord: &Coord) -> Option<String> {
+        self.areas_at(coord)
+            .find_map(|area| area.country_code.clone())
+    }
+
+    fn timezone(&self, coord: &Coord) -> Option<String> {
+        self.areas_at(coord).find_map(|area| area.timezone.clone())
+    }
+}
+
+impl Model {
+    /// Sets the `country_code` and `timezone` properties of the POIs with
+    /// valid coordinates, when `enricher` knows them. Returns the number of
+    /// POIs whose properties changed; their `updated_at` is set to the
+    /// current time.
+    pub fn enrich<E: Enricher + ?Sized>(&mut self, enricher: &E) -> usize {
+        let now = Utc::now();
+        let mut count = 0;
+        for poi in self.pois.values_mut().filter(|poi| poi.coord.is_valid()) {
+            let mut changed = false;
+            let values = [
+                (COUNTRY_CODE_KEY, enricher.country_code(&poi.coord)),
+                (TIMEZONE_KEY, enricher.timezone(&poi.coord)),
+            ];
+            for (key, value) in values.iter() {
+                if let Some(value) = value {
+                    if poi.properties.get(*key) != Some(value) {
+                        poi.properties.insert(key.to_string(), value.clone());
+                        changed = true;
+                    }
+                }
+            }
+            if changed {
+                poi.updated_at = Some(now);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Sets the elevation of the POIs with valid coordinates, when
+    /// `provider` knows it. Returns the number of POIs whose elevation
+    /// changed; their `updated_at` is set to the current time.
+    pub fn assign_elevations<P: ElevationProvider + ?Sized>(&mut self, provider: &P) -> usize {
+        let now = Utc::now();
+        let mut count = 0;
+        for poi in self.pois.values_mut().filter(|poi| poi.coord.is_valid()) {
+            let elevation_m = provider.elevation_m(&poi.coord);
+            if elevation_m.is_some() && elevation_m != poi.elevation_m {
+                poi.elevation_m = elevation_m;
+                poi.updated_at = Some(now);
+                count += 1;
+            }
+        }
+        count
+    }
+}
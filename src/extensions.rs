@@ -0,0 +1,273 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+//! Typed extensions of the POIs of some types
+//!
+//! A [PoiExtension] gives a typed view of some properties of the POIs of
+//! given types, like the plugs of a charging station or the capacity of a
+//! car park. Extensions are stored in the properties, so they round trip
+//! through any archive.
+//!
+//! ```
+//! use navitia_poi_model::extensions::ChargingStation;
+//! use navitia_poi_model::Poi;
+//!
+//! let mut poi = Poi::default();
+//! poi.set_extension(&ChargingStation {
+//!     capacity: Some(4),
+//!     plug_types: vec!["type2".to_string(), "ccs".to_string()],
+//!     ..ChargingStation::default()
+//! });
+//! assert_eq!(poi.properties["plug_types"], "type2;ccs");
+//! assert_eq!(poi.extension::<ChargingStation>().unwrap().capacity, Some(4));
+//! ```
+
+use crate::poi_types::well_known;
+use crate::{Model, Poi};
+use std::collections::BTreeMap;
+
+/// A typed view of some properties of the POIs of given types
+pub trait PoiExtension: Sized {
+    /// Ids of the POI types with this extension. The descendants of these
+    /// types have it too.
+    const POI_TYPES: &'static [&'static str];
+
+    /// Keys of the properties holding the extension
+    const KEYS: &'static [&'static str];
+
+    /// Parses the extension from properties. Returns `None` when none of
+    /// its keys is present.
+    fn from_properties(properties: &BTreeMap<String, String>) -> Option<Self>;
+
+    /// Returns the properties holding the extension
+    fn to_properties(&self) -> BTreeMap<String, String>;
+}
+
+/// The equipment of a charging station for electric vehicles
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChargingStation {
+    /// Operator of the station, from the `operator` property
+    pub operator: Option<String>,
+
+    /// Number of vehicles that can be charged at the same time, from the
+    /// `capacity` property
+    pub capacity: Option<u32>,
+
+    /// Types of plugs, like `type2` or `ccs`, from the `;` separated
+    /// `plug_types` property
+    pub plug_types: Vec<String>,
+
+    /// Maximum power delivered, in kW, from the `max_power_kw` property
+    pub max_power_kw: Option<f64>,
+}
+
+impl PoiExtension for ChargingStation {
+    const POI_TYPES: &'static [&'static str] = &[well_known::CHARGING_STATION];
+    const KEYS: &'static [&'static str] = &["operator", "capacity", "plug_types", "max_power_kw"];
+
+    fn from_properties(properties: &BTreeMap<String, String>) -> Option<Self> {
+        if !Self::KEYS.iter().any(|key| properties.contains_key(*key)) {
+            return None;
+        }
+        Some(ChargingStation {
+            operator: properties.get("operator").cloned(),
+            capacity: parse(properties, "capacity"),
+            plug_types: properties
+                .get("plug_types")
+                .map(|plugs| split_list(plugs))
+                .unwrap_or_default(),
+            max_power_kw: parse(properties, "max_power_kw"),
+        })
+    }
+
+    fn to_properties(&self) -> BTreeMap<String, String> {
+        let mut properties = BTreeMap::new();
+        insert(&mut properties, "operator", self.operator.as_ref());
+        insert(&mut properties, "capacity", self.capacity);
+        if !self.plug_types.is_empty() {
+            properties.insert("plug_types".to_string(), self.plug_types.join(";"));
+        }
+        insert(&mut properties, "max_power_kw", self.max_power_kw);
+        properties
+    }
+}
+
+/// A bike-sharing station
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BikeShareStation {
+    /// Operator of the station, from the `operator` property
+    pub operator: Option<String>,
+
+    /// Network the station belongs to, from the `network` property
+    pub network: Option<String>,
+
+    /// Number of docks, from the `capacity` property
+    pub capacity: Option<u32>,
+}
+
+impl PoiExtension for BikeShareStation {
+    const POI_TYPES: &'static [&'static str] = &[well_known::BICYCLE_RENTAL];
+    const KEYS: &'static [&'static str] = &["operator", "network", "capacity"];
+
+    fn from_properties(properties: &BTreeMap<String, String>) -> Option<Self> {
+        if !Self::KEYS.iter().any(|key| properties.contains_key(*key)) {
+            return None;
+        }
+        Some(BikeShareStation {
+            operator: properties.get("operator").cloned(),
+            network: properties.get("network").cloned(),
+            capacity: parse(properties, "capacity"),
+        })
+    }
+
+    fn to_properties(&self) -> BTreeMap<String, String> {
+        let mut properties = BTreeMap::new();
+        insert(&mut properties, "operator", self.operator.as_ref());
+        insert(&mut properties, "network", self.network.as_ref());
+        insert(&mut properties, "capacity", self.capacity);
+        properties
+    }
+}
+
+/// The capacity and conditions of a car park, from the conventional OSM
+/// property keys
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParkingInfo {
+    /// Total number of spots, from the `capacity` property
+    pub capacity: Option<u32>,
+
+    /// Number of spots for disabled people, from the `capacity:disabled`
+    /// property
+    pub disabled_spots: Option<u32>,
+
+    /// Maximum height of the vehicles, in meters, from the `maxheight`
+    /// property (`2.1` or `2.1 m`)
+    pub max_height_m: Option<f64>,
+
+    /// Whether parking is charged, from the `fee` property (`yes` or `no`)
+    pub fee: Option<bool>,
+}
+
+impl PoiExtension for ParkingInfo {
+    const POI_TYPES: &'static [&'static str] = &[well_known::PARKING];
+    const KEYS: &'static [&'static str] = &["capacity", "capacity:disabled", "maxheight", "fee"];
+
+    fn from_properties(properties: &BTreeMap<String, String>) -> Option<Self> {
+        if !Self::KEYS.iter().any(|key| properties.contains_key(*key)) {
+            return None;
+        }
+        Some(ParkingInfo {
+            capacity: parse(properties, "capacity"),
+            disabled_spots: parse(properties, "capacity:disabled"),
+            max_height_m: properties.get("maxheight").and_then(|height| {
+                let height = height.trim();
+                height
+                    .strip_suffix('m')
+                    .unwrap_or(height)
+                    .trim()
+                    .parse()
+                    .ok()
+            }),
+            fee: properties
+                .get("fee")
+                .and_then(|fee| match fee.trim().to_lowercase().as_str() {
+                    "yes" | "true" | "1" => Some(true),
+                    "no" | "false" | "0" => Some(false),
+                    _ => None,
+                }),
+        })
+    }
+
+    fn to_properties(&self) -> BTreeMap<String, String> {
+        let mut properties = BTreeMap::new();
+        insert(&mut properties, "capacity", self.capacity);
+        insert(&mut properties, "capacity:disabled", self.disabled_spots);
+        insert(&mut properties, "maxheight", self.max_height_m);
+        insert(
+            &mut properties,
+            "fee",
+            self.fee.map(|fee| if fee { "yes" } else { "no" }),
+        );
+        properties
+    }
+}
+
+/// Parses a property, ignoring the invalid values
+pub(crate) fn parse<T: std::str::FromStr>(
+    properties: &BTreeMap<String, String>,
+    key: &str,
+) -> Option<T> {
+    properties
+        .get(key)
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Inserts a property if the value is known
+pub(crate) fn insert<T: ToString>(
+    properties: &mut BTreeMap<String, String>,
+    key: &str,
+    value: Option<T>,
+) {
+    if let Some(value) = value {
+        properties.insert(key.to_string(), value.to_string());
+    }
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(';')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl Poi {
+    /// Parses the extension `E` from the properties of the POI, whatever
+    /// its type
+    pub fn extension<E: PoiExtension>(&self) -> Option<E> {
+        E::from_properties(&self.properties)
+    }
+
+    /// Stores the extension in the properties of the POI, replacing the
+    /// previous values of its keys
+    pub fn set_extension<E: PoiExtension>(&mut self, extension: &E) {
+        for key in E::KEYS {
+            self.properties.remove(*key);
+        }
+        self.properties.extend(extension.to_properties());
+    }
+}
+
+impl Model {
+    /// Returns the parking information of the POI, if it is a car park.
+    /// See [Model::extension].
+    pub fn parking_info(&self, poi_id: &str) -> Option<ParkingInfo> {
+        self.extension(poi_id)
+    }
+
+    /// Returns the extension `E` of the POI, if the POI exists, and its type
+    /// or one of the ancestors of its type is one of the types of `E`
+    pub fn extension<E: PoiExtension>(&self, poi_id: &str) -> Option<E> {
+        let poi = self.pois.get(poi_id)?;
+        let has_type = E::POI_TYPES.contains(&poi.poi_type_id.as_str())
+            || self
+                .poi_type_ancestors(&poi.poi_type_id)
+                .iter()
+                .any(|poi_type| E::POI_TYPES.contains(&poi_type.id.as_str()));
+        if has_type {
+            poi.extension()
+        } else {
+            None
+        }
+    }
+}
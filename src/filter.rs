@@ -0,0 +1,470 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Composable filters to select POIs
+//!
+//! Filters are built from simple criteria, and combined with
+//! [PoiFilter::and], [PoiFilter::or] and `!`.
+//!
+//! They can also be parsed from a query with [PoiFilter::parse], like
+//! `poi_type=amenity:parking AND wheelchair=yes AND weight>=3`. A query is
+//! made of criteria combined with `AND`, `OR`, `NOT` and parentheses, `AND`
+//! binding tighter than `OR`:
+//!
+//! - `poi_type=<id>`: POIs of the given type
+//! - `visible=true` or `visible=false`: POIs with the given visibility
+//! - `weight<op><number>`, `<op>` being one of `=`, `!=`, `<`, `<=`, `>`
+//!   or `>=`: POIs whose weight compares to the number
+//! - `name=<name>`, `name!=<name>` or `name~<regex>`: POIs whose name is
+//!   (or is not) the given name, or matches the regular expression
+//! - `bbox=<min_lon>,<min_lat>,<max_lon>,<max_lat>`: POIs in the box
+//! - `<key>=<value>` or `<key>!=<value>`: POIs having (or not) the property
+//!   `key` set to `value`
+//! - `<key>`: POIs having the property `key`
+//!
+//! Values containing spaces, parentheses or operators are written between
+//! double quotes, with `\"` and `\\` as escapes. Keywords are case
+//! insensitive.
+
+use crate::{Model, Poi, Result};
+use anyhow::{anyhow, bail};
+use regex::Regex;
+use std::iter::Peekable;
+use std::str::FromStr;
+
+/// A predicate on POIs
+#[derive(Debug, Clone, Default)]
+pub enum PoiFilter {
+    /// Matches every POI
+    #[default]
+    All,
+
+    /// Matches the POIs of the given type
+    PoiType(String),
+
+    /// Matches the POIs in the bounding box, bounds included
+    BBox(geo::Rect<f64>),
+
+    /// Matches the POIs having the property `key`, with the given value if
+    /// any
+    Property {
+        /// Key of the property
+        key: String,
+        /// Expected value of the property, any value if `None`
+        value: Option<String>,
+    },
+
+    /// Matches the POIs with the given visibility
+    Visible(bool),
+
+    /// Matches the POIs whose weight is in the range, bounds included
+    Weight {
+        /// Lowest accepted weight, if any
+        min: Option<f64>,
+        /// Highest accepted weight, if any
+        max: Option<f64>,
+    },
+
+    /// Matches the POIs whose name matches the regular expression
+    Name(Regex),
+
+    /// Matches the POIs matched by all the filters
+    And(Vec<PoiFilter>),
+
+    /// Matches the POIs matched by any of the filters
+    Or(Vec<PoiFilter>),
+
+    /// Matches the POIs not matched by the filter
+    Not(Box<PoiFilter>),
+}
+
+impl PoiFilter {
+    /// Matches the POIs of the given type
+    pub fn poi_type<S: Into<String>>(poi_type_id: S) -> PoiFilter {
+        PoiFilter::PoiType(poi_type_id.into())
+    }
+
+    /// Matches the POIs in the bounding box, bounds included
+    pub fn bbox(bbox: geo::Rect<f64>) -> PoiFilter {
+        PoiFilter::BBox(bbox)
+    }
+
+    /// Matches the POIs having the property `key`
+    pub fn has_property<S: Into<String>>(key: S) -> PoiFilter {
+        PoiFilter::Property {
+            key: key.into(),
+            value: None,
+        }
+    }
+
+    /// Matches the POIs having the property `key` set to `value`
+    pub fn property<K: Into<String>, V: Into<String>>(key: K, value: V) -> PoiFilter {
+        PoiFilter::Property {
+            key: key.into(),
+            value: Some(value.into()),
+        }
+    }
+
+    /// Matches the POIs with the given visibility
+    pub fn visible(visible: bool) -> PoiFilter {
+        PoiFilter::Visible(visible)
+    }
+
+    /// Matches the POIs whose weight is between `min` and `max`, included
+    pub fn weight_between(min: Option<f64>, max: Option<f64>) -> PoiFilter {
+        PoiFilter::Weight { min, max }
+    }
+
+    /// Matches the POIs whose name matches the regular expression `pattern`
+    pub fn name_regex(pattern: &str) -> Result<PoiFilter> {
+        Ok(PoiFilter::Name(Regex::new(pattern)?))
+    }
+
+    /// Matches the POIs matched by both filters
+    pub fn and(self, rhs: PoiFilter) -> PoiFilter {
+        match self {
+            PoiFilter::All => rhs,
+            PoiFilter::And(mut filters) => {
+                filters.push(rhs);
+                PoiFilter::And(filters)
+            }
+            lhs => PoiFilter::And(vec![lhs, rhs]),
+        }
+    }
+
+    /// Matches the POIs matched by any of the filters
+    pub fn or(self, rhs: PoiFilter) -> PoiFilter {
+        match self {
+            PoiFilter::Or(mut filters) => {
+                filters.push(rhs);
+                PoiFilter::Or(filters)
+            }
+            lhs => PoiFilter::Or(vec![lhs, rhs]),
+        }
+    }
+
+    /// Returns true if the POI is matched by the filter
+    pub fn matches(&self, poi: &Poi) -> bool {
+        match self {
+            PoiFilter::All => true,
+            PoiFilter::PoiType(id) => &poi.poi_type_id == id,
+            PoiFilter::BBox(bbox) => poi.coord.is_in(bbox),
+            PoiFilter::Property { key, value } => match (poi.properties.get(key), value) {
+                (Some(actual), Some(expected)) => actual == expected,
+                (Some(_), None) => true,
+                (None, _) => false,
+            },
+            PoiFilter::Visible(visible) => poi.visible == *visible,
+            PoiFilter::Weight { min, max } => {
+                min.is_none_or(|min| min <= poi.weight.value())
+                    && max.is_none_or(|max| poi.weight.value() <= max)
+            }
+            PoiFilter::Name(regex) => regex.is_match(&poi.name),
+            PoiFilter::And(filters) => filters.iter().all(|f| f.matches(poi)),
+            PoiFilter::Or(filters) => filters.iter().any(|f| f.matches(poi)),
+            PoiFilter::Not(filter) => !filter.matches(poi),
+        }
+    }
+}
+
+impl PoiFilter {
+    /// Parses a query into a filter (see the [module documentation](self)
+    /// for the syntax). The empty query matches every POI.
+    pub fn parse(query: &str) -> Result<PoiFilter> {
+        let mut tokens = tokenize(query)?.into_iter().peekable();
+        if tokens.peek().is_none() {
+            return Ok(PoiFilter::All);
+        }
+        let filter = parse_or(&mut tokens)?;
+        match tokens.next() {
+            None => Ok(filter),
+            Some(token) => bail!("unexpected {} in filter '{}'", token, query),
+        }
+    }
+}
+
+impl FromStr for PoiFilter {
+    type Err = crate::Error;
+
+    fn from_str(query: &str) -> Result<PoiFilter> {
+        PoiFilter::parse(query)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Operator(String),
+    Word(String),
+    Quoted(String),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+            Token::Operator(s) | Token::Word(s) => write!(f, "'{}'", s),
+            Token::Quoted(s) => write!(f, "'\"{}\"'", s),
+        }
+    }
+}
+
+const OPERATOR_CHARS: &str = "=!<>~";
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::Open);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::Close);
+        } else if c == '"' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c) => value.push(c),
+                        None => bail!("unterminated string in filter '{}'", query),
+                    },
+                    Some(c) => value.push(c),
+                    None => bail!("unterminated string in filter '{}'", query),
+                }
+            }
+            tokens.push(Token::Quoted(value));
+        } else if OPERATOR_CHARS.contains(c) {
+            let mut operator = String::new();
+            while let Some(c) = chars.next_if(|c| OPERATOR_CHARS.contains(*c)) {
+                operator.push(c);
+            }
+            tokens.push(Token::Operator(operator));
+        } else {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| {
+                !c.is_whitespace() && !"()\"".contains(*c) && !OPERATOR_CHARS.contains(*c)
+            }) {
+                word.push(c);
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+type Tokens = Peekable<std::vec::IntoIter<Token>>;
+
+fn is_keyword(token: Option<&Token>, keyword: &str) -> bool {
+    matches!(token, Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+}
+
+fn parse_or(tokens: &mut Tokens) -> Result<PoiFilter> {
+    let mut filter = parse_and(tokens)?;
+    while is_keyword(tokens.peek(), "or") {
+        tokens.next();
+        filter = filter.or(parse_and(tokens)?);
+    }
+    Ok(filter)
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<PoiFilter> {
+    let mut filter = parse_unary(tokens)?;
+    while is_keyword(tokens.peek(), "and") {
+        tokens.next();
+        filter = filter.and(parse_unary(tokens)?);
+    }
+    Ok(filter)
+}
+
+fn parse_unary(tokens: &mut Tokens) -> Result<PoiFilter> {
+    match tokens.next() {
+        Some(Token::Word(word)) if word.eq_ignore_ascii_case("not") => Ok(!parse_unary(tokens)?),
+        Some(Token::Open) => {
+            let filter = parse_or(tokens)?;
+            match tokens.next() {
+                Some(Token::Close) => Ok(filter),
+                Some(token) => bail!("expected ')', found {}", token),
+                None => bail!("expected ')', found the end of the filter"),
+            }
+        }
+        Some(Token::Word(key)) | Some(Token::Quoted(key)) => parse_criterion(key, tokens),
+        Some(token) => bail!("expected a criterion, found {}", token),
+        None => bail!("expected a criterion, found the end of the filter"),
+    }
+}
+
+fn parse_criterion(key: String, tokens: &mut Tokens) -> Result<PoiFilter> {
+    let operator = match tokens.next_if(|token| matches!(token, Token::Operator(_))) {
+        Some(Token::Operator(operator)) => operator,
+        _ => return Ok(PoiFilter::has_property(key)),
+    };
+    let value = match tokens.next() {
+        Some(Token::Word(value)) | Some(Token::Quoted(value)) => value,
+        Some(token) => bail!(
+            "expected a value after '{}{}', found {}",
+            key,
+            operator,
+            token
+        ),
+        None => bail!("expected a value after '{}{}'", key, operator),
+    };
+    let unsupported = || anyhow!("unsupported operator '{}' for '{}'", operator, key);
+    match key.as_str() {
+        "poi_type" => match operator.as_str() {
+            "=" => Ok(PoiFilter::poi_type(value)),
+            "!=" => Ok(!PoiFilter::poi_type(value)),
+            _ => Err(unsupported()),
+        },
+        "visible" => {
+            let visible = match value.to_ascii_lowercase().as_str() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => bail!("invalid visibility '{}', expected true or false", value),
+            };
+            match operator.as_str() {
+                "=" => Ok(PoiFilter::visible(visible)),
+                "!=" => Ok(PoiFilter::visible(!visible)),
+                _ => Err(unsupported()),
+            }
+        }
+        "weight" => {
+            let weight: f64 = value
+                .parse()
+                .map_err(|_| anyhow!("invalid weight '{}'", value))?;
+            match operator.as_str() {
+                "=" => Ok(PoiFilter::weight_between(Some(weight), Some(weight))),
+                "!=" => Ok(!PoiFilter::weight_between(Some(weight), Some(weight))),
+                "<=" => Ok(PoiFilter::weight_between(None, Some(weight))),
+                ">=" => Ok(PoiFilter::weight_between(Some(weight), None)),
+                "<" => Ok(!PoiFilter::weight_between(Some(weight), None)),
+                ">" => Ok(!PoiFilter::weight_between(None, Some(weight))),
+                _ => Err(unsupported()),
+            }
+        }
+        "name" => {
+            let pattern = match operator.as_str() {
+                "~" => value,
+                "=" | "!=" => format!("^{}$", regex::escape(&value)),
+                _ => return Err(unsupported()),
+            };
+            let filter = PoiFilter::name_regex(&pattern)?;
+            Ok(if operator == "!=" { !filter } else { filter })
+        }
+        "bbox" if operator == "=" => {
+            let bounds = value
+                .split(',')
+                .map(|bound| bound.trim().parse::<f64>())
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .ok()
+                .filter(|bounds| bounds.len() == 4)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "invalid bbox '{}', expected min_lon,min_lat,max_lon,max_lat",
+                        value
+                    )
+                })?;
+            Ok(PoiFilter::bbox(geo::Rect::new(
+                geo_types::Coord {
+                    x: bounds[0],
+                    y: bounds[1],
+                },
+                geo_types::Coord {
+                    x: bounds[2],
+                    y: bounds[3],
+                },
+            )))
+        }
+        _ => match operator.as_str() {
+            "=" => Ok(PoiFilter::property(key, value)),
+            "!=" => Ok(!PoiFilter::property(key, value)),
+            _ => Err(unsupported()),
+        },
+    }
+}
+
+impl std::ops::Not for PoiFilter {
+    type Output = PoiFilter;
+
+    /// Matches the POIs not matched by the filter
+    fn not(self) -> PoiFilter {
+        match self {
+            PoiFilter::Not(filter) => *filter,
+            filter => PoiFilter::Not(Box::new(filter)),
+        }
+    }
+}
+
+/// Sets the visibility of the POIs matched by a filter
+#[derive(Debug, Clone)]
+pub struct VisibilityRule {
+    /// The POIs concerned by the rule
+    pub filter: PoiFilter,
+
+    /// The visibility given to these POIs
+    pub visible: bool,
+}
+
+impl VisibilityRule {
+    /// Shows the POIs matched by the filter
+    pub fn show(filter: PoiFilter) -> VisibilityRule {
+        VisibilityRule {
+            filter,
+            visible: true,
+        }
+    }
+
+    /// Hides the POIs matched by the filter
+    pub fn hide(filter: PoiFilter) -> VisibilityRule {
+        VisibilityRule {
+            filter,
+            visible: false,
+        }
+    }
+}
+
+impl Model {
+    /// Returns a new model made of the POIs matched by the filter, and of
+    /// the POI types they reference.
+    pub fn filtered(&self, filter: &PoiFilter) -> Model {
+        self.sub_model(self.pois.values().filter(|poi| filter.matches(poi)))
+    }
+
+    /// Sets the visibility of the POIs according to the rules, and returns
+    /// the number of POIs whose visibility changed.
+    ///
+    /// For each POI, the last matching rule wins. POIs matched by no rule
+    /// are left untouched, the `updated_at` of the others is set to the
+    /// current time.
+    pub fn apply_visibility_rules(&mut self, rules: &[VisibilityRule]) -> usize {
+        let now = chrono::Utc::now();
+        let mut count = 0;
+        for poi in self.pois.values_mut() {
+            if let Some(rule) = rules.iter().rev().find(|rule| rule.filter.matches(poi)) {
+                if poi.visible != rule.visible {
+                    poi.visible = rule.visible;
+                    poi.updated_at = Some(now);
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+}
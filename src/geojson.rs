@@ -0,0 +1,183 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+//! Conversions between [Poi] and GeoJSON features
+//!
+//! A POI is a feature with a point geometry at `[lon, lat]`, whose id is
+//! the id of the POI, and with the following properties:
+//!
+//! | property       | field of the POI                                  |
+//! |----------------|---------------------------------------------------|
+//! | `name`         | `name`, empty if absent                           |
+//! | `poi_type`     | `poi_type_id`, required                           |
+//! | `weight`       | `weight`, 0 if absent                             |
+//! | `visible`      | `visible`, true if absent                         |
+//! | `elevation`    | `elevation_m`, if any                             |
+//! | `created_at`   | `created_at` in RFC 3339 format, if any           |
+//! | `updated_at`   | `updated_at` in RFC 3339 format, if any           |
+//! | `poi_properties` | `properties`, as an object of strings           |
+//!
+//! The accessibility of the POI is taken from its properties, see
+//! [Poi::set_accessibility]. Other properties of a feature are ignored.
+//!
+//! ```
+//! use navitia_poi_model::{Coord, Poi};
+//! use std::convert::TryFrom;
+//!
+//! let poi = Poi {
+//!     id: "poi:1".into(),
+//!     coord: Coord::new(2.35, 48.85),
+//!     poi_type_id: "amenity:parking".into(),
+//!     ..Poi::default()
+//! };
+//! let feature = geojson::Feature::from(&poi);
+//! assert_eq!(Poi::try_from(feature).unwrap(), poi);
+//! ```
+
+use crate::accessibility::Accessibility;
+use crate::{Coord, Error, Poi, Weight};
+use ::geojson::feature::Id;
+use ::geojson::{Feature, Geometry, JsonObject, JsonValue, Value};
+use anyhow::{anyhow, bail, Context};
+use chrono::{DateTime, Utc};
+use std::convert::TryFrom;
+
+impl From<&Poi> for Feature {
+    fn from(poi: &Poi) -> Feature {
+        let mut properties = JsonObject::new();
+        properties.insert("name".into(), poi.name.clone().into());
+        properties.insert("poi_type".into(), poi.poi_type_id.clone().into());
+        properties.insert(
+            "weight".into(),
+            serde_json::to_value(poi.weight).expect("weights are serializable"),
+        );
+        properties.insert("visible".into(), poi.visible.into());
+        if let Some(elevation_m) = poi.elevation_m {
+            properties.insert("elevation".into(), elevation_m.into());
+        }
+        for (key, timestamp) in [
+            ("created_at", poi.created_at),
+            ("updated_at", poi.updated_at),
+        ] {
+            if let Some(timestamp) = timestamp {
+                properties.insert(
+                    key.into(),
+                    serde_json::to_value(timestamp).expect("timestamps are serializable"),
+                );
+            }
+        }
+        properties.insert(
+            "poi_properties".into(),
+            JsonValue::Object(
+                poi.properties
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone().into()))
+                    .collect(),
+            ),
+        );
+        Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::Point(vec![
+                poi.coord.lon(),
+                poi.coord.lat(),
+            ]))),
+            id: Some(Id::String(poi.id.clone())),
+            properties: Some(properties),
+            foreign_members: None,
+        }
+    }
+}
+
+impl TryFrom<Feature> for Poi {
+    type Error = Error;
+
+    fn try_from(feature: Feature) -> Result<Poi, Error> {
+        let Feature {
+            geometry,
+            id,
+            properties,
+            ..
+        } = feature;
+        let id = match id {
+            Some(Id::String(id)) => id,
+            Some(Id::Number(id)) => id.to_string(),
+            None => bail!("feature without id"),
+        };
+        let convert = |id: &str| -> Result<Poi, Error> {
+            let coord = match geometry.map(|geometry| geometry.value) {
+                Some(Value::Point(position)) if position.len() >= 2 => {
+                    Coord::try_new(position[0], position[1])?
+                }
+                Some(_) => bail!("geometry is not a point"),
+                None => bail!("feature without geometry"),
+            };
+            let mut properties = properties.unwrap_or_default();
+            let string = |properties: &mut JsonObject, key: &str| match properties.remove(key) {
+                Some(JsonValue::String(value)) => Ok(Some(value)),
+                Some(JsonValue::Null) | None => Ok(None),
+                Some(value) => Err(anyhow!("invalid value {} for property '{}'", value, key)),
+            };
+            let number = |properties: &mut JsonObject, key: &str| match properties.remove(key) {
+                Some(JsonValue::Number(value)) => Ok(value.as_f64()),
+                Some(JsonValue::Null) | None => Ok(None),
+                Some(value) => Err(anyhow!("invalid value {} for property '{}'", value, key)),
+            };
+            let timestamp = |properties: &mut JsonObject, key: &str| {
+                string(properties, key)?
+                    .map(|value| {
+                        value.parse::<DateTime<Utc>>().with_context(|| {
+                            format!("invalid value '{}' for property '{}'", value, key)
+                        })
+                    })
+                    .transpose()
+            };
+            let visible = match properties.remove("visible") {
+                Some(JsonValue::Bool(visible)) => visible,
+                Some(JsonValue::Null) | None => true,
+                Some(value) => bail!("invalid value {} for property 'visible'", value),
+            };
+            let poi_properties = match properties.remove("poi_properties") {
+                Some(JsonValue::Object(object)) => object
+                    .into_iter()
+                    .map(|(key, value)| match value {
+                        JsonValue::String(value) => Ok((key, value)),
+                        value => Err(anyhow!(
+                            "invalid value {} for poi property '{}'",
+                            value,
+                            key
+                        )),
+                    })
+                    .collect::<Result<_, Error>>()?,
+                Some(JsonValue::Null) | None => Default::default(),
+                Some(value) => bail!("invalid value {} for property 'poi_properties'", value),
+            };
+            let mut poi = Poi {
+                id: id.to_string(),
+                name: string(&mut properties, "name")?.unwrap_or_default(),
+                coord,
+                poi_type_id: string(&mut properties, "poi_type")?
+                    .ok_or_else(|| anyhow!("missing property 'poi_type'"))?,
+                properties: poi_properties,
+                visible,
+                weight: Weight(number(&mut properties, "weight")?.unwrap_or_default()),
+                accessibility: Accessibility::default(),
+                elevation_m: number(&mut properties, "elevation")?,
+                created_at: timestamp(&mut properties, "created_at")?,
+                updated_at: timestamp(&mut properties, "updated_at")?,
+            };
+            poi.accessibility = Accessibility::from_properties(&poi.properties);
+            Ok(poi)
+        };
+        convert(&id).with_context(|| format!("invalid feature '{}'", id))
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Generation of POI ids following the navitia convention, `poi:` followed
+//! by the source of the POI or by a unique part
+//!
+//! ```
+//! use navitia_poi_model::ids::PoiId;
+//! use navitia_poi_model::{Model, Poi};
+//!
+//! assert_eq!(PoiId::from_source("osm", "node:42").as_str(), "poi:osm:node:42");
+//! assert_eq!(PoiId::from_name("Gare de Lyon – Hall 1").as_str(), "poi:gare-de-lyon-hall-1");
+//!
+//! let mut model = Model::default();
+//! let id = PoiId::from_name("Mairie");
+//! model.pois.insert(id.to_string(), Poi::default());
+//! assert_eq!(id.unique_in(&model).as_str(), "poi:mairie-2");
+//! ```
+
+use crate::normalize::tokenize;
+use crate::Model;
+use itertools::Itertools;
+
+/// Prefix of the navitia POI ids
+pub const POI_ID_PREFIX: &str = "poi:";
+
+/// Id of a POI, see [module documentation](self)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PoiId(String);
+
+impl PoiId {
+    /// Id of a POI imported from `provider`, like `osm`, where its id is
+    /// `external_id`: `poi:<provider>:<external_id>`. Importing the same
+    /// POI again gives the same id.
+    pub fn from_source(provider: &str, external_id: &str) -> PoiId {
+        PoiId(format!(
+            "{}{}:{}",
+            POI_ID_PREFIX,
+            provider.trim(),
+            external_id.trim()
+        ))
+    }
+
+    /// Id made of the normalized words of `name` (see
+    /// [normalize](crate::normalize)), joined by dashes, like
+    /// `poi:gare-de-lyon`
+    pub fn from_name(name: &str) -> PoiId {
+        PoiId(format!("{}{}", POI_ID_PREFIX, tokenize(name).join("-")))
+    }
+
+    /// Id made of a random UUID, like
+    /// `poi:67e55044-10b1-426f-9247-bb680e5fe0c8`
+    pub fn random() -> PoiId {
+        PoiId(format!("{}{}", POI_ID_PREFIX, uuid::Uuid::new_v4()))
+    }
+
+    /// Returns this id if no POI of `model` has it, else the first of
+    /// `<id>-2`, `<id>-3`… that is unused
+    pub fn unique_in(self, model: &Model) -> PoiId {
+        if !model.pois.contains_key(&self.0) {
+            return self;
+        }
+        (2..)
+            .map(|n| format!("{}-{}", self.0, n))
+            .find(|candidate| !model.pois.contains_key(candidate))
+            .map(PoiId)
+            .expect("an unused id is always found")
+    }
+
+    /// Returns the id as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for PoiId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for PoiId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<PoiId> for String {
+    fn from(id: PoiId) -> String {
+        id.0
+    }
+}
@@ -0,0 +1,316 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use crate::Result;
+use anyhow::{anyhow, bail};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{Read, Seek, Write};
+
+/// Name of the archive member holding the checksums of the other members
+pub(crate) const CHECKSUMS_FILE: &str = "checksums.txt";
+
+/// Writes the members of a `.poi` archive, keeping track of their SHA-256
+/// checksums, which are written in [CHECKSUMS_FILE] on
+/// [ArchiveWriter::finish].
+pub(crate) struct ArchiveWriter<W: Write + Seek> {
+    zip: zip::ZipWriter<W>,
+    zip64: bool,
+    current: Option<Member>,
+    checksums: Vec<(String, String)>,
+}
+
+/// The member being written
+struct Member {
+    name: String,
+    hasher: Sha256,
+}
+
+impl<W: Write + Seek> ArchiveWriter<W> {
+    /// Creates a writer. With `zip64`, members are written with zip64
+    /// extensions, and can then exceed 4 GiB.
+    pub(crate) fn new(writer: W, zip64: bool) -> Self {
+        ArchiveWriter {
+            zip: zip::ZipWriter::new(writer),
+            zip64,
+            current: None,
+            checksums: Vec::new(),
+        }
+    }
+
+    /// Options of the members. Timestamps and permissions are fixed, so
+    /// that the same content always gives the same archive.
+    fn file_options(&self) -> zip::write::FileOptions {
+        zip::write::FileOptions::default()
+            .last_modified_time(zip::DateTime::default())
+            .unix_permissions(0o644)
+            .large_file(self.zip64)
+    }
+
+    /// Starts a new member, subsequent writes go into it
+    pub(crate) fn start_file(&mut self, name: &str) -> Result<()> {
+        self.end_file();
+        self.zip.start_file(name, self.file_options())?;
+        self.current = Some(Member {
+            name: name.to_string(),
+            hasher: Sha256::new(),
+        });
+        Ok(())
+    }
+
+    fn end_file(&mut self) {
+        if let Some(member) = self.current.take() {
+            self.checksums
+                .push((member.name, to_hex(&member.hasher.finalize())));
+        }
+    }
+
+    /// Writes the checksums and the central directory of the archive
+    pub(crate) fn finish(mut self) -> Result<W> {
+        self.end_file();
+        self.zip.start_file(CHECKSUMS_FILE, self.file_options())?;
+        for (name, checksum) in &self.checksums {
+            writeln!(self.zip, "{}  {}", checksum, name)?;
+        }
+        Ok(self.zip.finish()?)
+    }
+}
+
+impl<W: Write + Seek> Write for ArchiveWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.zip.write(buf)?;
+        if let Some(member) = &mut self.current {
+            member.hasher.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.zip.flush()
+    }
+}
+
+/// Checks the members of the archive against the checksums listed in its
+/// [CHECKSUMS_FILE], every other member having to be listed
+pub(crate) fn verify_checksums<R: Read + Seek>(zip: &mut zip::ZipArchive<R>) -> Result<()> {
+    let mut checksums = String::new();
+    zip.by_name(CHECKSUMS_FILE)
+        .map_err(|_| {
+            anyhow!(
+                "no {} in the archive, its integrity cannot be verified",
+                CHECKSUMS_FILE
+            )
+        })?
+        .read_to_string(&mut checksums)?;
+    let mut listed = HashSet::new();
+    for line in checksums.lines().filter(|line| !line.trim().is_empty()) {
+        let (expected, name) = line
+            .split_once("  ")
+            .ok_or_else(|| anyhow!("invalid line '{}' in {}", line, CHECKSUMS_FILE))?;
+        let mut member = zip.by_name(name).map_err(|_| {
+            anyhow!(
+                "'{}' is listed in {} but missing from the archive",
+                name,
+                CHECKSUMS_FILE
+            )
+        })?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut member, &mut hasher)?;
+        let actual = to_hex(&hasher.finalize());
+        if actual != expected {
+            bail!(
+                "checksum mismatch for '{}': expected {}, found {}",
+                name,
+                expected,
+                actual
+            );
+        }
+        listed.insert(name);
+    }
+    if let Some(name) = zip
+        .file_names()
+        .find(|name| *name != CHECKSUMS_FILE && !listed.contains(name))
+    {
+        bail!("'{}' is not listed in {}", name, CHECKSUMS_FILE);
+    }
+    Ok(())
+}
+
+/// An archive being read. When its content is at hand (see
+/// [ArchiveReader::mapped]), its stored members are read in place instead
+/// of through the zip reader, once their CRC-32 is checked.
+pub(crate) struct ArchiveReader<'a, R> {
+    pub(crate) zip: zip::ZipArchive<R>,
+    content: Option<&'a [u8]>,
+}
+
+impl<R: Read + Seek> ArchiveReader<'_, R> {
+    pub(crate) fn new(zip: zip::ZipArchive<R>) -> Self {
+        ArchiveReader { zip, content: None }
+    }
+
+    /// Opens the member `name`
+    pub(crate) fn by_name(&mut self, name: &str) -> zip::result::ZipResult<ArchiveMember<'_>> {
+        let file = self.zip.by_name(name)?;
+        match self.content {
+            Some(content) if file.compression() == zip::CompressionMethod::Stored => {
+                let start = file.data_start() as usize;
+                let bytes = content
+                    .get(start..start + file.size() as usize)
+                    .ok_or(zip::result::ZipError::InvalidArchive("truncated member"))?;
+                // the zip reader checks the CRC-32 at the end of the member,
+                // it is checked upfront here
+                if crc32fast::hash(bytes) != file.crc32() {
+                    return Err(zip::result::ZipError::InvalidArchive(
+                        "checksum mismatch of a stored member",
+                    ));
+                }
+                Ok(ArchiveMember::InPlace {
+                    name: file.name().to_string(),
+                    bytes,
+                })
+            }
+            _ => Ok(ArchiveMember::Zipped(Box::new(file))),
+        }
+    }
+
+    /// Opens the member `name`, if the archive has one
+    pub(crate) fn optional_by_name(&mut self, name: &str) -> Result<Option<ArchiveMember<'_>>> {
+        match self.by_name(name) {
+            Ok(member) => Ok(Some(member)),
+            Err(zip::result::ZipError::FileNotFound) => Ok(None),
+            Err(e) => Err(anyhow::Error::new(e).context(format!("cannot read '{}'", name))),
+        }
+    }
+}
+
+impl<'a> ArchiveReader<'a, std::io::Cursor<&'a [u8]>> {
+    /// Reads the archive held by `content`, like a memory-mapped file
+    pub(crate) fn mapped(content: &'a [u8]) -> Result<Self> {
+        Ok(ArchiveReader {
+            zip: zip::ZipArchive::new(std::io::Cursor::new(content))?,
+            content: Some(content),
+        })
+    }
+}
+
+/// A member of an archive being read
+pub(crate) enum ArchiveMember<'a> {
+    /// Read through the zip reader, decompressing it if needed
+    Zipped(Box<zip::read::ZipFile<'a>>),
+    /// Stored member, read in place
+    InPlace { name: String, bytes: &'a [u8] },
+}
+
+impl ArchiveMember<'_> {
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            ArchiveMember::Zipped(file) => file.name(),
+            ArchiveMember::InPlace { name, .. } => name,
+        }
+    }
+
+    /// Uncompressed size of the member
+    pub(crate) fn size(&self) -> u64 {
+        match self {
+            ArchiveMember::Zipped(file) => file.size(),
+            ArchiveMember::InPlace { bytes, .. } => bytes.len() as u64,
+        }
+    }
+}
+
+impl Read for ArchiveMember<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            ArchiveMember::Zipped(file) => file.read(buf),
+            ArchiveMember::InPlace { bytes, .. } => bytes.read(buf),
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_back(content: Vec<u8>) -> zip::ZipArchive<std::io::Cursor<Vec<u8>>> {
+        zip::ZipArchive::new(std::io::Cursor::new(content)).unwrap()
+    }
+
+    #[test]
+    fn more_than_65535_members() {
+        let mut archive = ArchiveWriter::new(std::io::Cursor::new(Vec::new()), true);
+        for i in 0..70_000 {
+            archive.start_file(&format!("{}.txt", i)).unwrap();
+            writeln!(archive, "{}", i).unwrap();
+        }
+        let mut zip = read_back(archive.finish().unwrap().into_inner());
+
+        assert_eq!(zip.len(), 70_001);
+        verify_checksums(&mut zip).unwrap();
+        let mut content = String::new();
+        zip.by_name("69999.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "69999\n");
+    }
+
+    #[test]
+    fn stored_members_read_in_place_are_checked() {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("poi.txt", options).unwrap();
+        zip.write_all(b"poi_id;poi_name\npoi:1;Gare\n").unwrap();
+        let mut content = zip.finish().unwrap().into_inner();
+
+        {
+            let mut archive = ArchiveReader::mapped(&content).unwrap();
+            let mut member = archive.by_name("poi.txt").unwrap();
+            assert!(matches!(member, ArchiveMember::InPlace { .. }));
+            let mut text = String::new();
+            member.read_to_string(&mut text).unwrap();
+            assert_eq!(text, "poi_id;poi_name\npoi:1;Gare\n");
+        }
+
+        let offset = content.windows(4).position(|w| w == b"Gare").unwrap();
+        content[offset] = b'B';
+        let mut archive = ArchiveReader::mapped(&content).unwrap();
+        assert!(archive.by_name("poi.txt").is_err());
+    }
+
+    #[test]
+    #[ignore = "streams more than 4 GiB"]
+    fn member_larger_than_4_gib() {
+        const SIZE: u64 = (1 << 32) + 1024;
+        let file = tempfile::tempfile().unwrap();
+        let mut archive = ArchiveWriter::new(file, true);
+        archive.start_file("large.txt").unwrap();
+        let chunk = vec![b'x'; 1 << 20];
+        let mut written = 0;
+        while written < SIZE {
+            let n = chunk.len().min((SIZE - written) as usize);
+            archive.write_all(&chunk[..n]).unwrap();
+            written += n as u64;
+        }
+        let mut zip = zip::ZipArchive::new(archive.finish().unwrap()).unwrap();
+
+        assert_eq!(zip.by_name("large.txt").unwrap().size(), SIZE);
+        verify_checksums(&mut zip).unwrap();
+    }
+}
@@ -0,0 +1,427 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Binary snapshots of models, behind the `binary` feature
+//!
+//! A snapshot starts with [BINARY_MAGIC] and the version of its format, a
+//! little-endian `u32`, followed by the model encoded with [bincode]. It is
+//! meant for the warm restarts of services, which read it back much faster
+//! than the CSV files of an archive: it is not an exchange format, and
+//! snapshots of another version are rejected rather than converted.
+//!
+//! The lengths of the strings and lists are read from the snapshot, so the
+//! decoding is bounded by the length of the snapshot: a corrupt or crafted
+//! length fails the load instead of allocating that much memory.
+
+use crate::accessibility::{Access, Accessibility};
+use crate::trace::Traced;
+use crate::{Coord, Metadata, Model, Poi, PoiRelation, PoiType, Result, Weight};
+use anyhow::{anyhow, bail, Context};
+use bincode::Options;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// First bytes of a binary snapshot
+pub const BINARY_MAGIC: &[u8; 8] = b"POIMODEL";
+
+/// Version of the binary snapshot format, bumped on each change of the
+/// format
+pub const BINARY_VERSION: u32 = 1;
+
+/// A date as seconds and nanoseconds since the epoch
+type Timestamp = (i64, u32);
+
+fn to_timestamp(date: Option<DateTime<Utc>>) -> Option<Timestamp> {
+    date.map(|date| (date.timestamp(), date.timestamp_subsec_nanos()))
+}
+
+fn from_timestamp(timestamp: Option<Timestamp>) -> Result<Option<DateTime<Utc>>> {
+    timestamp
+        .map(|(secs, nanos)| {
+            Utc.timestamp_opt(secs, nanos)
+                .single()
+                .ok_or_else(|| anyhow!("invalid timestamp {}.{:09}", secs, nanos))
+        })
+        .transpose()
+}
+
+/// The model as encoded, borrowing the POIs when written. Unlike the serde
+/// implementations of the objects, it writes every field, as bincode needs.
+#[derive(Serialize, Deserialize)]
+struct BinaryModel<'a> {
+    pois: Vec<BinaryPoi<'a>>,
+    poi_types: Vec<BinaryPoiType>,
+    metadata: Option<BinaryMetadata>,
+    relations: Vec<(String, String, String)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryPoi<'a> {
+    id: Cow<'a, str>,
+    name: Cow<'a, str>,
+    lon: f64,
+    lat: f64,
+    poi_type_id: Cow<'a, str>,
+    properties: Cow<'a, BTreeMap<String, String>>,
+    visible: bool,
+    weight: f64,
+    accessibility: [Option<Access>; 4],
+    elevation_m: Option<f64>,
+    created_at: Option<Timestamp>,
+    updated_at: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryPoiType {
+    id: String,
+    name: String,
+    parent_id: Option<String>,
+    icon: Option<String>,
+    color: Option<String>,
+    zoom_min: Option<u8>,
+    visible_by_default: Option<bool>,
+    translations: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryMetadata {
+    provider: Option<String>,
+    extracted_at: Option<Timestamp>,
+    source_version: Option<String>,
+    license: Option<String>,
+    bbox: Option<[f64; 4]>,
+}
+
+impl<'a> From<&'a Poi> for BinaryPoi<'a> {
+    fn from(poi: &'a Poi) -> BinaryPoi<'a> {
+        let accessibility = &poi.accessibility;
+        BinaryPoi {
+            id: Cow::Borrowed(&poi.id),
+            name: Cow::Borrowed(&poi.name),
+            lon: poi.coord.lon(),
+            lat: poi.coord.lat(),
+            poi_type_id: Cow::Borrowed(&poi.poi_type_id),
+            properties: Cow::Borrowed(&poi.properties),
+            visible: poi.visible,
+            weight: poi.weight.value(),
+            accessibility: [
+                accessibility.wheelchair,
+                accessibility.visual_aids,
+                accessibility.audio_aids,
+                accessibility.elevator,
+            ],
+            elevation_m: poi.elevation_m,
+            created_at: to_timestamp(poi.created_at),
+            updated_at: to_timestamp(poi.updated_at),
+        }
+    }
+}
+
+impl BinaryPoi<'_> {
+    fn into_poi(self) -> Result<Poi> {
+        let [wheelchair, visual_aids, audio_aids, elevator] = self.accessibility;
+        Ok(Poi {
+            id: self.id.into_owned(),
+            name: self.name.into_owned(),
+            coord: Coord::new(self.lon, self.lat),
+            poi_type_id: self.poi_type_id.into_owned(),
+            properties: self.properties.into_owned(),
+            visible: self.visible,
+            weight: Weight(self.weight),
+            accessibility: Accessibility {
+                wheelchair,
+                visual_aids,
+                audio_aids,
+                elevator,
+            },
+            elevation_m: self.elevation_m,
+            created_at: from_timestamp(self.created_at)?,
+            updated_at: from_timestamp(self.updated_at)?,
+        })
+    }
+}
+
+impl From<&PoiType> for BinaryPoiType {
+    fn from(poi_type: &PoiType) -> BinaryPoiType {
+        BinaryPoiType {
+            id: poi_type.id.clone(),
+            name: poi_type.name.clone(),
+            parent_id: poi_type.parent_id.clone(),
+            icon: poi_type.icon.clone(),
+            color: poi_type.color.clone(),
+            zoom_min: poi_type.zoom_min,
+            visible_by_default: poi_type.visible_by_default,
+            translations: poi_type.translations.clone(),
+        }
+    }
+}
+
+impl From<BinaryPoiType> for PoiType {
+    fn from(poi_type: BinaryPoiType) -> PoiType {
+        PoiType {
+            id: poi_type.id,
+            name: poi_type.name,
+            parent_id: poi_type.parent_id,
+            icon: poi_type.icon,
+            color: poi_type.color,
+            zoom_min: poi_type.zoom_min,
+            visible_by_default: poi_type.visible_by_default,
+            translations: poi_type.translations,
+        }
+    }
+}
+
+/// Writes `model` as a binary snapshot to `writer`: [BINARY_MAGIC], then
+/// [BINARY_VERSION] as a little-endian `u32`, then the model encoded with
+/// bincode.
+///
+/// Snapshots are meant for the warm restarts of services, being read much
+/// faster than archives, not for exchanging models.
+pub fn write_model_to_binary_writer<W: Write>(model: &Model, mut writer: W) -> Result<()> {
+    let binary = BinaryModel {
+        pois: model.pois.values().map(BinaryPoi::from).collect(),
+        poi_types: model.poi_types.values().map(BinaryPoiType::from).collect(),
+        metadata: model.metadata.as_ref().map(|metadata| BinaryMetadata {
+            provider: metadata.provider.clone(),
+            extracted_at: to_timestamp(metadata.extracted_at),
+            source_version: metadata.source_version.clone(),
+            license: metadata.license.clone(),
+            bbox: metadata.bbox,
+        }),
+        relations: model
+            .relations
+            .iter()
+            .map(|relation| {
+                (
+                    relation.parent_id.clone(),
+                    relation.child_id.clone(),
+                    relation.relation_type.clone(),
+                )
+            })
+            .collect(),
+    };
+    writer.write_all(BINARY_MAGIC)?;
+    writer.write_all(&BINARY_VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut writer, &binary)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads a binary snapshot from `reader`, failing if it is not a snapshot,
+/// or a snapshot of another version of the format.
+///
+/// At most `limit` bytes are decoded after the header, which bounds the
+/// memory allocated for the strings and lists of the model: give the
+/// length of the snapshot. A snapshot needing more fails to load.
+pub fn load_model_from_binary_reader<R: Read>(mut reader: R, limit: u64) -> Result<Model> {
+    let mut header = [0; 12];
+    reader
+        .read_exact(&mut header)
+        .context("cannot read the header of the binary snapshot")?;
+    if &header[..8] != BINARY_MAGIC {
+        bail!("not a binary snapshot of a model");
+    }
+    let version = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+    if version != BINARY_VERSION {
+        bail!(
+            "unsupported version {} of binary snapshot, expected {}",
+            version,
+            BINARY_VERSION
+        );
+    }
+    let binary: BinaryModel<'static> = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_limit(limit)
+        .deserialize_from(reader)
+        .context("cannot decode the binary snapshot")?;
+    Ok(Model {
+        pois: binary
+            .pois
+            .into_iter()
+            .map(|poi| poi.into_poi().map(|poi| (poi.id.clone(), poi)))
+            .collect::<Result<_>>()?,
+        poi_types: binary
+            .poi_types
+            .into_iter()
+            .map(|poi_type| (poi_type.id.clone(), PoiType::from(poi_type)))
+            .collect(),
+        metadata: match binary.metadata {
+            Some(metadata) => Some(Metadata {
+                provider: metadata.provider,
+                extracted_at: from_timestamp(metadata.extracted_at)?,
+                source_version: metadata.source_version,
+                license: metadata.license,
+                bbox: metadata.bbox,
+                dialect: None,
+            }),
+            None => None,
+        },
+        relations: binary
+            .relations
+            .into_iter()
+            .map(|(parent_id, child_id, relation_type)| PoiRelation {
+                parent_id,
+                child_id,
+                relation_type,
+            })
+            .collect(),
+    })
+}
+
+/// Writes `model` as a binary snapshot to the file `path`
+pub fn write_model_to_binary(model: &Model, path: &Path) -> Result<()> {
+    let traced = Traced::start("save", path.display());
+    let result = File::create(path)
+        .with_context(|| format!("cannot create {}", path.display()))
+        .and_then(|file| write_model_to_binary_writer(model, BufWriter::new(file)));
+    traced.finish_with(result, |_| model.pois.len())
+}
+
+/// Reads the binary snapshot of the file `path`
+pub fn load_model_from_binary(path: &Path) -> Result<Model> {
+    let traced = Traced::start("load", path.display());
+    let result = File::open(path)
+        .with_context(|| format!("cannot open {}", path.display()))
+        .and_then(|file| {
+            let limit = file.metadata()?.len();
+            load_model_from_binary_reader(BufReader::new(file), limit)
+        })
+        .with_context(|| format!("cannot load binary snapshot {}", path.display()));
+    traced.finish_with(result, |model| model.pois.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        load_model_from_binary, load_model_from_binary_reader, write_model_to_binary,
+        write_model_to_binary_writer, BINARY_MAGIC, BINARY_VERSION,
+    };
+    use crate::accessibility::{Access, Accessibility};
+    use crate::{Coord, Metadata, Model, Poi, PoiRelation, PoiType};
+    use chrono::{TimeZone, Utc};
+
+    fn model() -> Model {
+        let date = Utc.with_ymd_and_hms(2021, 3, 4, 5, 6, 7).unwrap();
+        let mut model: Model = vec![
+            Poi {
+                id: "station".to_string(),
+                name: "Gare de Lyon".to_string(),
+                coord: Coord::new(2.373, 48.844),
+                poi_type_id: "amenity:railway".to_string(),
+                properties: vec![("operator".to_string(), "SNCF".to_string())]
+                    .into_iter()
+                    .collect(),
+                accessibility: Accessibility {
+                    wheelchair: Some(Access::Yes),
+                    elevator: Some(Access::Limited),
+                    ..Accessibility::default()
+                },
+                elevation_m: Some(35.5),
+                created_at: Some(date),
+                updated_at: Some(date),
+                ..Poi::default()
+            },
+            Poi {
+                id: "entrance".to_string(),
+                poi_type_id: "amenity:railway".to_string(),
+                ..Poi::default()
+            },
+        ]
+        .into_iter()
+        .collect();
+        model.poi_types.insert(
+            "amenity:railway".to_string(),
+            PoiType {
+                id: "amenity:railway".to_string(),
+                name: "Gare".to_string(),
+                translations: vec![("en".to_string(), "Station".to_string())]
+                    .into_iter()
+                    .collect(),
+                ..PoiType::default()
+            },
+        );
+        model.relations.insert(PoiRelation {
+            parent_id: "station".to_string(),
+            child_id: "entrance".to_string(),
+            relation_type: PoiRelation::ENTRANCE.to_string(),
+        });
+        model.metadata = Some(Metadata {
+            provider: Some("osm".to_string()),
+            extracted_at: Some(date),
+            bbox: Some([2.3, 48.8, 2.4, 48.9]),
+            ..Metadata::default()
+        });
+        model
+    }
+
+    fn snapshot(model: &Model) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_model_to_binary_writer(model, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let model = model();
+        let bytes = snapshot(&model);
+        let loaded = load_model_from_binary_reader(&bytes[..], bytes.len() as u64).unwrap();
+        assert_eq!(loaded, model);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.bin");
+        write_model_to_binary(&model, &path).unwrap();
+        assert_eq!(load_model_from_binary(&path).unwrap(), model);
+    }
+
+    #[test]
+    fn truncated_snapshots_fail() {
+        let bytes = snapshot(&model());
+        for len in [0, 8, 12, 20, bytes.len() / 2, bytes.len() - 1] {
+            let truncated = &bytes[..len];
+            assert!(load_model_from_binary_reader(truncated, len as u64).is_err());
+        }
+    }
+
+    #[test]
+    fn corrupt_snapshots_fail() {
+        // one POI whose id is 4 TiB long
+        let mut bytes = BINARY_MAGIC.to_vec();
+        bytes.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+        bytes.extend_from_slice(&(1u64 << 42).to_le_bytes());
+        assert!(load_model_from_binary_reader(&bytes[..], bytes.len() as u64).is_err());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.bin");
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(load_model_from_binary(&path).is_err());
+
+        let mut bytes = snapshot(&model());
+        bytes[0] = b'X';
+        assert!(load_model_from_binary_reader(&bytes[..], bytes.len() as u64).is_err());
+
+        let mut bytes = snapshot(&model());
+        bytes[8] = 2;
+        assert!(load_model_from_binary_reader(&bytes[..], bytes.len() as u64).is_err());
+
+        // the length of the list of the POIs exceeds what is left
+        let mut bytes = snapshot(&model());
+        bytes[12..20].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(load_model_from_binary_reader(&bytes[..], bytes.len() as u64).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Tarballs (`.tar` and `.tar.gz`) holding the members of a `.poi` archive,
+//! as some providers can only deliver them. They are repacked in memory:
+//! into a zip archive to be loaded, and from one to be written.
+
+use crate::Result;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::borrow::Cow;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+/// Format of the file holding the members of an archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Container {
+    /// A plain `.poi` zip archive
+    Zip,
+    /// A `.tar` tarball
+    Tar,
+    /// A gzipped tarball, `.tar.gz` or `.tgz`
+    TarGz,
+}
+
+impl Container {
+    /// The container written at `path`, from its extension
+    pub(crate) fn from_path(path: &Path) -> Container {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Container::TarGz
+        } else if name.ends_with(".tar") {
+            Container::Tar
+        } else {
+            Container::Zip
+        }
+    }
+
+    /// The container of `content`, from its magic bytes. Only the first
+    /// [MAGIC_LEN] bytes are needed.
+    pub(crate) fn sniff(content: &[u8]) -> Container {
+        if content.starts_with(&[0x1f, 0x8b]) {
+            Container::TarGz
+        } else if content.get(257..MAGIC_LEN) == Some(b"ustar") {
+            Container::Tar
+        } else {
+            Container::Zip
+        }
+    }
+}
+
+/// Number of bytes read by [Container::sniff], up to the end of the magic of
+/// tar headers
+pub(crate) const MAGIC_LEN: usize = 262;
+
+/// The zip archive in `content`, repacked from a tarball if needed. The
+/// members are looked up by file name, whatever their directory in the
+/// tarball.
+pub(crate) fn unpack(content: &[u8]) -> Result<Cow<'_, [u8]>> {
+    match Container::sniff(content) {
+        Container::Zip => Ok(Cow::Borrowed(content)),
+        Container::Tar => repack(tar::Archive::new(content)).map(Cow::Owned),
+        Container::TarGz => repack(tar::Archive::new(GzDecoder::new(content))).map(Cow::Owned),
+    }
+}
+
+fn repack<R: Read>(mut tarball: tar::Archive<R>) -> Result<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for entry in tarball.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = match entry.path()?.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        zip.start_file(name, options)?;
+        std::io::copy(&mut entry, &mut zip)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Writes the members of the zip archive `content` as a tarball to
+/// `writer`. Like in the zip archive, timestamps and permissions are fixed.
+pub(crate) fn pack<W: Write>(content: &[u8], container: Container, writer: W) -> Result<()> {
+    match container {
+        Container::Zip => {
+            let mut writer = writer;
+            writer.write_all(content)?;
+            writer.flush()?;
+        }
+        Container::Tar => write_tar(content, writer)?.flush()?,
+        Container::TarGz => write_tar(content, GzEncoder::new(writer, Compression::default()))?
+            .finish()?
+            .flush()?,
+    }
+    Ok(())
+}
+
+fn write_tar<W: Write>(content: &[u8], writer: W) -> Result<W> {
+    let mut zip = zip::ZipArchive::new(Cursor::new(content))?;
+    let mut tarball = tar::Builder::new(writer);
+    for index in 0..zip.len() {
+        let member = zip.by_index(index)?;
+        let mut header = tar::Header::new_ustar();
+        header.set_size(member.size());
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_entry_type(tar::EntryType::Regular);
+        let name = member.name().to_string();
+        tarball.append_data(&mut header, name, member)?;
+    }
+    Ok(tarball.into_inner()?)
+}
@@ -0,0 +1,202 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Detection of the CSV dialect of an archive, for the archives written by
+//! older exporters: comma delimiters, or legacy headers like `id;name` in
+//! `poi_type.txt`
+
+use super::encoding::DecodingReader;
+use super::{ArchiveReader, LoadOptions};
+use csv::ByteRecord;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Delimiters looked for in the headers, when the expected one is absent
+const DELIMITERS: [u8; 4] = [b';', b',', b'\t', b'|'];
+
+/// Legacy headers, as `(file, legacy header, current header)`
+const LEGACY_HEADERS: [(&str, &str, &str); 2] = [
+    ("poi_type.txt", "id", "poi_type_id"),
+    ("poi_type.txt", "name", "poi_type_name"),
+];
+
+/// Usual alternative headers, as `(file, alias, current header)`
+const ALIASES: [(&str, &str, &str); 14] = [
+    ("poi.txt", "id", "poi_id"),
+    ("poi.txt", "type", "poi_type_id"),
+    ("poi.txt", "type_id", "poi_type_id"),
+    ("poi.txt", "poi_type", "poi_type_id"),
+    ("poi.txt", "name", "poi_name"),
+    ("poi.txt", "lat", "poi_lat"),
+    ("poi.txt", "latitude", "poi_lat"),
+    ("poi.txt", "lon", "poi_lon"),
+    ("poi.txt", "lng", "poi_lon"),
+    ("poi.txt", "longitude", "poi_lon"),
+    ("poi.txt", "weight", "poi_weight"),
+    ("poi.txt", "visible", "poi_visible"),
+    ("poi_properties.txt", "id", "poi_id"),
+    ("poi_properties.txt", "property_key", "key"),
+];
+
+/// Alternative names of the headers of the CSV files, replaced on load by
+/// the current ones, unless the current ones are already there. Aliases
+/// are matched regardless of the case and of the surrounding whitespaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderAliases {
+    /// Current header of each alias, trimmed and lowercased, by file
+    pub aliases: HashMap<String, HashMap<String, String>>,
+}
+
+impl Default for HeaderAliases {
+    /// The built-in aliases: the legacy headers, and the usual alternative
+    /// ones, like `lat`, `latitude` or `name` in `poi.txt`
+    fn default() -> HeaderAliases {
+        let mut aliases = HeaderAliases::legacy();
+        for (file, alias, header) in ALIASES.iter() {
+            aliases.insert(file, alias, header);
+        }
+        aliases
+    }
+}
+
+impl HeaderAliases {
+    /// Only the legacy headers
+    fn legacy() -> HeaderAliases {
+        let mut aliases = HeaderAliases {
+            aliases: HashMap::new(),
+        };
+        for (file, alias, header) in LEGACY_HEADERS.iter() {
+            aliases.insert(file, alias, header);
+        }
+        aliases
+    }
+
+    /// Adds `alias` as an alternative name of `header` in `file`, replacing
+    /// the built-in one if any
+    pub fn insert(&mut self, file: &str, alias: &str, header: &str) {
+        self.aliases
+            .entry(file.to_string())
+            .or_default()
+            .insert(alias.trim().to_lowercase(), header.to_string());
+    }
+
+    /// Returns the current header of which `header`, in `file`, is an alias
+    pub fn current(&self, file: &str, header: &[u8]) -> Option<&str> {
+        let header = String::from_utf8_lossy(header).trim().to_lowercase();
+        self.aliases.get(file)?.get(&header).map(String::as_str)
+    }
+}
+
+/// The CSV dialect of a loaded archive, when it differs from the expected
+/// one. It is reported in the [Metadata](crate::Metadata) of the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dialect {
+    /// Field delimiter of the CSV files
+    pub delimiter: u8,
+
+    /// Files using legacy headers, like `id;name` in `poi_type.txt`
+    pub legacy_headers: Vec<String>,
+}
+
+/// Detects the dialect of the archive, from the headers of its files.
+/// Returns `None` when it is the one of the options.
+///
+/// The delimiter is guessed from the headers of `poi.txt`, and used for all
+/// the files.
+pub(super) fn detect<R>(zip: &mut ArchiveReader<'_, R>, options: &LoadOptions) -> Option<Dialect>
+where
+    R: Read + std::io::Seek,
+{
+    let expected = options.csv.delimiter;
+    let delimiter = first_line(zip, "poi.txt", options)
+        .filter(|line| !line.contains(&expected))
+        .and_then(|line| {
+            DELIMITERS
+                .iter()
+                .copied()
+                .map(|delimiter| (delimiter, bytecount(&line, delimiter)))
+                .filter(|(_, count)| *count > 0)
+                .max_by_key(|(_, count)| *count)
+                .map(|(delimiter, _)| delimiter)
+        })
+        .unwrap_or(expected);
+
+    let legacy_headers: Vec<String> = LEGACY_HEADERS
+        .iter()
+        .map(|(file, _, _)| *file)
+        .dedup()
+        .filter(|file| {
+            first_line(zip, file, options).is_some_and(|line| {
+                let headers: ByteRecord = line.split(|byte| *byte == delimiter).collect();
+                rename_headers(file, &headers, &HeaderAliases::legacy()) != headers
+            })
+        })
+        .map(str::to_string)
+        .collect();
+
+    if delimiter == expected && legacy_headers.is_empty() {
+        None
+    } else {
+        Some(Dialect {
+            delimiter,
+            legacy_headers,
+        })
+    }
+}
+
+/// Renames the headers of `file` that are aliases to the current ones,
+/// unless the current ones are already there
+pub(super) fn rename_headers(
+    file: &str,
+    headers: &ByteRecord,
+    aliases: &HeaderAliases,
+) -> ByteRecord {
+    headers
+        .iter()
+        .map(|header| {
+            aliases
+                .current(file, header)
+                .filter(|current| !headers.iter().any(|h| h == current.as_bytes()))
+                .map_or(header, str::as_bytes)
+        })
+        .collect()
+}
+
+/// The first line of `file`, without its terminator, if any
+fn first_line<R>(
+    zip: &mut ArchiveReader<'_, R>,
+    file: &str,
+    options: &LoadOptions,
+) -> Option<Vec<u8>>
+where
+    R: Read + std::io::Seek,
+{
+    let member = zip.by_name(file).ok()?;
+    let mut head = Vec::new();
+    DecodingReader::new(member, options.encoding)
+        .take(4096)
+        .read_to_end(&mut head)
+        .ok()?;
+    let end = head
+        .iter()
+        .position(|byte| *byte == b'\n' || *byte == b'\r')
+        .unwrap_or(head.len());
+    head.truncate(end);
+    Some(head)
+}
+
+fn bytecount(line: &[u8], byte: u8) -> usize {
+    line.iter().filter(|b| **b == byte).count()
+}
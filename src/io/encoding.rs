@@ -0,0 +1,148 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use std::io::Read;
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Character encoding of the files of an archive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8, decoding as ISO-8859-1 (Latin-1) the bytes that are not
+    /// valid UTF-8
+    #[default]
+    Auto,
+
+    /// UTF-8 only, invalid content is an error
+    Utf8,
+
+    /// ISO-8859-1 (Latin-1)
+    Latin1,
+}
+
+/// Wraps a reader, stripping the BOM and converting its content to UTF-8
+pub(crate) struct DecodingReader<R> {
+    inner: R,
+    encoding: Encoding,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    pos: usize,
+    bom_checked: bool,
+    eof: bool,
+}
+
+impl<R: Read> DecodingReader<R> {
+    pub(crate) fn new(inner: R, encoding: Encoding) -> Self {
+        DecodingReader {
+            inner,
+            encoding,
+            input: Vec::new(),
+            output: Vec::new(),
+            pos: 0,
+            bom_checked: false,
+            eof: false,
+        }
+    }
+
+    fn fill(&mut self) -> std::io::Result<()> {
+        let mut chunk = [0; 8192];
+        let n = self.inner.read(&mut chunk)?;
+        if n == 0 {
+            self.eof = true;
+        } else {
+            self.input.extend_from_slice(&chunk[..n]);
+        }
+        if !self.bom_checked {
+            if self.input.len() < BOM.len() && !self.eof {
+                return Ok(());
+            }
+            if self.input.starts_with(BOM) {
+                self.input.drain(..BOM.len());
+            }
+            self.bom_checked = true;
+        }
+
+        self.output.clear();
+        self.pos = 0;
+        let consumed = match self.encoding {
+            Encoding::Utf8 => {
+                self.output.extend_from_slice(&self.input);
+                self.input.len()
+            }
+            Encoding::Latin1 => {
+                for &b in &self.input {
+                    push_latin1(b, &mut self.output);
+                }
+                self.input.len()
+            }
+            Encoding::Auto => decode_auto(&self.input, self.eof, &mut self.output),
+        };
+        self.input.drain(..consumed);
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.output.len() {
+            if self.eof && self.input.is_empty() {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let n = buf.len().min(self.output.len() - self.pos);
+        buf[..n].copy_from_slice(&self.output[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+fn push_latin1(b: u8, output: &mut Vec<u8>) {
+    let mut buf = [0; 2];
+    output.extend_from_slice(char::from(b).encode_utf8(&mut buf).as_bytes());
+}
+
+/// Decodes `input` into `output`, and returns the number of bytes consumed.
+/// An incomplete UTF-8 sequence at the end of `input` is left for the next
+/// call, unless we reached the end of the file.
+fn decode_auto(mut input: &[u8], eof: bool, output: &mut Vec<u8>) -> usize {
+    let total = input.len();
+    loop {
+        match std::str::from_utf8(input) {
+            Ok(s) => {
+                output.extend_from_slice(s.as_bytes());
+                return total;
+            }
+            Err(e) => {
+                let (valid, rest) = input.split_at(e.valid_up_to());
+                output.extend_from_slice(valid);
+                match e.error_len() {
+                    Some(len) => {
+                        for &b in &rest[..len] {
+                            push_latin1(b, output);
+                        }
+                        input = &rest[len..];
+                    }
+                    None if eof => {
+                        for &b in rest {
+                            push_latin1(b, output);
+                        }
+                        return total;
+                    }
+                    None => return total - rest.len(),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use crate::Error;
+use csv::ByteRecord;
+
+/// Maximum number of characters of a record kept in a [CsvError]
+const SNIPPET_LEN: usize = 120;
+
+/// The error of a record of a CSV file of an archive that cannot be read or
+/// decoded. It can be told from other errors with
+/// `error.downcast_ref::<CsvError>()`.
+#[derive(Debug)]
+pub struct CsvError {
+    /// Name of the file, in the archive
+    pub file: String,
+
+    /// Line (1-based) at which the record starts, when known
+    pub line: Option<u64>,
+
+    /// Column (1-based) of the offending field, when known
+    pub column: Option<usize>,
+
+    /// The beginning of the raw record, its fields joined by the delimiter,
+    /// when it could be read
+    pub record: Option<String>,
+
+    source: Error,
+}
+
+impl CsvError {
+    /// The error of `file`, located from `source` and the record being read
+    pub(super) fn new(
+        file: &str,
+        line: Option<u64>,
+        record: Option<(&ByteRecord, u8)>,
+        source: Error,
+    ) -> CsvError {
+        let column = match source.downcast_ref::<FieldError>() {
+            Some(field) => Some(field.index + 1),
+            None => source
+                .downcast_ref::<csv::Error>()
+                .and_then(|error| match error.kind() {
+                    csv::ErrorKind::Deserialize { err, .. } => err.field(),
+                    _ => None,
+                })
+                .map(|index| index as usize + 1),
+        };
+        CsvError {
+            file: file.to_string(),
+            line,
+            column,
+            record: record
+                .filter(|(record, _)| !record.is_empty())
+                .map(|(record, delimiter)| snippet(record, delimiter)),
+            source,
+        }
+    }
+
+    /// What is wrong with the record, without the file and the line
+    pub fn reason(&self) -> String {
+        let mut reason = match self.column {
+            Some(column) => format!("column {}: {}", column, self.source),
+            None => self.source.to_string(),
+        };
+        if let Some(record) = &self.record {
+            reason.push_str(&format!(", in record {:?}", record));
+        }
+        reason
+    }
+}
+
+impl std::fmt::Display for CsvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file, line, self.reason()),
+            None => write!(f, "{}: {}", self.file, self.reason()),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// The error of the decoding of the field at `index` of a record
+#[derive(Debug)]
+pub(super) struct FieldError {
+    pub(super) index: usize,
+    pub(super) source: Error,
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.source, f)
+    }
+}
+
+impl std::error::Error for FieldError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.source()
+    }
+}
+
+/// The fields of `record` joined by `delimiter`, cut to [SNIPPET_LEN]
+/// characters
+fn snippet(record: &ByteRecord, delimiter: u8) -> String {
+    let raw = record.iter().collect::<Vec<_>>().join(&delimiter);
+    let raw = String::from_utf8_lossy(&raw);
+    match raw.char_indices().nth(SNIPPET_LEN) {
+        Some((end, _)) => format!("{}...", &raw[..end]),
+        None => raw.into_owned(),
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use crate::{Model, Poi, PoiType, Result};
+use serde::Serialize;
+use std::io::Write;
+
+/// The bulk action used for each document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkAction {
+    /// Creates or replaces the document
+    Index,
+
+    /// Creates the document, failing if it already exists
+    Create,
+}
+
+/// Where and how the documents are indexed
+#[derive(Debug, Clone)]
+pub struct IndexSettings {
+    /// Name of the index, `munin_poi` by default
+    pub index: String,
+
+    /// The bulk action, [BulkAction::Index] by default
+    pub action: BulkAction,
+}
+
+impl Default for IndexSettings {
+    fn default() -> IndexSettings {
+        IndexSettings {
+            index: "munin_poi".to_string(),
+            action: BulkAction::Index,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ActionMeta<'a> {
+    #[serde(rename = "_index")]
+    index: &'a str,
+    #[serde(rename = "_id")]
+    id: &'a str,
+}
+
+#[derive(Serialize)]
+struct EsCoord {
+    lon: f64,
+    lat: f64,
+}
+
+#[derive(Serialize)]
+struct EsPoiType<'a> {
+    id: &'a str,
+    name: &'a str,
+}
+
+#[derive(Serialize)]
+struct EsProperty<'a> {
+    key: &'a str,
+    value: &'a str,
+}
+
+/// A POI, shaped like the documents of mimir
+#[derive(Serialize)]
+struct EsPoi<'a> {
+    id: &'a str,
+    label: &'a str,
+    name: &'a str,
+    coord: EsCoord,
+    approx_coord: EsCoord,
+    weight: f64,
+    poi_type: EsPoiType<'a>,
+    properties: Vec<EsProperty<'a>>,
+    administrative_regions: [(); 0],
+    zip_codes: [(); 0],
+}
+
+impl<'a> EsPoi<'a> {
+    fn new(poi: &'a Poi, poi_type: Option<&'a PoiType>) -> Self {
+        EsPoi {
+            id: &poi.id,
+            label: &poi.name,
+            name: &poi.name,
+            coord: EsCoord {
+                lon: poi.coord.lon(),
+                lat: poi.coord.lat(),
+            },
+            approx_coord: EsCoord {
+                lon: poi.coord.lon(),
+                lat: poi.coord.lat(),
+            },
+            weight: poi.weight.value(),
+            poi_type: EsPoiType {
+                id: &poi.poi_type_id,
+                name: poi_type.map_or("", |poi_type| &poi_type.name),
+            },
+            properties: poi
+                .properties
+                .iter()
+                .map(|(key, value)| EsProperty { key, value })
+                .collect(),
+            administrative_regions: [],
+            zip_codes: [],
+        }
+    }
+}
+
+/// Writes the POIs of the model as an Elasticsearch bulk request (NDJSON):
+/// an action line followed by a document line per POI.
+///
+/// Documents follow the shape of mimir's POIs: `id`, `label`, `name`,
+/// `coord`, `approx_coord`, `weight`, `poi_type` (`id` and `name`) and
+/// `properties` (list of `key` / `value`). Administrative regions and zip
+/// codes are left empty.
+pub fn write_es_bulk<W>(model: &Model, mut writer: W, settings: &IndexSettings) -> Result<()>
+where
+    W: Write,
+{
+    let action = match settings.action {
+        BulkAction::Index => "index",
+        BulkAction::Create => "create",
+    };
+    for poi in model.pois.values() {
+        let meta = ActionMeta {
+            index: &settings.index,
+            id: &poi.id,
+        };
+        write!(writer, "{{\"{}\":", action)?;
+        serde_json::to_writer(&mut writer, &meta)?;
+        writeln!(writer, "}}")?;
+        let poi_type = model.poi_types.get(&poi.poi_type_id);
+        serde_json::to_writer(&mut writer, &EsPoi::new(poi, poi_type))?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+use super::{write_csv, CsvOptions};
+use crate::{Model, Poi, Result, Weight};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io::Write;
+
+/// A POI joined with the name of its type and its properties
+#[derive(Debug, Serialize)]
+struct FlatRecord<'a> {
+    poi_id: &'a str,
+    poi_type_id: &'a str,
+    poi_type_name: Option<&'a str>,
+    poi_name: &'a str,
+    poi_lat: f64,
+    poi_lon: f64,
+    poi_weight: Weight,
+    #[serde(serialize_with = "super::ser_from_bool")]
+    poi_visible: bool,
+    poi_created_at: Option<DateTime<Utc>>,
+    poi_updated_at: Option<DateTime<Utc>>,
+    /// Properties, as a JSON object
+    poi_properties: String,
+}
+
+impl<'a> FlatRecord<'a> {
+    fn new(model: &'a Model, poi: &'a Poi) -> Result<FlatRecord<'a>> {
+        Ok(FlatRecord {
+            poi_id: &poi.id,
+            poi_type_id: &poi.poi_type_id,
+            poi_type_name: model
+                .poi_types
+                .get(&poi.poi_type_id)
+                .map(|poi_type| poi_type.name.as_str()),
+            poi_name: &poi.name,
+            poi_lat: poi.coord.lat(),
+            poi_lon: poi.coord.lon(),
+            poi_weight: poi.weight,
+            poi_visible: poi.visible,
+            poi_created_at: poi.created_at,
+            poi_updated_at: poi.updated_at,
+            poi_properties: serde_json::to_string(&poi.properties)?,
+        })
+    }
+}
+
+/// Writes the POIs of the model as a single comma-separated CSV file.
+///
+/// Each row holds the columns of `poi.txt`, along with the name of the POI
+/// type (`poi_type_name`, empty for an unknown type) and the properties of
+/// the POI as a JSON object (`poi_properties`).
+pub fn write_flat_csv<W>(model: &Model, writer: W) -> Result<()>
+where
+    W: Write,
+{
+    let options = CsvOptions {
+        delimiter: b',',
+        ..CsvOptions::default()
+    };
+    write_flat_csv_with_options(model, writer, &options)
+}
+
+/// Same as [write_flat_csv], with the given CSV dialect
+pub fn write_flat_csv_with_options<W>(model: &Model, writer: W, options: &CsvOptions) -> Result<()>
+where
+    W: Write,
+{
+    let records = model
+        .pois
+        .values()
+        .map(|poi| FlatRecord::new(model, poi))
+        .collect::<Result<Vec<_>>>()?;
+    write_csv(writer, options, records.into_iter())
+}
@@ -0,0 +1,170 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use super::encoding::{DecodingReader, Encoding};
+use crate::accessibility::{Access, Accessibility};
+use crate::{Coord, Model, Poi, PoiType, Result, Weight};
+use anyhow::{anyhow, Context};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::path::Path;
+
+/// Describes how the stops of a GTFS or NTFS feed are turned into POIs
+#[derive(Debug, Clone)]
+pub struct StopMapping {
+    /// The `location_type`s of the stops to import, an empty
+    /// `location_type` being `0`. Only stations (`1`) by default.
+    pub location_types: Vec<u8>,
+
+    /// The POI type of the imported stops
+    pub poi_type: PoiType,
+
+    /// Prepended to the `stop_id` to build the id of the POI
+    pub id_prefix: String,
+
+    /// The weight of the imported stops
+    pub weight: Weight,
+}
+
+impl Default for StopMapping {
+    fn default() -> StopMapping {
+        StopMapping {
+            location_types: vec![1],
+            poi_type: PoiType {
+                id: "stop_area".to_string(),
+                name: "Stop area".to_string(),
+                ..PoiType::default()
+            },
+            id_prefix: "poi:".to_string(),
+            weight: Weight::default(),
+        }
+    }
+}
+
+const MAPPED_COLUMNS: [&str; 5] = [
+    "stop_id",
+    "stop_name",
+    "stop_lat",
+    "stop_lon",
+    "location_type",
+];
+
+/// Builds a model from the `stops.txt` of a GTFS or NTFS feed. `path` is
+/// either the `stops.txt` file itself, the directory containing it, or a
+/// zipped feed.
+///
+/// The other non empty columns of the stops (`stop_code`,
+/// `wheelchair_boarding`, ...) are kept as properties. The
+/// `wheelchair_boarding` column also gives the wheelchair accessibility of
+/// the POIs.
+pub fn load_model_from_gtfs_stops<P>(path: P, mapping: &StopMapping) -> Result<Model>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let load = || {
+        if path.is_dir() {
+            read_stops(File::open(path.join("stops.txt"))?, mapping)
+        } else if path.extension() == Some("zip".as_ref()) {
+            let mut zip = zip::ZipArchive::new(File::open(path)?)?;
+            let stops = zip.by_name("stops.txt")?;
+            read_stops(stops, mapping)
+        } else {
+            read_stops(File::open(path)?, mapping)
+        }
+    };
+    load().with_context(|| format!("cannot read stops from '{}'", path.display()))
+}
+
+fn read_stops<R: std::io::Read>(reader: R, mapping: &StopMapping) -> Result<Model> {
+    let mut csv_reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(DecodingReader::new(reader, Encoding::Utf8));
+    let headers = csv_reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or_else(|| anyhow!("missing column '{}'", name))
+    };
+    let id = column("stop_id")?;
+    let name = column("stop_name")?;
+    let lat = column("stop_lat")?;
+    let lon = column("stop_lon")?;
+    let location_type = column("location_type").ok();
+    let wheelchair_boarding = column("wheelchair_boarding").ok();
+
+    let mut pois = BTreeMap::new();
+    for rec in csv_reader.records() {
+        let rec = rec?;
+        let field = |i: usize| rec.get(i).unwrap_or("").trim();
+        let rec_location_type = match location_type.map(field) {
+            None | Some("") => 0,
+            Some(t) => t
+                .parse::<u8>()
+                .with_context(|| format!("invalid location_type for stop '{}'", field(id)))?,
+        };
+        if !mapping.location_types.contains(&rec_location_type) {
+            continue;
+        }
+        let coord = Coord::try_new(
+            field(lon)
+                .parse()
+                .with_context(|| format!("invalid stop_lon for stop '{}'", field(id)))?,
+            field(lat)
+                .parse()
+                .with_context(|| format!("invalid stop_lat for stop '{}'", field(id)))?,
+        )
+        .with_context(|| format!("invalid coordinates for stop '{}'", field(id)))?;
+        let properties = headers
+            .iter()
+            .zip(rec.iter())
+            .filter(|(h, v)| !MAPPED_COLUMNS.contains(&h.trim()) && !v.trim().is_empty())
+            .map(|(h, v)| (h.trim().to_string(), v.trim().to_string()))
+            .collect();
+        let mut poi = Poi {
+            id: format!("{}{}", mapping.id_prefix, field(id)),
+            name: field(name).to_string(),
+            coord,
+            poi_type_id: mapping.poi_type.id.clone(),
+            properties,
+            visible: true,
+            weight: mapping.weight,
+            accessibility: Accessibility::default(),
+            elevation_m: None,
+            created_at: None,
+            updated_at: None,
+        };
+        // 0 or empty: no information, 1: accessible, 2: not accessible
+        let wheelchair = match wheelchair_boarding.map(field) {
+            Some("1") => Some(Access::Yes),
+            Some("2") => Some(Access::No),
+            _ => None,
+        };
+        poi.set_accessibility(Accessibility {
+            wheelchair,
+            ..Accessibility::default()
+        });
+        pois.insert(poi.id.clone(), poi);
+    }
+
+    let mut poi_types = HashMap::new();
+    poi_types.insert(mapping.poi_type.id.clone(), mapping.poi_type.clone());
+    Ok(Model {
+        pois,
+        poi_types,
+        metadata: None,
+        relations: BTreeSet::new(),
+    })
+}
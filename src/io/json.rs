@@ -0,0 +1,183 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use crate::accessibility::Accessibility;
+use crate::{Coord, Model, Poi, PoiType, Result, Weight};
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Read;
+
+/// Describes where the fields of the POIs are in JSON records.
+///
+/// Fields are given as paths of keys separated by `.`, where numbers index
+/// arrays: `geometry.coordinates.0` is the first coordinate of the
+/// `geometry` of a GeoJSON feature. The mapping can be deserialized, from
+/// a JSON or TOML configuration for instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct FieldMapping {
+    /// Path of the array of records in the document, the document itself
+    /// being the array when empty
+    pub records: String,
+
+    /// Path of the id of the POI, a string or a number
+    pub id: String,
+
+    /// Path of the name of the POI, empty if absent
+    pub name: String,
+
+    /// Path of the latitude, a number or a string
+    pub lat: String,
+
+    /// Path of the longitude, a number or a string
+    pub lon: String,
+
+    /// Path of the id of the POI type
+    pub poi_type: String,
+
+    /// POI type of the records without one, which are rejected otherwise
+    pub default_poi_type: Option<String>,
+
+    /// Path of the weight of the POI, 0 if absent
+    pub weight: Option<String>,
+
+    /// Properties of the POI, as paths by property key. Absent values are
+    /// left out.
+    pub properties: BTreeMap<String, String>,
+
+    /// Prepended to the id of the record to build the id of the POI
+    pub id_prefix: String,
+}
+
+/// Builds a model from JSON records, like the dumps of place APIs, whose
+/// fields are found according to `mapping`.
+///
+/// The POI types are those of the records, named after their id. Scalar
+/// values are converted to strings when a string is expected. Records
+/// with the same id replace the previous ones.
+///
+/// ```
+/// use navitia_poi_model::io::FieldMapping;
+/// use navitia_poi_model::Model;
+///
+/// let json = r#"{"results": [
+///     {"uid": 42, "title": "Gare", "geo": {"lat": 48.84, "lng": "2.37"}, "kind": "station"}
+/// ]}"#;
+/// let mapping = FieldMapping {
+///     records: "results".to_string(),
+///     id: "uid".to_string(),
+///     name: "title".to_string(),
+///     lat: "geo.lat".to_string(),
+///     lon: "geo.lng".to_string(),
+///     poi_type: "kind".to_string(),
+///     id_prefix: "poi:".to_string(),
+///     ..FieldMapping::default()
+/// };
+/// let model = Model::try_from_json_records(json.as_bytes(), &mapping).unwrap();
+/// assert_eq!(model.pois["poi:42"].name, "Gare");
+/// assert_eq!(model.pois["poi:42"].coord.lon(), 2.37);
+/// ```
+pub fn load_model_from_json_records<R>(reader: R, mapping: &FieldMapping) -> Result<Model>
+where
+    R: Read,
+{
+    let document: Value = serde_json::from_reader(reader).context("invalid JSON document")?;
+    let records = lookup(&document, &mapping.records)
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("no array of records at '{}'", mapping.records))?;
+
+    let mut pois = BTreeMap::new();
+    let mut poi_types = HashMap::new();
+    for (index, record) in records.iter().enumerate() {
+        let poi = to_poi(record, mapping).with_context(|| format!("invalid record {}", index))?;
+        poi_types
+            .entry(poi.poi_type_id.clone())
+            .or_insert_with(|| PoiType::placeholder(&poi.poi_type_id));
+        pois.insert(poi.id.clone(), poi);
+    }
+    Ok(Model {
+        pois,
+        poi_types,
+        metadata: None,
+        relations: BTreeSet::new(),
+    })
+}
+
+fn to_poi(record: &Value, mapping: &FieldMapping) -> Result<Poi> {
+    let field = |path: &str| lookup(record, path).filter(|value| !value.is_null());
+    let required = |path: &str| field(path).ok_or_else(|| anyhow!("missing field '{}'", path));
+    let number = |path: &str| -> Result<f64> {
+        let value = required(path)?;
+        value
+            .as_f64()
+            .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+            .ok_or_else(|| anyhow!("invalid number {} for field '{}'", value, path))
+    };
+
+    let id = text(required(&mapping.id)?)
+        .ok_or_else(|| anyhow!("invalid id for field '{}'", mapping.id))?;
+    let poi_type_id = match field(&mapping.poi_type).and_then(text) {
+        Some(poi_type_id) => poi_type_id,
+        None => mapping
+            .default_poi_type
+            .clone()
+            .ok_or_else(|| anyhow!("missing field '{}'", mapping.poi_type))?,
+    };
+    let weight = match &mapping.weight {
+        Some(path) if field(path).is_some() => Weight(number(path)?),
+        _ => Weight::default(),
+    };
+    let properties: BTreeMap<String, String> = mapping
+        .properties
+        .iter()
+        .filter_map(|(key, path)| Some((key.clone(), text(field(path)?)?)))
+        .collect();
+    Ok(Poi {
+        id: format!("{}{}", mapping.id_prefix, id),
+        name: field(&mapping.name).and_then(text).unwrap_or_default(),
+        coord: Coord::try_new(number(&mapping.lon)?, number(&mapping.lat)?)?,
+        poi_type_id,
+        accessibility: Accessibility::from_properties(&properties),
+        properties,
+        visible: true,
+        weight,
+        elevation_m: None,
+        created_at: None,
+        updated_at: None,
+    })
+}
+
+/// The value at `path` in `value`, the value itself for an empty path
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Object(object) => object.get(key),
+        Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// A scalar value as a string
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+use crate::{Model, Poi, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+/// The icon style of the placemarks of a POI type
+#[derive(Debug, Clone, Default)]
+pub struct KmlStyle {
+    /// URL of the icon
+    pub icon_href: String,
+
+    /// Color of the icon, as `aabbggrr` hexadecimal digits, if any
+    pub color: Option<String>,
+
+    /// Scale of the icon, if any
+    pub scale: Option<f64>,
+}
+
+/// The styles of the placemarks, by POI type id. Placemarks of the POI
+/// types without a style get the default style of the viewer.
+#[derive(Debug, Clone, Default)]
+pub struct StyleMap {
+    /// Styles by POI type id
+    pub styles: HashMap<String, KmlStyle>,
+}
+
+/// Writes the POIs of the model as a KML document, viewable in Google
+/// Earth.
+///
+/// POIs are written as placemarks, in one folder per POI type. Their
+/// properties are written as extended data, and hidden POIs are written
+/// with a `visibility` of 0.
+pub fn write_kml<W>(model: &Model, mut writer: W, styles: &StyleMap) -> Result<()>
+where
+    W: Write,
+{
+    let mut folders: BTreeMap<&str, Vec<&Poi>> = BTreeMap::new();
+    for poi in model.pois.values() {
+        folders.entry(&poi.poi_type_id).or_default().push(poi);
+    }
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(writer, "<Document>")?;
+    let mut style_ids = HashMap::new();
+    for poi_type_id in folders.keys() {
+        let style = match styles.styles.get(*poi_type_id) {
+            Some(style) => style,
+            None => continue,
+        };
+        let style_id = format!("style{}", style_ids.len());
+        writeln!(writer, r#"<Style id="{}"><IconStyle>"#, style_id)?;
+        if let Some(color) = &style.color {
+            writeln!(writer, "<color>{}</color>", escape(color))?;
+        }
+        if let Some(scale) = style.scale {
+            writeln!(writer, "<scale>{}</scale>", scale)?;
+        }
+        writeln!(
+            writer,
+            "<Icon><href>{}</href></Icon>",
+            escape(&style.icon_href)
+        )?;
+        writeln!(writer, "</IconStyle></Style>")?;
+        style_ids.insert(*poi_type_id, style_id);
+    }
+
+    for (poi_type_id, pois) in &folders {
+        let name = model
+            .poi_types
+            .get(*poi_type_id)
+            .map_or(*poi_type_id, |poi_type| poi_type.name.as_str());
+        writeln!(writer, "<Folder>")?;
+        writeln!(writer, "<name>{}</name>", escape(name))?;
+        for poi in pois {
+            writeln!(writer, "<Placemark>")?;
+            writeln!(writer, "<name>{}</name>", escape(&poi.name))?;
+            if !poi.visible {
+                writeln!(writer, "<visibility>0</visibility>")?;
+            }
+            if let Some(style_id) = style_ids.get(poi_type_id) {
+                writeln!(writer, "<styleUrl>#{}</styleUrl>", style_id)?;
+            }
+            writeln!(writer, "<ExtendedData>")?;
+            writeln!(
+                writer,
+                r#"<Data name="id"><value>{}</value></Data>"#,
+                escape(&poi.id)
+            )?;
+            for (key, value) in &poi.properties {
+                writeln!(
+                    writer,
+                    r#"<Data name="{}"><value>{}</value></Data>"#,
+                    escape(key),
+                    escape(value)
+                )?;
+            }
+            writeln!(writer, "</ExtendedData>")?;
+            writeln!(
+                writer,
+                "<Point><coordinates>{},{}</coordinates></Point>",
+                poi.coord.lon(),
+                poi.coord.lat()
+            )?;
+            writeln!(writer, "</Placemark>")?;
+        }
+        writeln!(writer, "</Folder>")?;
+    }
+    writeln!(writer, "</Document>")?;
+    writeln!(writer, "</kml>")?;
+    Ok(())
+}
+
+/// Escapes a value for XML text and attributes
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,96 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use crate::{Coord, Model, Result};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+/// Name of the archive member holding the [Manifest]
+pub(crate) const MANIFEST_FILE: &str = "manifest.json";
+
+/// Summary of the content of an archive, written along with the data so that
+/// it can be inspected without loading it (see [read_manifest])
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Version of the archive format
+    pub version: u32,
+
+    /// Number of records of each file of the archive, by file name
+    pub files: BTreeMap<String, u64>,
+
+    /// Number of POIs of each POI type, by POI type id
+    pub poi_types: BTreeMap<String, u64>,
+
+    /// Bounding box of the POIs with valid coordinates, as
+    /// `[min_lon, min_lat, max_lon, max_lat]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<[f64; 4]>,
+}
+
+impl Manifest {
+    /// The manifest of `model`, with the POI counts and bounding box filled
+    /// in. The record counts of the files are left to the writer.
+    pub(crate) fn new(model: &Model, version: u32) -> Manifest {
+        let mut manifest = Manifest {
+            version,
+            ..Manifest::default()
+        };
+        for poi in model.pois.values() {
+            manifest.add_poi(&poi.poi_type_id, &poi.coord);
+        }
+        manifest
+    }
+
+    /// Counts a POI of type `poi_type_id` at `coord`, and extends the
+    /// bounding box to it if its coordinates are valid
+    pub(crate) fn add_poi(&mut self, poi_type_id: &str, coord: &Coord) {
+        match self.poi_types.get_mut(poi_type_id) {
+            Some(count) => *count += 1,
+            None => {
+                self.poi_types.insert(poi_type_id.to_string(), 1);
+            }
+        }
+        if !coord.is_valid() {
+            return;
+        }
+        let (lon, lat) = (coord.lon(), coord.lat());
+        self.bbox = Some(match self.bbox {
+            None => [lon, lat, lon, lat],
+            Some([min_lon, min_lat, max_lon, max_lat]) => [
+                min_lon.min(lon),
+                min_lat.min(lat),
+                max_lon.max(lon),
+                max_lat.max(lat),
+            ],
+        });
+    }
+}
+
+/// Reads the manifest of the archive at `path`, without loading its data.
+/// Fails for the archives written before manifests were introduced.
+pub fn read_manifest<P>(path: P) -> Result<Manifest>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let mut zip = zip::ZipArchive::new(File::open(path)?)?;
+    let member = zip
+        .by_name(MANIFEST_FILE)
+        .map_err(|_| anyhow!("no {} in '{}'", MANIFEST_FILE, path.display()))?;
+    serde_json::from_reader(member)
+        .map_err(|e| anyhow!("invalid {} in '{}': {}", MANIFEST_FILE, path.display(), e))
+}
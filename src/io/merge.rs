@@ -0,0 +1,652 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Merge of archives streamed to a new archive, without loading them into
+//! a [Model](crate::Model), see [merge_archives]
+
+use super::archive::{ArchiveReader, ArchiveWriter};
+use super::decode::PoiDecoder;
+use super::{
+    dialect, finish_archive, read_csv, read_csv_with, read_version, write_member, write_poi_types,
+    write_relations, CsvOptions, LoadOptions, Manifest, OptionalPoiColumns, PoiProperty, PoiRecord,
+    PoiRelationRecord, PoiRow, PoiTypeRecord, PoiTypeTranslationRecord, WriteOptions,
+    CURRENT_VERSION,
+};
+use crate::accessibility::Accessibility;
+use crate::trace::Traced;
+use crate::{Coord, Error, IdCollision, MergeStrategy, Metadata, PoiRelation, PoiType, Result};
+use anyhow::{anyhow, bail, Context};
+use csv::ByteRecord;
+use itertools::Either;
+use serde::de::DeserializeOwned;
+use sha2::{Digest as _, Sha256};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// An archive being merged
+struct Input {
+    path: PathBuf,
+    zip: ArchiveReader<'static, File>,
+    options: LoadOptions,
+    /// New ids of the POIs of this archive renamed because of a collision
+    renamed: HashMap<String, String>,
+}
+
+/// SHA-256 digest of a POI and of its properties
+type Digest = [u8; 32];
+
+/// The archive whose version of a POI is written, a digest of this version,
+/// to tell identical POIs from conflicting ones, and the optional columns
+/// of `poi.txt` it needs
+struct Owner {
+    input: usize,
+    digest: Digest,
+    columns: OptionalPoiColumns,
+}
+
+/// What the first pass learns about a POI of an archive
+struct Summary {
+    digest: Digest,
+    columns: OptionalPoiColumns,
+}
+
+/// Merges the archives in `inputs`, in order, into a new archive at
+/// `output`, handling the POIs sharing the same id according to `strategy`
+/// like [load_model_from_paths](super::load_model_from_paths) does, but
+/// without loading the archives: the records are streamed from the inputs
+/// to the output, only an index of the ids of the POIs being kept in memory.
+///
+/// Unlike an archive written from a [Model](crate::Model), the POIs are
+/// written in the order of the inputs, not ordered by id. The extension of
+/// `output` is replaced by `.poi`.
+pub fn merge_archives<I, P, Q>(inputs: I, output: Q, strategy: &MergeStrategy) -> Result<()>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let mut inputs = inputs
+        .into_iter()
+        .map(|path| Input::open(path.as_ref()))
+        .collect::<Result<Vec<_>>>()?;
+    let out = output.as_ref().with_extension("poi");
+    let traced = Traced::start("merge", out.display());
+    let file = File::create(&out)?;
+    let result = merge(&mut inputs, file, strategy);
+    if result.is_err() {
+        let _ = std::fs::remove_file(&out);
+    }
+    traced.finish_with(result, |count| *count).map(drop)
+}
+
+/// Merges the inputs into `file`, and returns the number of POIs written
+fn merge(inputs: &mut [Input], file: File, strategy: &MergeStrategy) -> Result<usize> {
+    // first pass, to find which archive owns each POI, and to merge what
+    // is small enough to be kept in memory
+    let mut owners: HashMap<String, Owner> = HashMap::new();
+    let mut poi_types: HashMap<String, PoiType> = HashMap::new();
+    let mut metadata: Option<Metadata> = None;
+    for (index, input) in inputs.iter_mut().enumerate() {
+        let path = input.path.clone();
+        let context = || format!("cannot merge '{}'", path.display());
+        let summaries = input.summaries().with_context(context)?;
+        input
+            .index(index, summaries, &mut owners, strategy)
+            .with_context(context)?;
+        for (id, poi_type) in input.poi_types().with_context(context)? {
+            match poi_types.entry(id) {
+                Entry::Occupied(entry) if *entry.get() != poi_type => {
+                    return Err(anyhow!(
+                        "Trying to override POI Type with id {}",
+                        entry.key()
+                    ))
+                    .with_context(context);
+                }
+                Entry::Occupied(_) => {}
+                Entry::Vacant(entry) => {
+                    entry.insert(poi_type);
+                }
+            }
+        }
+        if metadata.is_none() {
+            metadata = input.metadata().with_context(context)?;
+        }
+    }
+    let owns = |index: usize, id: &str| owners.get(id).map(|owner| owner.input) == Some(index);
+    // the optional columns are written only when needed, like write_archive
+    // does
+    let columns = owners
+        .values()
+        .fold(OptionalPoiColumns::default(), |columns, owner| {
+            columns.or(owner.columns)
+        });
+
+    // second pass, streaming the records owned by each archive
+    let options = WriteOptions::default();
+    let mut zip = ArchiveWriter::new(file, options.zip64);
+    let mut manifest = Manifest {
+        version: CURRENT_VERSION,
+        ..Manifest::default()
+    };
+    zip.start_file("version.txt")?;
+    std::io::Write::write_fmt(&mut zip, format_args!("{}\n", CURRENT_VERSION))?;
+
+    let mut error: Option<Error> = None;
+    let pois = inputs
+        .iter_mut()
+        .enumerate()
+        .flat_map(|(index, input)| {
+            input
+                .pois()
+                .filter(move |rec| rec.as_ref().map_or(true, |rec| owns(index, &rec.id)))
+        })
+        .map_while(|rec| rec.map_err(|e| error = Some(e)).ok())
+        .inspect(|rec| manifest.add_poi(&rec.type_id, &Coord::new(rec.lon, rec.lat)))
+        .map(|record| PoiRow {
+            record,
+            columns,
+            visible_format: options.visible_format,
+            projection: options.projection,
+        });
+    let count = write_member(&mut zip, &options, "poi.txt", pois)?;
+    error.take().map_or(Ok(()), Err)?;
+    manifest.files.insert("poi.txt".to_string(), count);
+
+    write_poi_types(&mut zip, &options, poi_types.values(), &mut manifest)?;
+
+    let properties = inputs
+        .iter_mut()
+        .enumerate()
+        .flat_map(|(index, input)| {
+            input
+                .properties()
+                .filter(move |rec| rec.as_ref().map_or(true, |rec| owns(index, &rec.poi_id)))
+        })
+        .map_while(|rec| rec.map_err(|e| error = Some(e)).ok());
+    let count = write_member(&mut zip, &options, "poi_properties.txt", properties)?;
+    error.take().map_or(Ok(()), Err)?;
+    manifest
+        .files
+        .insert("poi_properties.txt".to_string(), count);
+
+    let mut relations = BTreeSet::new();
+    for input in inputs.iter_mut() {
+        relations.extend(input.relations()?);
+    }
+    write_relations(&mut zip, &options, &relations, &mut manifest)?;
+
+    finish_archive(zip, metadata.as_ref(), &manifest)?;
+    Ok(manifest.files["poi.txt"] as usize)
+}
+
+impl Input {
+    fn open(path: &Path) -> Result<Input> {
+        let file = File::open(path).with_context(|| format!("cannot open '{}'", path.display()))?;
+        let mut zip = ArchiveReader::new(zip::ZipArchive::new(file)?);
+        let version = read_version(&mut zip.zip)?;
+        if version != CURRENT_VERSION {
+            bail!(
+                "'{}' uses the version {} of the archive format, which cannot be merged",
+                path.display(),
+                version
+            );
+        }
+        // archives of older exporters are read with their own dialect
+        let options = LoadOptions::default();
+        let options = match dialect::detect(&mut zip, &options) {
+            Some(dialect) => LoadOptions {
+                csv: CsvOptions {
+                    delimiter: dialect.delimiter,
+                    ..options.csv
+                },
+                ..options
+            },
+            None => options,
+        };
+        Ok(Input {
+            path: path.to_path_buf(),
+            zip,
+            options,
+            renamed: HashMap::new(),
+        })
+    }
+
+    /// Streams the records of the CSV member `name`, if present, the errors
+    /// being located in the archive
+    fn records<T>(&mut self, name: &'static str) -> impl Iterator<Item = Result<T>> + '_
+    where
+        T: DeserializeOwned + 'static,
+    {
+        let Input {
+            path, zip, options, ..
+        } = self;
+        zip.by_name(name)
+            .ok()
+            .map(|zipper| read_csv(options, zipper))
+            .into_iter()
+            .flatten()
+            .map(move |(line, rec)| locate(path, name, line, rec))
+    }
+
+    /// Streams the records of `poi.txt`, the renamed POIs having their new id
+    fn pois(&mut self) -> impl Iterator<Item = Result<PoiRecord>> + '_ {
+        let Input {
+            path,
+            zip,
+            options,
+            renamed,
+        } = self;
+        let defaults = &options.column_defaults;
+        let records = match zip.by_name("poi.txt") {
+            Ok(zipper) => Either::Left(
+                read_csv_with(options, zipper, move |headers| {
+                    let decoder = PoiDecoder::new(headers, defaults)?;
+                    Ok(move |record: &ByteRecord| decoder.decode(record))
+                })
+                .map(move |(line, rec)| locate(path, "poi.txt", line, rec)),
+            ),
+            Err(e) => Either::Right(std::iter::once(Err(anyhow!(
+                "cannot find 'poi.txt' in '{}': {}",
+                path.display(),
+                e
+            )))),
+        };
+        records.map(move |rec| {
+            let mut rec = rec?;
+            if let Some(id) = renamed.get(&rec.id) {
+                rec.id = id.clone();
+            }
+            Ok(rec)
+        })
+    }
+
+    /// Streams the records of `poi_properties.txt`, the renamed POIs having
+    /// their new id
+    fn properties(&mut self) -> impl Iterator<Item = Result<PoiProperty>> + '_ {
+        let renamed = self.renamed.clone();
+        self.records("poi_properties.txt").map(move |rec| {
+            let mut rec: PoiProperty = rec?;
+            if let Some(id) = renamed.get(&rec.poi_id) {
+                rec.poi_id = id.clone();
+            }
+            Ok(rec)
+        })
+    }
+
+    /// Digests and optional columns of the POIs of the archive, covering
+    /// their properties
+    fn summaries(&mut self) -> Result<BTreeMap<String, Summary>> {
+        let mut records = BTreeMap::new();
+        for rec in self.pois() {
+            let rec = rec?;
+            let mut hasher = Sha256::new();
+            for field in [&rec.type_id, &rec.name] {
+                feed(&mut hasher, field.as_bytes());
+            }
+            for number in [rec.lat, rec.lon, rec.weight.0] {
+                feed(&mut hasher, &number.to_bits().to_le_bytes());
+            }
+            let optional = format!(
+                "{:?}",
+                (
+                    rec.visible,
+                    rec.created_at,
+                    rec.updated_at,
+                    [
+                        rec.wheelchair,
+                        rec.visual_aids,
+                        rec.audio_aids,
+                        rec.elevator
+                    ],
+                    rec.elevation_m.map(f64::to_bits),
+                )
+            );
+            feed(&mut hasher, optional.as_bytes());
+            let columns = OptionalPoiColumns::of_record(&rec);
+            records.insert(rec.id, (hasher, columns, Vec::new()));
+        }
+        // the properties are digested apart, then sorted, to be digested
+        // regardless of their order
+        for rec in self.properties() {
+            let rec = rec?;
+            if let Some((_, columns, properties)) = records.get_mut(&rec.poi_id) {
+                let mut hasher = Sha256::new();
+                feed(&mut hasher, rec.key.as_bytes());
+                feed(&mut hasher, rec.value.as_bytes());
+                properties.push(Digest::from(hasher.finalize()));
+                let property = std::iter::once((rec.key, rec.value)).collect();
+                let accessibility = Accessibility::from_properties(&property);
+                *columns = columns.or(OptionalPoiColumns::of_accessibility(&accessibility));
+            }
+        }
+        Ok(records
+            .into_iter()
+            .map(|(id, (mut hasher, columns, mut properties))| {
+                properties.sort_unstable();
+                for property in &properties {
+                    hasher.update(property);
+                }
+                let digest = Digest::from(hasher.finalize());
+                (id, Summary { digest, columns })
+            })
+            .collect())
+    }
+
+    /// Adds the POIs of the archive, of the given `summaries`, to `owners`,
+    /// renaming them if needed
+    fn index(
+        &mut self,
+        index: usize,
+        summaries: BTreeMap<String, Summary>,
+        owners: &mut HashMap<String, Owner>,
+        strategy: &MergeStrategy,
+    ) -> Result<()> {
+        for (id, summary) in &summaries {
+            let owner = Owner {
+                input: index,
+                digest: summary.digest,
+                columns: summary.columns,
+            };
+            let collision = match owners.get(id) {
+                None => {
+                    owners.insert(id.clone(), owner);
+                    continue;
+                }
+                Some(existing) if existing.digest == summary.digest => continue,
+                Some(_) => match strategy {
+                    MergeStrategy::Fail => &IdCollision::Fail,
+                    MergeStrategy::KeepFirst => continue,
+                    MergeStrategy::KeepLast => {
+                        owners.insert(id.clone(), owner);
+                        continue;
+                    }
+                    MergeStrategy::Rename(collision) => collision,
+                },
+            };
+            let base = match collision {
+                IdCollision::Fail => bail!("POI with id {} already in the model", id),
+                IdCollision::Suffix(suffix) => format!("{}{}", id, suffix),
+                IdCollision::Namespace(namespace) => format!("{}:{}", namespace, id),
+            };
+            let new_id = std::iter::once(base.clone())
+                .chain((2..).map(|n| format!("{}-{}", base, n)))
+                .find(|candidate| {
+                    !owners.contains_key(candidate) && !summaries.contains_key(candidate)
+                })
+                .expect("an unused id is always found");
+            owners.insert(new_id.clone(), owner);
+            self.renamed.insert(id.clone(), new_id);
+        }
+        Ok(())
+    }
+
+    /// POI types of the archive, with their translations
+    fn poi_types(&mut self) -> Result<HashMap<String, PoiType>> {
+        if self.zip.by_name("poi_type.txt").is_err() {
+            bail!("cannot find 'poi_type.txt'");
+        }
+        let mut poi_types = HashMap::new();
+        for rec in self.records::<PoiTypeRecord>("poi_type.txt") {
+            let poi_type = PoiType::from(rec?);
+            poi_types.insert(poi_type.id.clone(), poi_type);
+        }
+        for rec in self.records::<PoiTypeTranslationRecord>("poi_type_translations.txt") {
+            let translation = rec?;
+            match poi_types.get_mut(&translation.poi_type_id) {
+                Some(poi_type) => {
+                    poi_type
+                        .translations
+                        .insert(translation.lang, translation.poi_type_name);
+                }
+                None => bail!(
+                    "cannot find poi type '{}' for translation",
+                    translation.poi_type_id
+                ),
+            }
+        }
+        Ok(poi_types)
+    }
+
+    /// Relations of the archive, between the new ids of the renamed POIs
+    fn relations(&mut self) -> Result<Vec<PoiRelation>> {
+        let renamed = self.renamed.clone();
+        let remap = |id: String| renamed.get(&id).cloned().unwrap_or(id);
+        self.records::<PoiRelationRecord>("poi_relations.txt")
+            .map(|rec| {
+                let relation = PoiRelation::from(rec?);
+                Ok(PoiRelation {
+                    parent_id: remap(relation.parent_id),
+                    child_id: remap(relation.child_id),
+                    ..relation
+                })
+            })
+            .collect()
+    }
+
+    fn metadata(&mut self) -> Result<Option<Metadata>> {
+        match self.zip.by_name("metadata.json") {
+            Ok(zipper) => Ok(Some(
+                serde_json::from_reader(zipper).context("invalid 'metadata.json'")?,
+            )),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// Feeds `bytes` to `hasher` preceded by their length, so that consecutive
+/// fields cannot be mistaken for one another
+fn feed(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+/// Adds the archive and the line of a record to its error
+fn locate<T>(path: &Path, name: &str, line: Option<u64>, rec: Result<T>) -> Result<T> {
+    rec.with_context(|| match line {
+        Some(line) => format!("in file '{}' of '{}', line {}", name, path.display(), line),
+        None => format!("in file '{}' of '{}'", name, path.display()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_archives;
+    use crate::io::{load_model_from_path, write_model_to_path, CURRENT_VERSION};
+    use crate::{IdCollision, MergeStrategy, Model, Poi, PoiRelation, PoiType};
+    use std::io::{Read, Write};
+    use std::path::{Path, PathBuf};
+
+    fn poi(id: &str, name: &str) -> Poi {
+        Poi {
+            id: id.to_string(),
+            name: name.to_string(),
+            poi_type_id: "amenity:parking".to_string(),
+            ..Poi::default()
+        }
+    }
+
+    fn model(pois: Vec<Poi>) -> Model {
+        let mut model: Model = pois.into_iter().collect();
+        model.poi_types.insert(
+            "amenity:parking".to_string(),
+            PoiType {
+                id: "amenity:parking".to_string(),
+                name: "Parking".to_string(),
+                ..PoiType::default()
+            },
+        );
+        model
+    }
+
+    fn relation(parent_id: &str, child_id: &str) -> PoiRelation {
+        PoiRelation {
+            parent_id: parent_id.to_string(),
+            child_id: child_id.to_string(),
+            relation_type: PoiRelation::ENTRANCE.to_string(),
+        }
+    }
+
+    /// Writes the models to `dir`, and returns their paths
+    fn save(dir: &Path, models: &[Model]) -> Vec<PathBuf> {
+        models
+            .iter()
+            .enumerate()
+            .map(|(index, model)| {
+                let path = dir.join(format!("{}.poi", index));
+                write_model_to_path(model, &path).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    /// Merges the models, and loads the result
+    fn merge(models: &[Model], strategy: &MergeStrategy) -> crate::Result<Model> {
+        let dir = tempfile::tempdir().unwrap();
+        let inputs = save(dir.path(), models);
+        let output = dir.path().join("merged.poi");
+        merge_archives(&inputs, &output, strategy)?;
+        load_model_from_path(&output)
+    }
+
+    fn names(model: &Model) -> Vec<(&str, &str)> {
+        model
+            .pois
+            .values()
+            .map(|poi| (poi.id.as_str(), poi.name.as_str()))
+            .collect()
+    }
+
+    fn conflicting() -> Vec<Model> {
+        vec![
+            model(vec![poi("poi:1", "first"), poi("poi:2", "kept")]),
+            model(vec![poi("poi:1", "last"), poi("poi:2", "kept")]),
+        ]
+    }
+
+    #[test]
+    fn keep_first() {
+        let merged = merge(&conflicting(), &MergeStrategy::KeepFirst).unwrap();
+        assert_eq!(names(&merged), [("poi:1", "first"), ("poi:2", "kept")]);
+    }
+
+    #[test]
+    fn keep_last() {
+        let merged = merge(&conflicting(), &MergeStrategy::KeepLast).unwrap();
+        assert_eq!(names(&merged), [("poi:1", "last"), ("poi:2", "kept")]);
+    }
+
+    #[test]
+    fn rename_remaps_the_relations() {
+        let mut models = conflicting();
+        models[1]
+            .pois
+            .insert("poi:3".to_string(), poi("poi:3", "entrance"));
+        models[1].relations.insert(relation("poi:1", "poi:3"));
+        let strategy = MergeStrategy::Rename(IdCollision::Suffix("-b".to_string()));
+        let merged = merge(&models, &strategy).unwrap();
+        assert_eq!(
+            names(&merged),
+            [
+                ("poi:1", "first"),
+                ("poi:1-b", "last"),
+                ("poi:2", "kept"),
+                ("poi:3", "entrance")
+            ]
+        );
+        let relations: Vec<_> = merged.relations.into_iter().collect();
+        assert_eq!(relations, [relation("poi:1-b", "poi:3")]);
+    }
+
+    #[test]
+    fn fail_on_conflicts_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let inputs = save(dir.path(), &conflicting());
+        let output = dir.path().join("merged.poi");
+        assert!(merge_archives(&inputs, &output, &MergeStrategy::Fail).is_err());
+        assert!(!output.exists());
+
+        // identical POIs are not conflicts
+        let same = model(vec![poi("poi:1", "same")]);
+        let merged = merge(&[same.clone(), same], &MergeStrategy::Fail).unwrap();
+        assert_eq!(names(&merged), [("poi:1", "same")]);
+    }
+
+    #[test]
+    fn optional_columns_written_only_when_used() {
+        let header = |models: &[Model]| {
+            let dir = tempfile::tempdir().unwrap();
+            let inputs = save(dir.path(), models);
+            let output = dir.path().join("merged.poi");
+            merge_archives(&inputs, &output, &MergeStrategy::Fail).unwrap();
+            let mut zip = zip::ZipArchive::new(std::fs::File::open(&output).unwrap()).unwrap();
+            let mut content = String::new();
+            zip.by_name("poi.txt")
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content.lines().next().unwrap().to_string()
+        };
+        let models = [
+            model(vec![poi("poi:1", "a")]),
+            model(vec![poi("poi:2", "b")]),
+        ];
+        assert_eq!(
+            header(&models),
+            "poi_id;poi_type_id;poi_name;poi_lat;poi_lon;poi_weight;poi_visible"
+        );
+
+        let mut models = models;
+        models[1].pois.get_mut("poi:2").unwrap().elevation_m = Some(12.);
+        assert!(header(&models).ends_with(";poi_visible;poi_elevation"));
+    }
+
+    #[test]
+    fn duplicated_properties_are_not_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = save(dir.path(), &[model(vec![poi("poi:1", "a")])]).remove(0);
+        // the same POI, with a property given twice
+        let second = dir.path().join("second.poi");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&second).unwrap());
+        let files = [
+            ("version.txt", format!("{}\n", CURRENT_VERSION)),
+            (
+                "poi.txt",
+                "poi_id;poi_type_id;poi_name;poi_lat;poi_lon;poi_weight;poi_visible\n\
+                 poi:1;amenity:parking;a;0;0;0;1\n"
+                    .to_string(),
+            ),
+            (
+                "poi_type.txt",
+                "poi_type_id;poi_type_name\namenity:parking;Parking\n".to_string(),
+            ),
+            (
+                "poi_properties.txt",
+                "poi_id;key;value\npoi:1;operator;x\npoi:1;operator;x\n".to_string(),
+            ),
+        ];
+        for (name, content) in files.iter() {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let output = dir.path().join("merged.poi");
+        let inputs = [first, second];
+        assert!(merge_archives(&inputs, &output, &MergeStrategy::Fail).is_err());
+        assert!(merge_archives(&inputs, &output, &MergeStrategy::KeepFirst).is_ok());
+    }
+}
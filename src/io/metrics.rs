@@ -0,0 +1,83 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Metrics of the loads and saves, reported to a [Metrics] implementation
+//! set in the [LoadOptions](super::LoadOptions) or the
+//! [WriteOptions](super::WriteOptions)
+
+use std::sync::Arc;
+
+/// Counter of the records read, labelled by `file`
+pub const RECORDS_READ: &str = "poi_model_records_read_total";
+
+/// Counter of the records written, labelled by `file`
+pub const RECORDS_WRITTEN: &str = "poi_model_records_written_total";
+
+/// Counter of the (uncompressed) bytes read, labelled by `file`
+pub const BYTES_READ: &str = "poi_model_bytes_read_total";
+
+/// Counter of the (uncompressed) bytes written, labelled by `file`
+pub const BYTES_WRITTEN: &str = "poi_model_bytes_written_total";
+
+/// Counter of the failed operations, labelled by `operation` (`load` or
+/// `save`)
+pub const ERRORS: &str = "poi_model_errors_total";
+
+/// Histogram of the durations of the operations, in seconds, labelled by
+/// `operation` (`load` or `save`)
+pub const DURATION_SECONDS: &str = "poi_model_operation_duration_seconds";
+
+/// Receives the metrics of the loads and saves, to be exported to a
+/// monitoring system like Prometheus. The names of the metrics are the
+/// constants of this module, like [RECORDS_READ].
+pub trait Metrics: Send + Sync {
+    /// Adds `value` to the counter `name` of `labels`
+    fn counter(&self, name: &'static str, labels: &[(&'static str, &str)], value: u64);
+
+    /// Records `value` in the histogram `name` of `labels`
+    fn histogram(&self, name: &'static str, labels: &[(&'static str, &str)], value: f64);
+}
+
+/// [Metrics] shared by the operations they are handed to
+#[derive(Clone)]
+pub struct SharedMetrics(Arc<dyn Metrics>);
+
+impl SharedMetrics {
+    /// Shares `metrics`
+    pub fn new<M>(metrics: M) -> SharedMetrics
+    where
+        M: Metrics + 'static,
+    {
+        SharedMetrics(Arc::new(metrics))
+    }
+
+    pub(crate) fn counter(&self, name: &'static str, labels: &[(&'static str, &str)], value: u64) {
+        self.0.counter(name, labels, value)
+    }
+
+    pub(crate) fn histogram(
+        &self,
+        name: &'static str,
+        labels: &[(&'static str, &str)],
+        value: f64,
+    ) {
+        self.0.histogram(name, labels, value)
+    }
+}
+
+impl std::fmt::Debug for SharedMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedMetrics")
+    }
+}
@@ -0,0 +1,2200 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Functions to read and write models from and to `.poi` archives
+//!
+//! With the `mmap` feature, archives are memory-mapped when loaded, and
+//! their stored (uncompressed) members are parsed in place. An archive must
+//! then not be modified while it is loaded.
+
+mod archive;
+#[cfg(feature = "binary")]
+mod binary;
+mod container;
+mod decode;
+mod dialect;
+mod encoding;
+mod error;
+mod es;
+mod flat;
+mod gtfs;
+mod json;
+mod kml;
+mod manifest;
+mod merge;
+pub mod metrics;
+mod navitia;
+#[cfg(feature = "overpass")]
+mod overpass;
+mod postgres;
+mod progress;
+#[cfg(feature = "object_store")]
+mod remote;
+
+use crate::accessibility::{Access, Accessibility};
+use crate::cancel::{self, CancellationToken, Cancelled};
+use crate::constraints::{PropertyConstraints, ViolationPolicy};
+use crate::projection::Projection;
+use crate::trace::Traced;
+use crate::warnings::{self, Warning, WarningCallback};
+use crate::{Coord, MergeStrategy, Model, Poi, PoiRelation, PoiType, Property, Weight};
+use crate::{Error, Result};
+use anyhow::{anyhow, bail, Context};
+use archive::{ArchiveMember, ArchiveReader, ArchiveWriter};
+use chrono::{DateTime, Utc};
+use container::Container;
+use encoding::DecodingReader;
+use itertools::{Either, Itertools};
+use manifest::MANIFEST_FILE;
+use progress::{Counting, Tracked, Tracker};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, PoisonError};
+
+#[cfg(feature = "binary")]
+pub use binary::{
+    load_model_from_binary, load_model_from_binary_reader, write_model_to_binary,
+    write_model_to_binary_writer, BINARY_MAGIC, BINARY_VERSION,
+};
+pub use csv::{ByteRecord, QuoteStyle, Terminator};
+pub use decode::PoiDecoder;
+pub use dialect::{Dialect, HeaderAliases};
+pub use encoding::Encoding;
+pub use error::CsvError;
+pub use es::{write_es_bulk, BulkAction, IndexSettings};
+pub use flat::{write_flat_csv, write_flat_csv_with_options};
+pub use gtfs::{load_model_from_gtfs_stops, StopMapping};
+pub use json::{load_model_from_json_records, FieldMapping};
+pub use kml::{write_kml, KmlStyle, StyleMap};
+pub use manifest::{read_manifest, Manifest};
+pub use merge::merge_archives;
+pub use metrics::{Metrics, SharedMetrics};
+pub use navitia::write_navitia_json;
+#[cfg(feature = "overpass")]
+pub use overpass::{
+    load_model_from_overpass, load_model_from_overpass_with_weights, OVERPASS_ENDPOINT,
+};
+pub use postgres::{postgres_schema, write_postgres_copy, POSTGRES_COLUMNS};
+pub use progress::{Progress, ProgressCallback};
+#[cfg(feature = "object_store")]
+pub use remote::{load_model_from_url, write_model_to_url};
+
+/// Version of the archive format written by this crate. It is stored in the
+/// `version.txt` file of the archive, and bumped on each change of the
+/// format.
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Size of the buffer of the CSV readers, larger than the default one to
+/// make fewer reads on large files
+const CSV_BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// The CSV dialect used by the files of an archive
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Field delimiter, `;` by default
+    pub delimiter: u8,
+
+    /// Quote character, `"` by default
+    pub quote: u8,
+
+    /// When fields are quoted on write, only when necessary by default.
+    /// `QuoteStyle::NonNumeric` quotes all the text fields, and
+    /// `QuoteStyle::Never` writes invalid CSV if a field contains the
+    /// delimiter, the quote or a line break.
+    pub quote_style: QuoteStyle,
+
+    /// Escape character of the quotes inside quoted fields, like `\`.
+    /// `None`, the default, doubles them instead, like `""`.
+    pub escape: Option<u8>,
+
+    /// Record terminator. `None` keeps the default behaviour: any of `\r`,
+    /// `\n` or `\r\n` on read, `\n` on write
+    pub terminator: Option<Terminator>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            delimiter: b';',
+            quote: b'"',
+            quote_style: QuoteStyle::Necessary,
+            escape: None,
+            terminator: None,
+        }
+    }
+}
+
+/// Options used when loading a model
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Dialect of the CSV files
+    pub csv: CsvOptions,
+
+    /// Character encoding of the CSV files
+    pub encoding: Encoding,
+
+    /// Checks the members of the archive against the SHA-256 checksums of
+    /// its `checksums.txt` before loading it. Archives without checksums,
+    /// or with members it does not list, are then rejected.
+    pub verify_integrity: bool,
+
+    /// What to do with POIs whose coordinates are not valid (see
+    /// [Coord::is_valid])
+    pub coordinate_policy: CoordinatePolicy,
+
+    /// Alternative names of the headers of the CSV files, the built-in
+    /// ones by default
+    pub header_aliases: HeaderAliases,
+
+    /// Values of the optional columns of `poi.txt` that are absent or empty
+    pub column_defaults: ColumnDefaults,
+
+    /// Normalizes the keys of the properties, if set
+    pub property_keys: Option<KeyNormalization>,
+
+    /// Cleans the names of the POIs and of the POI types, and the values of
+    /// the properties up (see [sanitize](crate::normalize::sanitize)). The
+    /// changes are reported as issues by [load_model_lenient].
+    pub sanitize: bool,
+
+    /// Constraints enforced on the values of the properties, after their
+    /// keys are normalized, if set
+    pub property_constraints: Option<PropertyConstraints>,
+
+    /// Called with the progress of the load, if set
+    pub progress: Option<ProgressCallback>,
+
+    /// Called with the [Warning]s of the load (deprecated headers,
+    /// properties given several times, values clamped or fixed), if set
+    pub warnings: Option<WarningCallback>,
+
+    /// Receives the metrics of the load, if set
+    pub metrics: Option<SharedMetrics>,
+
+    /// Stops the load with a [Cancelled] error once cancelled, if set
+    pub cancellation: Option<CancellationToken>,
+}
+
+/// Values of the optional columns of `poi.txt`, for the POIs of files
+/// without them, or with empty values
+#[derive(Debug, Clone)]
+pub struct ColumnDefaults {
+    /// Visibility of the POIs, in place of `poi_visible`, true by default
+    pub visible: bool,
+
+    /// Weight of the POIs, in place of `poi_weight`, 0 by default
+    pub weight: Weight,
+}
+
+impl Default for ColumnDefaults {
+    fn default() -> ColumnDefaults {
+        ColumnDefaults {
+            visible: true,
+            weight: Weight(0.),
+        }
+    }
+}
+
+/// Normalization of property keys: keys are trimmed and lowercased, then
+/// known aliases are replaced by their canonical key
+#[derive(Debug, Clone)]
+pub struct KeyNormalization {
+    /// Canonical key of each alias, both trimmed and lowercased
+    pub aliases: HashMap<String, String>,
+}
+
+impl Default for KeyNormalization {
+    /// Normalization with the built-in aliases
+    fn default() -> KeyNormalization {
+        let aliases = [
+            ("wheelchair_access", "wheelchair"),
+            ("wheelchair_accessible", "wheelchair"),
+            ("opening_hour", "opening_hours"),
+            ("contact:phone", "phone"),
+            ("contact:website", "website"),
+        ];
+        KeyNormalization {
+            aliases: aliases
+                .iter()
+                .map(|(alias, key)| (alias.to_string(), key.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl KeyNormalization {
+    /// Returns the normalized key
+    pub fn normalize(&self, key: &str) -> String {
+        let key = key.trim().to_lowercase();
+        self.aliases.get(&key).cloned().unwrap_or(key)
+    }
+}
+
+/// What to do, at load time, with POIs whose coordinates are not valid
+///
+/// NaN and infinite coordinates are never loaded: they are skipped with
+/// [CoordinatePolicy::SkipRecord], and rejected otherwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoordinatePolicy {
+    /// Load them as is
+    #[default]
+    Accept,
+
+    /// Fail the load (or report an issue and skip the POI in lenient mode)
+    Reject,
+
+    /// Bring out-of-range coordinates back into the valid range. `(0, 0)`
+    /// coordinates are kept as is.
+    Clamp,
+
+    /// Skip the POI and its properties
+    SkipRecord,
+}
+
+/// How the booleans are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BoolFormat {
+    /// As `0` or `1`
+    #[default]
+    Numeric,
+
+    /// As `true` or `false`
+    Text,
+}
+
+/// Options used when saving a model
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Dialect of the CSV files
+    pub csv: CsvOptions,
+
+    /// Format of the `poi_visible` column. Both formats are accepted on
+    /// load.
+    pub visible_format: BoolFormat,
+
+    /// Adds the `poi_x` and `poi_y` columns to `poi.txt`, with the
+    /// coordinates of the POIs in this projection, if set. They are empty
+    /// for the POIs that cannot be projected, and ignored on load.
+    pub projection: Option<Projection>,
+
+    /// Writes the members of the archive with zip64 extensions, needed
+    /// when one of them exceeds 4 GiB. Readers must then support zip64.
+    /// Without it, writing such a member fails.
+    pub zip64: bool,
+
+    /// Leaves out the POI types that are neither the type of a POI, nor an
+    /// ancestor of such a type (see [Model::prune_poi_types])
+    pub prune_poi_types: bool,
+
+    /// Constraints enforced on the values of the properties, if set
+    pub property_constraints: Option<PropertyConstraints>,
+
+    /// Called with the progress of the save, if set
+    pub progress: Option<ProgressCallback>,
+
+    /// Called with the [Warning]s of the save (values fixed), if set
+    pub warnings: Option<WarningCallback>,
+
+    /// Receives the metrics of the save, if set
+    pub metrics: Option<SharedMetrics>,
+
+    /// Stops the save with a [Cancelled] error once cancelled, if set. The
+    /// partially written archive is then removed.
+    pub cancellation: Option<CancellationToken>,
+}
+
+/// Saves the model to a file, in CSV format.
+pub fn write_model_to_path<P>(model: &Model, path: P) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    write_model_to_path_with_options(model, path, &WriteOptions::default())
+}
+
+/// Saves the model to a file, in CSV format, using the given options.
+///
+/// The output is reproducible: rows are sorted (POIs and properties by POI
+/// id then key, POI types by id) and the timestamps and permissions of the
+/// members are fixed, so the same model always gives the same bytes.
+///
+/// A [Manifest] summing up the content is written along with the data, see
+/// [read_manifest].
+///
+/// If `path` ends with `.tar`, `.tar.gz` or `.tgz`, the members of the
+/// archive are written as a tarball instead. Otherwise the extension of
+/// `path` is replaced by `.poi`.
+pub fn write_model_to_path_with_options<P>(
+    model: &Model,
+    path: P,
+    options: &WriteOptions,
+) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    let container = Container::from_path(path.as_ref());
+    let out = match container {
+        Container::Zip => path.as_ref().with_extension("poi"),
+        Container::Tar | Container::TarGz => path.as_ref().to_path_buf(),
+    };
+    let traced = Traced::start("save", out.display()).with_metrics(options.metrics.as_ref());
+    let result = reject_violations(model, options)
+        .and_then(|_| Ok(File::create(&out)?))
+        .and_then(|file| match container {
+            Container::Zip => write_archive(model, file, options).map(drop),
+            Container::Tar | Container::TarGz => {
+                write_archive(model, std::io::Cursor::new(Vec::new()), options)
+                    .and_then(|zip| container::pack(&zip.into_inner(), container, file))
+            }
+        });
+    if let Err(e) = &result {
+        if e.is::<Cancelled>() {
+            let _ = std::fs::remove_file(&out);
+        }
+    }
+    traced.finish_with(result, |_| model.pois.len())
+}
+
+/// Writes the model as an archive to `writer`, like the standard output.
+/// The archive is first built in memory, since writing it needs to seek.
+pub fn write_model_to_writer<W>(model: &Model, mut writer: W, options: &WriteOptions) -> Result<()>
+where
+    W: Write,
+{
+    let traced = Traced::start("save", "<stream>").with_metrics(options.metrics.as_ref());
+    let result = reject_violations(model, options)
+        .and_then(|_| write_archive(model, std::io::Cursor::new(Vec::new()), options))
+        .and_then(|content| {
+            writer.write_all(&content.into_inner())?;
+            writer.flush()?;
+            Ok(())
+        });
+    traced.finish_with(result, |_| model.pois.len())
+}
+
+/// Fails if the property constraints of `options` reject a property of the
+/// model, before anything is written
+fn reject_violations(model: &Model, options: &WriteOptions) -> Result<()> {
+    let constraints = options
+        .property_constraints
+        .as_ref()
+        .filter(|c| c.policy == ViolationPolicy::Reject);
+    if let Some(constraints) = constraints {
+        if let Some((id, key, violation)) = model.property_violations(constraints).first() {
+            bail!("property '{}' of poi '{}': {}", key, id, violation);
+        }
+    }
+    Ok(())
+}
+
+/// Writes the archive of the model, and returns the underlying writer
+fn write_archive<W>(model: &Model, writer: W, options: &WriteOptions) -> Result<W>
+where
+    W: Write + std::io::Seek,
+{
+    let mut zip = ArchiveWriter::new(writer, options.zip64);
+    let mut manifest = Manifest::new(model, CURRENT_VERSION);
+
+    zip.start_file("version.txt")?;
+    writeln!(zip, "{}", CURRENT_VERSION)?;
+
+    // the optional columns are written only when needed, for the readers
+    // that do not know them
+    let columns = OptionalPoiColumns::of(model.pois.values());
+    let pois = model.pois.values().map(|poi| PoiRow {
+        record: PoiRecord::from(poi),
+        columns,
+        visible_format: options.visible_format,
+        projection: options.projection,
+    });
+    let count = write_member(&mut zip, options, "poi.txt", pois)?;
+    manifest.files.insert("poi.txt".to_string(), count);
+
+    let used_poi_types = if options.prune_poi_types {
+        model.used_poi_type_ids()
+    } else {
+        HashSet::new()
+    };
+    let poi_types = model
+        .poi_types
+        .values()
+        .filter(|pt| !options.prune_poi_types || used_poi_types.contains(pt.id.as_str()));
+    write_poi_types(&mut zip, options, poi_types, &mut manifest)?;
+
+    let truncated = options
+        .property_constraints
+        .as_ref()
+        .filter(|c| c.policy == ViolationPolicy::Truncate);
+
+    // the accessibility is mirrored in the properties, for the readers that
+    // do not know its columns
+    let poi_properties = model.pois.values().flat_map(|poi| {
+        let mut properties: BTreeMap<&str, &str> = poi
+            .properties
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        for (key, value) in poi.accessibility.to_properties() {
+            properties.insert(key, value);
+        }
+        properties.into_iter().map(move |(key, value)| {
+            let constraint = truncated.map(|c| c.constraint(key));
+            let value = match constraint.and_then(|c| c.check(value).map(|v| (c, v))) {
+                Some((constraint, violation)) => {
+                    warnings::warn(
+                        options.warnings.as_ref(),
+                        Warning::PropertyFixed {
+                            poi_id: poi.id.clone(),
+                            key: key.to_string(),
+                            violation: violation.to_string(),
+                        },
+                    );
+                    constraint.fix(value)
+                }
+                None => value.to_string(),
+            };
+            PoiProperty {
+                poi_id: poi.id.clone(),
+                key: key.to_string(),
+                value,
+            }
+        })
+    });
+    let count = write_member(&mut zip, options, "poi_properties.txt", poi_properties)?;
+    manifest
+        .files
+        .insert("poi_properties.txt".to_string(), count);
+
+    write_relations(&mut zip, options, &model.relations, &mut manifest)?;
+    finish_archive(zip, model.metadata.as_ref(), &manifest)
+}
+
+/// Writes `poi_type.txt`, and `poi_type_translations.txt` if needed, with
+/// the POI types ordered by id
+fn write_poi_types<'a, W, I>(
+    zip: &mut ArchiveWriter<W>,
+    options: &WriteOptions,
+    poi_types: I,
+    manifest: &mut Manifest,
+) -> Result<()>
+where
+    W: Write + std::io::Seek,
+    I: Iterator<Item = &'a PoiType>,
+{
+    let poi_types: Vec<&PoiType> = poi_types
+        .sorted_by(|lhs, rhs| lhs.id.cmp(&rhs.id))
+        .collect();
+    let records: Vec<PoiTypeRecord> = poi_types
+        .iter()
+        .map(|pt| PoiTypeRecord::from((*pt).clone()))
+        .collect();
+    // the optional columns are written only when needed, for the readers of
+    // the legacy two-column format
+    let columns = PoiTypeColumns::of(poi_types.iter().copied());
+    let records = records
+        .into_iter()
+        .map(|record| PoiTypeRow { record, columns });
+    let count = write_member(zip, options, "poi_type.txt", records)?;
+    manifest.files.insert("poi_type.txt".to_string(), count);
+
+    // written only when needed, for the readers that do not know this file
+    let translations: Vec<PoiTypeTranslationRecord> = poi_types
+        .iter()
+        .flat_map(|pt| {
+            pt.translations
+                .iter()
+                .map(move |(lang, name)| PoiTypeTranslationRecord {
+                    poi_type_id: pt.id.clone(),
+                    lang: lang.clone(),
+                    poi_type_name: name.clone(),
+                })
+        })
+        .sorted()
+        .collect();
+    if !translations.is_empty() {
+        let count = write_member(
+            zip,
+            options,
+            "poi_type_translations.txt",
+            translations.into_iter(),
+        )?;
+        manifest
+            .files
+            .insert("poi_type_translations.txt".to_string(), count);
+    }
+    Ok(())
+}
+
+/// Writes `poi_relations.txt`, only when needed, for the readers that do
+/// not know this file
+fn write_relations<W>(
+    zip: &mut ArchiveWriter<W>,
+    options: &WriteOptions,
+    relations: &BTreeSet<PoiRelation>,
+    manifest: &mut Manifest,
+) -> Result<()>
+where
+    W: Write + std::io::Seek,
+{
+    if !relations.is_empty() {
+        let relations = relations.iter().map(PoiRelationRecord::from);
+        let count = write_member(zip, options, "poi_relations.txt", relations)?;
+        manifest
+            .files
+            .insert("poi_relations.txt".to_string(), count);
+    }
+    Ok(())
+}
+
+/// Writes the metadata, if any, and the manifest, then the checksums and
+/// the central directory of the archive
+fn finish_archive<W>(
+    mut zip: ArchiveWriter<W>,
+    metadata: Option<&crate::Metadata>,
+    manifest: &Manifest,
+) -> Result<W>
+where
+    W: Write + std::io::Seek,
+{
+    if let Some(metadata) = metadata {
+        zip.start_file("metadata.json")?;
+        serde_json::to_writer_pretty(&mut zip, metadata)?;
+    }
+
+    zip.start_file(MANIFEST_FILE)?;
+    serde_json::to_writer_pretty(&mut zip, manifest)?;
+
+    zip.finish()
+}
+
+/// Writes `records` as the CSV member `name` of the archive, reporting the
+/// progress. Returns the number of records written.
+fn write_member<W, I, T>(
+    zip: &mut ArchiveWriter<W>,
+    options: &WriteOptions,
+    name: &str,
+    records: I,
+) -> Result<u64>
+where
+    W: Write + std::io::Seek,
+    I: Iterator<Item = T>,
+    T: Serialize,
+{
+    let token = options.cancellation.as_ref();
+    zip.start_file(name)?;
+    let writer = Counting::new(zip);
+    let mut tracker = Tracker::new(
+        options.progress.as_ref(),
+        options.metrics.as_ref(),
+        name,
+        None,
+        writer.counter(),
+    );
+    let records = records
+        .take_while(|_| cancel::check(token).is_ok())
+        .inspect(|_| tracker.record());
+    write_csv(writer, &options.csv, records)?;
+    cancel::check(token)?;
+    tracker.finish();
+    Ok(tracker.records())
+}
+
+/// Splits the model into a grid of cells of `cell_size` degrees (see
+/// [Model::split_by_grid]), and saves each non-empty cell into `dir`, as
+/// `<x>_<y>.poi`. The directory is created if needed. Fails if `cell_size`
+/// is not a finite, positive number.
+pub fn write_tiles<P>(model: &Model, cell_size: f64, dir: P) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    for ((x, y), tile) in model.split_by_grid(cell_size)? {
+        write_model_to_path(&tile, dir.join(format!("{}_{}.poi", x, y)))?;
+    }
+    Ok(())
+}
+
+/// Splits the model by POI type (see [Model::split_by_poi_type]), and saves
+/// each POI type into `dir`, as `<poi type id>.poi`. The characters of the
+/// id other than ASCII letters, digits, `-` and `_` are percent-encoded, so
+/// that `amenity:parking` is saved as `amenity%3Aparking.poi`. The directory
+/// is created if needed.
+pub fn write_split<P>(model: &Model, dir: P) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    for (id, split) in model.split_by_poi_type() {
+        write_model_to_path(&split, dir.join(format!("{}.poi", file_stem(&id))))?;
+    }
+    Ok(())
+}
+
+/// `id`, with its characters that may not be safe in a file name
+/// percent-encoded
+fn file_stem(id: &str) -> String {
+    id.bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// An issue found on a record by [load_model_lenient], skipped or changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadIssue {
+    /// Name of the file, in the archive, containing the record
+    pub file: String,
+
+    /// Line (1-based) at which the record starts, when known
+    pub line: Option<u64>,
+
+    /// Why the record has been skipped, or how it has been changed
+    pub message: String,
+}
+
+impl std::fmt::Display for LoadIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file, line, self.message),
+            None => write!(f, "{}: {}", self.file, self.message),
+        }
+    }
+}
+
+/// Where the issues found while loading go: in lenient mode they are
+/// collected, otherwise the first one aborts the load
+struct Issues<'a>(Option<&'a mut Vec<LoadIssue>>);
+
+impl Issues<'_> {
+    /// Reports a malformed record, which is an error unless in lenient mode.
+    /// A file that cannot be read, like a member failing its CRC-32 check,
+    /// is an error in lenient mode too: none of its records can be trusted.
+    fn report(&mut self, file: &str, line: Option<u64>, error: Error) -> Result<()> {
+        let unreadable = error.chain().any(|cause| {
+            cause
+                .downcast_ref::<csv::Error>()
+                .is_some_and(csv::Error::is_io_error)
+                || cause
+                    .downcast_ref::<serde_json::Error>()
+                    .is_some_and(serde_json::Error::is_io)
+        });
+        match self.0.as_mut() {
+            Some(issues) if !unreadable => {
+                // the file and the line are already those of the issue
+                let message = match error.downcast_ref::<CsvError>() {
+                    Some(error) => error.reason(),
+                    None => error.to_string(),
+                };
+                issues.push(LoadIssue {
+                    file: file.to_string(),
+                    line,
+                    message,
+                });
+                Ok(())
+            }
+            _ => Err(error),
+        }
+    }
+
+    /// Notes a record skipped or changed on purpose, only kept in lenient
+    /// mode
+    fn note(&mut self, file: &str, line: Option<u64>, message: String) {
+        if let Some(issues) = self.0.as_mut() {
+            issues.push(LoadIssue {
+                file: file.to_string(),
+                line,
+                message,
+            });
+        }
+    }
+}
+
+/// Takes a zipped file containing pois, types, and properties,
+/// and returns the corresponding model.
+///
+/// Tarballs (`.tar` or `.tar.gz`) holding the same files are accepted too,
+/// whatever their extension: they are detected from their first bytes.
+pub fn load_model_from_path<P>(path: P) -> Result<Model>
+where
+    P: AsRef<Path>,
+{
+    load_model(path.as_ref(), &LoadOptions::default(), None)
+}
+
+/// Takes a zipped file containing pois, types, and properties,
+/// and returns the corresponding model, using the given options.
+pub fn load_model_from_path_with_options<P>(path: P, options: &LoadOptions) -> Result<Model>
+where
+    P: AsRef<Path>,
+{
+    load_model(path.as_ref(), options, None)
+}
+
+/// Loads the archive read from `reader`, like the standard input. The
+/// archive is first read in memory, since loading it needs to seek.
+///
+/// ```no_run
+/// use navitia_poi_model::io::{load_model_from_reader, write_model_to_writer};
+///
+/// # fn main() -> navitia_poi_model::Result<()> {
+/// let model = load_model_from_reader(std::io::stdin().lock(), &Default::default())?;
+/// write_model_to_writer(&model, std::io::stdout().lock(), &Default::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_model_from_reader<R>(mut reader: R, options: &LoadOptions) -> Result<Model>
+where
+    R: Read,
+{
+    let mut content = Vec::new();
+    reader.read_to_end(&mut content)?;
+    load_model_from_content(Path::new("<stream>"), &content, options, None)
+}
+
+/// Same as [load_model_from_path], but malformed records (bad floats,
+/// missing columns, properties of unknown pois, ...) are skipped instead
+/// of aborting the whole load. Each skipped record is reported as a
+/// [LoadIssue].
+///
+/// Errors that make the archive unusable (missing file, missing
+/// `poi.txt`, a file failing its CRC-32 check, ...) are still returned as
+/// errors.
+pub fn load_model_lenient<P>(path: P) -> Result<(Model, Vec<LoadIssue>)>
+where
+    P: AsRef<Path>,
+{
+    load_model_lenient_with_options(path, &LoadOptions::default())
+}
+
+/// Same as [load_model_lenient], using the given options.
+pub fn load_model_lenient_with_options<P>(
+    path: P,
+    options: &LoadOptions,
+) -> Result<(Model, Vec<LoadIssue>)>
+where
+    P: AsRef<Path>,
+{
+    let mut issues = Vec::new();
+    let model = load_model(path.as_ref(), options, Some(&mut issues))?;
+    Ok((model, issues))
+}
+
+/// Loads a model. If `issues` is given, malformed records are reported
+/// there and skipped, otherwise the first one aborts the load.
+fn load_model(
+    path: &Path,
+    options: &LoadOptions,
+    issues: Option<&mut Vec<LoadIssue>>,
+) -> Result<Model> {
+    let mut file = File::open(path)?;
+    let mut magic = Vec::with_capacity(container::MAGIC_LEN);
+    (&mut file)
+        .take(container::MAGIC_LEN as u64)
+        .read_to_end(&mut magic)?;
+    if Container::sniff(&magic) != Container::Zip {
+        return load_model_from_content(path, &std::fs::read(path)?, options, issues);
+    }
+    #[cfg(feature = "mmap")]
+    {
+        // SAFETY: the archive must not be modified while it is loaded, as
+        // documented in the module
+        let content = unsafe { memmap2::Mmap::map(&file)? };
+        load_model_from_content(path, &content, options, issues)
+    }
+    #[cfg(not(feature = "mmap"))]
+    load_archive(
+        path,
+        ArchiveReader::new(zip::ZipArchive::new(file)?),
+        options,
+        issues,
+    )
+}
+
+/// Loads a model from the content of the archive in `path`, already at
+/// hand, either a zip archive or a tarball
+fn load_model_from_content(
+    path: &Path,
+    content: &[u8],
+    options: &LoadOptions,
+    issues: Option<&mut Vec<LoadIssue>>,
+) -> Result<Model> {
+    let content = container::unpack(content)
+        .with_context(|| format!("cannot unpack the tarball '{}'", path.display()))?;
+    load_archive(path, ArchiveReader::mapped(&content)?, options, issues)
+}
+
+fn load_archive<R>(
+    path: &Path,
+    archive: ArchiveReader<'_, R>,
+    options: &LoadOptions,
+    issues: Option<&mut Vec<LoadIssue>>,
+) -> Result<Model>
+where
+    R: std::io::Read + std::io::Seek,
+{
+    let traced = Traced::start("load", path.display()).with_metrics(options.metrics.as_ref());
+    let result = load_versioned_archive(path, archive, options, issues);
+    traced.finish_with(result, |model| model.pois.len())
+}
+
+/// Loads an archive with the loader of its version
+fn load_versioned_archive<R>(
+    path: &Path,
+    mut archive: ArchiveReader<'_, R>,
+    options: &LoadOptions,
+    issues: Option<&mut Vec<LoadIssue>>,
+) -> Result<Model>
+where
+    R: std::io::Read + std::io::Seek,
+{
+    if options.verify_integrity {
+        archive::verify_checksums(&mut archive.zip)
+            .with_context(|| format!("integrity check of '{}' failed", path.display()))?;
+    }
+
+    match read_version(&mut archive.zip)? {
+        1 => load_model_v1(path, &mut archive, options, issues),
+        version => bail!(
+            "'{}' uses the version {} of the archive format, but this version of \
+             navitia-poi-model only supports versions up to {}",
+            path.display(),
+            version,
+            CURRENT_VERSION
+        ),
+    }
+}
+
+/// Loads the archives in `paths`, and merges them in order, according to
+/// `strategy`. The archives are loaded in parallel, on as many threads as
+/// available cores, and each of them is merged, then dropped, as soon as
+/// those before it are merged. An archive is loaded only once the one as
+/// many archives before it is merged, so that no more models than threads
+/// wait to be merged.
+pub fn load_model_from_paths<I, P>(paths: I, strategy: &MergeStrategy) -> Result<Model>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let paths: Vec<PathBuf> = paths
+        .into_iter()
+        .map(|path| path.as_ref().to_path_buf())
+        .collect();
+    let threads = std::thread::available_parallelism()
+        .map_or(1, std::num::NonZeroUsize::get)
+        .min(paths.len());
+    let (paths, next, stopped) = (&paths, &AtomicUsize::new(0), &AtomicBool::new(false));
+    // the index of the next archive to merge, and its changes
+    let (merging, merged) = (&Mutex::new(0), &Condvar::new());
+    std::thread::scope(|scope| {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        for _ in 0..threads {
+            let sender = sender.clone();
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let path = match paths.get(index) {
+                    Some(path) => path,
+                    None => return,
+                };
+                let guard = merging.lock().unwrap_or_else(PoisonError::into_inner);
+                let guard = merged
+                    .wait_while(guard, |merging| {
+                        index >= *merging + threads && !stopped.load(Ordering::Relaxed)
+                    })
+                    .unwrap_or_else(PoisonError::into_inner);
+                drop(guard);
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                // a panic would leave the threads waiting for this archive
+                let model = std::panic::catch_unwind(|| load_model_from_path(path))
+                    .unwrap_or_else(|_| Err(anyhow!("the loading panicked")))
+                    .with_context(|| format!("cannot load '{}'", path.display()));
+                if sender.send((index, model)).is_err() {
+                    return;
+                }
+            });
+        }
+        drop(sender);
+
+        // the models loaded ahead of the next one to merge
+        let mut loaded = HashMap::new();
+        let mut merge = || -> Result<Model> {
+            let mut model = Model::default();
+            for (index, path) in paths.iter().enumerate() {
+                let next_model = loop {
+                    if let Some(model) = loaded.remove(&index) {
+                        break model;
+                    }
+                    let (index, model) = receiver
+                        .recv()
+                        .map_err(|_| anyhow!("cannot load '{}'", path.display()))?;
+                    loaded.insert(index, model);
+                };
+                model = model
+                    .try_merge_with_strategy(next_model?, strategy)
+                    .with_context(|| format!("cannot merge '{}'", path.display()))?;
+                *merging.lock().unwrap_or_else(PoisonError::into_inner) = index + 1;
+                merged.notify_all();
+            }
+            Ok(model)
+        };
+        let model = merge();
+        // on failure, the archives not yet loaded are not needed
+        let guard = merging.lock().unwrap_or_else(PoisonError::into_inner);
+        stopped.store(true, Ordering::Relaxed);
+        merged.notify_all();
+        drop(guard);
+        model
+    })
+}
+
+/// Same as [load_model_from_paths], with the archives matching the glob
+/// `pattern` (e.g. `data/*.poi`), in alphabetical order
+pub fn load_model_from_glob(pattern: &str, strategy: &MergeStrategy) -> Result<Model> {
+    let paths = glob::glob(pattern)?.collect::<std::result::Result<Vec<_>, _>>()?;
+    if paths.is_empty() {
+        bail!("no archive matches '{}'", pattern);
+    }
+    load_model_from_paths(paths, strategy)
+}
+
+/// Returns the version of the format of the archive in `path`.
+pub fn archive_version<P>(path: P) -> Result<u32>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(path.as_ref())?;
+    read_version(&mut zip::ZipArchive::new(file)?)
+}
+
+/// Reads the format version of an archive. Archives written before the
+/// version was embedded are of version 1.
+fn read_version<R>(zip: &mut zip::ZipArchive<R>) -> Result<u32>
+where
+    R: std::io::Read + std::io::Seek,
+{
+    match zip.by_name("version.txt") {
+        Ok(mut zipper) => {
+            let mut version = String::new();
+            zipper.read_to_string(&mut version)?;
+            match version.trim().parse() {
+                Ok(version) if version > 0 => Ok(version),
+                _ => bail!("invalid archive format version '{}'", version.trim()),
+            }
+        }
+        Err(_) => Ok(1),
+    }
+}
+
+/// Loads an archive of version 1
+fn load_model_v1<R>(
+    path: &Path,
+    zip: &mut ArchiveReader<'_, R>,
+    options: &LoadOptions,
+    issues: Option<&mut Vec<LoadIssue>>,
+) -> Result<Model>
+where
+    R: std::io::Read + std::io::Seek,
+{
+    let mut issues = Issues(issues);
+
+    // archives of older exporters are read with their own dialect
+    let dialect = dialect::detect(zip, options);
+    let options = &LoadOptions {
+        csv: CsvOptions {
+            delimiter: dialect
+                .as_ref()
+                .map_or(options.csv.delimiter, |dialect| dialect.delimiter),
+            ..options.csv.clone()
+        },
+        ..options.clone()
+    };
+
+    let mut pois = BTreeMap::new();
+    // POIs skipped because of their coordinates, their properties are
+    // skipped as well
+    let mut skipped_pois = HashSet::new();
+    let accessibility_columns = Cell::new([false; 4]);
+    let zipper = zip.by_name("poi.txt").context("cannot read 'poi.txt'")?;
+    let poi_records = read_csv_with(options, zipper, |headers| {
+        let decoder = PoiDecoder::new(headers, &options.column_defaults)?;
+        accessibility_columns.set(decoder.accessibility_columns());
+        Ok(move |record: &ByteRecord| decoder.decode(record))
+    });
+    for (line, rec) in poi_records {
+        cancel::check(options.cancellation.as_ref())?;
+        let mut poi = match rec {
+            Ok(rec) => Poi::from(rec),
+            Err(e) => {
+                issues.report("poi.txt", line, e)?;
+                continue;
+            }
+        };
+        if !poi.coord.is_finite() {
+            // NaN and infinite coordinates can neither be kept nor clamped:
+            // they break the ordering of spatial indexes
+            let coordinates = format!(
+                "non-finite coordinates (lon: {}, lat: {})",
+                poi.coord.lon(),
+                poi.coord.lat()
+            );
+            if options.coordinate_policy == CoordinatePolicy::SkipRecord {
+                let message = format!("poi '{}' skipped, {}", poi.id, coordinates);
+                issues.note("poi.txt", line, message);
+            } else {
+                let error = anyhow!("{} for poi '{}'", coordinates, poi.id);
+                issues.report("poi.txt", line, error)?;
+            }
+            skipped_pois.insert(poi.id);
+            continue;
+        }
+        if !poi.coord.is_valid() {
+            match options.coordinate_policy {
+                CoordinatePolicy::Accept => {}
+                CoordinatePolicy::Reject => {
+                    issues.report(
+                        "poi.txt",
+                        line,
+                        anyhow!(
+                            "invalid coordinates (lon: {}, lat: {}) for poi '{}'",
+                            poi.coord.lon(),
+                            poi.coord.lat(),
+                            poi.id
+                        ),
+                    )?;
+                    skipped_pois.insert(poi.id);
+                    continue;
+                }
+                CoordinatePolicy::Clamp => {
+                    let clamped = Coord::new(
+                        poi.coord.lon().clamp(-180., 180.),
+                        poi.coord.lat().clamp(-90., 90.),
+                    );
+                    warnings::warn(
+                        options.warnings.as_ref(),
+                        Warning::CoordinatesClamped {
+                            poi_id: poi.id.clone(),
+                            from: std::mem::replace(&mut poi.coord, clamped.clone()),
+                            to: clamped,
+                        },
+                    );
+                }
+                CoordinatePolicy::SkipRecord => {
+                    issues.note(
+                        "poi.txt",
+                        line,
+                        format!(
+                            "poi '{}' skipped, invalid coordinates (lon: {}, lat: {})",
+                            poi.id,
+                            poi.coord.lon(),
+                            poi.coord.lat()
+                        ),
+                    );
+                    skipped_pois.insert(poi.id);
+                    continue;
+                }
+            }
+        }
+        if options.sanitize {
+            let what = format!("name of poi '{}'", poi.id);
+            sanitize(&mut issues, "poi.txt", line, &what, &mut poi.name);
+        }
+        pois.insert(poi.id.clone(), poi);
+    }
+
+    let mut poi_types = HashMap::new();
+    let zipper = zip
+        .by_name("poi_type.txt")
+        .context("cannot read 'poi_type.txt'")?;
+    for (line, rec) in read_csv::<PoiTypeRecord>(options, zipper) {
+        cancel::check(options.cancellation.as_ref())?;
+        match rec {
+            Ok(rec) => {
+                let mut poi_type = PoiType::from(rec);
+                if options.sanitize {
+                    let what = format!("name of poi type '{}'", poi_type.id);
+                    sanitize(&mut issues, "poi_type.txt", line, &what, &mut poi_type.name);
+                }
+                poi_types.insert(poi_type.id.clone(), poi_type);
+            }
+            Err(e) => issues.report("poi_type.txt", line, e)?,
+        }
+    }
+
+    // poi_type_translations.txt is optional
+    if let Some(zipper) = zip.optional_by_name("poi_type_translations.txt")? {
+        for (line, rec) in read_csv::<PoiTypeTranslationRecord>(options, zipper) {
+            cancel::check(options.cancellation.as_ref())?;
+            let translation = match rec {
+                Ok(translation) => translation,
+                Err(e) => {
+                    issues.report("poi_type_translations.txt", line, e)?;
+                    continue;
+                }
+            };
+            match poi_types.get_mut(&translation.poi_type_id) {
+                Some(poi_type) => {
+                    poi_type
+                        .translations
+                        .insert(translation.lang, translation.poi_type_name);
+                }
+                None => issues.report(
+                    "poi_type_translations.txt",
+                    line,
+                    anyhow!(
+                        "in file '{}', cannot find poi type '{}' for translation",
+                        path.display(),
+                        &translation.poi_type_id
+                    ),
+                )?,
+            }
+        }
+    }
+
+    // For poi_properties.txt, it's a bit different: If the file is not
+    // present, it does not mean it is an error.
+    if let Some(zipper) = zip.optional_by_name("poi_properties.txt")? {
+        for (line, rec) in read_csv::<PoiProperty>(options, zipper) {
+            cancel::check(options.cancellation.as_ref())?;
+            let mut poi_property = match rec {
+                Ok(poi_property) => poi_property,
+                Err(e) => {
+                    issues.report("poi_properties.txt", line, e)?;
+                    continue;
+                }
+            };
+            match pois.get_mut(&poi_property.poi_id) {
+                Some(poi) => {
+                    if options.sanitize {
+                        let what = format!(
+                            "property '{}' of poi '{}'",
+                            poi_property.key, poi_property.poi_id
+                        );
+                        sanitize(
+                            &mut issues,
+                            "poi_properties.txt",
+                            line,
+                            &what,
+                            &mut poi_property.value,
+                        );
+                    }
+                    let key = match &options.property_keys {
+                        Some(normalization) => normalization.normalize(&poi_property.key),
+                        None => poi_property.key,
+                    };
+                    let mut value = poi_property.value;
+                    if let Some(constraints) = &options.property_constraints {
+                        let constraint = constraints.constraint(&key);
+                        if let Some(violation) = constraint.check(&value) {
+                            let message = format!(
+                                "property '{}' of poi '{}': {}",
+                                key, poi_property.poi_id, violation
+                            );
+                            match constraints.policy {
+                                ViolationPolicy::Report => {
+                                    issues.note("poi_properties.txt", line, message)
+                                }
+                                ViolationPolicy::Truncate => {
+                                    value = constraint.fix(&value);
+                                    issues.note(
+                                        "poi_properties.txt",
+                                        line,
+                                        format!("{}, fixed", message),
+                                    );
+                                    warnings::warn(
+                                        options.warnings.as_ref(),
+                                        Warning::PropertyFixed {
+                                            poi_id: poi_property.poi_id.clone(),
+                                            key: key.clone(),
+                                            violation: violation.to_string(),
+                                        },
+                                    );
+                                }
+                                ViolationPolicy::Reject => {
+                                    issues.report("poi_properties.txt", line, anyhow!(message))?;
+                                    continue;
+                                }
+                            }
+                        }
+                    }
+                    if let Some(previous) = poi.properties.insert(key.clone(), value) {
+                        warnings::warn(
+                            options.warnings.as_ref(),
+                            Warning::PropertyOverwritten {
+                                poi_id: poi_property.poi_id,
+                                key,
+                                previous,
+                            },
+                        );
+                    }
+                }
+                None if skipped_pois.contains(&poi_property.poi_id) => {}
+                None => issues.report(
+                    "poi_properties.txt",
+                    line,
+                    anyhow!(
+                        "in file '{}', cannot find poi '{}' for property insertion",
+                        path.display(),
+                        &poi_property.poi_id
+                    ),
+                )?,
+            }
+        }
+    }
+    // the archives without the columns of the accessibility still have it
+    // in the properties
+    let [wheelchair, visual_aids, audio_aids, elevator] = accessibility_columns.get();
+    for poi in pois.values_mut() {
+        let parsed = Accessibility::from_properties(&poi.properties);
+        poi.accessibility = poi.accessibility.or(Accessibility {
+            wheelchair: parsed.wheelchair.filter(|_| !wheelchair),
+            visual_aids: parsed.visual_aids.filter(|_| !visual_aids),
+            audio_aids: parsed.audio_aids.filter(|_| !audio_aids),
+            elevator: parsed.elevator.filter(|_| !elevator),
+        });
+    }
+
+    // poi_relations.txt is optional as well
+    let mut relations = BTreeSet::new();
+    if let Some(zipper) = zip.optional_by_name("poi_relations.txt")? {
+        for (line, rec) in read_csv::<PoiRelationRecord>(options, zipper) {
+            cancel::check(options.cancellation.as_ref())?;
+            let relation = match rec {
+                Ok(rec) => PoiRelation::from(rec),
+                Err(e) => {
+                    issues.report("poi_relations.txt", line, e)?;
+                    continue;
+                }
+            };
+            let missing = [&relation.parent_id, &relation.child_id]
+                .iter()
+                .copied()
+                .find(|id| !pois.contains_key(*id));
+            match missing {
+                None => {
+                    relations.insert(relation);
+                }
+                Some(id) if skipped_pois.contains(id) => {}
+                Some(id) => issues.report(
+                    "poi_relations.txt",
+                    line,
+                    anyhow!(
+                        "in file '{}', cannot find poi '{}' for relation",
+                        path.display(),
+                        id
+                    ),
+                )?,
+            }
+        }
+    }
+
+    // metadata.json is optional as well
+    let metadata = match zip.optional_by_name("metadata.json")? {
+        Some(zipper) => match serde_json::from_reader(zipper) {
+            Ok(metadata) => Some(metadata),
+            Err(e) => {
+                issues.report("metadata.json", None, e.into())?;
+                None
+            }
+        },
+        None => None,
+    };
+    let metadata = match dialect {
+        Some(dialect) => Some(crate::Metadata {
+            dialect: Some(dialect),
+            ..metadata.unwrap_or_default()
+        }),
+        None => metadata,
+    };
+
+    Ok(Model {
+        pois,
+        poi_types,
+        metadata,
+        relations,
+    })
+}
+
+/// Cleans `value` up, noting the change if any
+fn sanitize(
+    issues: &mut Issues<'_>,
+    file: &str,
+    line: Option<u64>,
+    what: &str,
+    value: &mut String,
+) {
+    if let Cow::Owned(sanitized) = crate::normalize::sanitize(value) {
+        issues.note(
+            file,
+            line,
+            format!("{} sanitized from {:?} to {:?}", what, value, sanitized),
+        );
+        *value = sanitized;
+    }
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn ser_from_bool<S>(v: &bool, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u8(*v as u8)
+}
+
+fn de_from_bool<'de, D>(deserializer: D) -> std::result::Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Cow::<str>::deserialize(deserializer)?;
+    parse_bool(&value)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid boolean '{}'", value)))
+}
+
+/// Parses a boolean written as a number, non-zero being true, or as `true`
+/// or `false`, whatever their case
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    match value.parse::<u8>() {
+        Ok(i) => Some(i != 0),
+        Err(_) if value.eq_ignore_ascii_case("true") => Some(true),
+        Err(_) if value.eq_ignore_ascii_case("false") => Some(false),
+        Err(_) => None,
+    }
+}
+
+fn de_from_optional_u8<'de, D>(deserializer: D) -> std::result::Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let i = Option::<u8>::deserialize(deserializer)?;
+    Ok(i.map(|i| i != 0))
+}
+
+/// Used to import / export POI to / from CSV
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct PoiRecord {
+    /// Unique id of the POI
+    #[serde(rename = "poi_id")]
+    pub id: String,
+    /// Id of the POI type
+    #[serde(rename = "poi_type_id")]
+    pub type_id: String,
+    /// Name of the POI
+    #[serde(rename = "poi_name")]
+    pub name: String,
+    /// Latitude of the POI, as read: out-of-range values are kept so that
+    /// the [CoordinatePolicy] of the load decides what to do with them
+    #[serde(rename = "poi_lat")]
+    pub lat: f64,
+    /// Longitude of the POI, as read, see [PoiRecord::lat]
+    #[serde(rename = "poi_lon")]
+    pub lon: f64,
+    /// Weight of the POI
+    #[serde(rename = "poi_weight")]
+    pub weight: Weight,
+    /// Indicates if the POI is visible in the map. Written as `0` or `1`,
+    /// read as well from `true` or `false`.
+    #[serde(
+        rename = "poi_visible",
+        serialize_with = "ser_from_bool",
+        deserialize_with = "de_from_bool"
+    )]
+    pub visible: bool,
+    /// When the POI was created, if known
+    #[serde(rename = "poi_created_at", default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the POI was last modified, if known
+    #[serde(rename = "poi_updated_at", default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Access for wheelchairs, if known
+    #[serde(rename = "poi_wheelchair", default)]
+    pub wheelchair: Option<Access>,
+    /// Aids for visually impaired people, if known
+    #[serde(rename = "poi_visual_aids", default)]
+    pub visual_aids: Option<Access>,
+    /// Aids for hearing impaired people, if known
+    #[serde(rename = "poi_audio_aids", default)]
+    pub audio_aids: Option<Access>,
+    /// Elevator, if known
+    #[serde(rename = "poi_elevator", default)]
+    pub elevator: Option<Access>,
+    /// Elevation of the POI in meters, if known
+    #[serde(rename = "poi_elevation", default)]
+    pub elevation_m: Option<f64>,
+}
+
+impl From<&Poi> for PoiRecord {
+    fn from(poi: &Poi) -> PoiRecord {
+        PoiRecord {
+            id: poi.id.clone(),
+            type_id: poi.poi_type_id.clone(),
+            name: poi.name.clone(),
+            lat: poi.coord.lat(),
+            lon: poi.coord.lon(),
+            visible: poi.visible,
+            weight: poi.weight,
+            created_at: poi.created_at,
+            updated_at: poi.updated_at,
+            wheelchair: poi.accessibility.wheelchair,
+            visual_aids: poi.accessibility.visual_aids,
+            audio_aids: poi.accessibility.audio_aids,
+            elevator: poi.accessibility.elevator,
+            elevation_m: poi.elevation_m,
+        }
+    }
+}
+
+/// The optional columns of `poi.txt` holding a value for at least one POI
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct OptionalPoiColumns {
+    created_at: bool,
+    updated_at: bool,
+    wheelchair: bool,
+    visual_aids: bool,
+    audio_aids: bool,
+    elevator: bool,
+    elevation_m: bool,
+}
+
+impl OptionalPoiColumns {
+    /// The columns needed by `pois`. The column of an attribute of the
+    /// accessibility is also needed when a property would be parsed as this
+    /// attribute without it, so that it reads back as unknown.
+    pub(crate) fn of<'a>(pois: impl Iterator<Item = &'a Poi>) -> OptionalPoiColumns {
+        pois.fold(OptionalPoiColumns::default(), |columns, poi| {
+            let accessibility = poi
+                .accessibility
+                .or(Accessibility::from_properties(&poi.properties));
+            columns.or(OptionalPoiColumns {
+                created_at: poi.created_at.is_some(),
+                updated_at: poi.updated_at.is_some(),
+                elevation_m: poi.elevation_m.is_some(),
+                ..OptionalPoiColumns::of_accessibility(&accessibility)
+            })
+        })
+    }
+
+    /// The columns needed by a record of `poi.txt`, regardless of its
+    /// properties
+    fn of_record(record: &PoiRecord) -> OptionalPoiColumns {
+        OptionalPoiColumns {
+            created_at: record.created_at.is_some(),
+            updated_at: record.updated_at.is_some(),
+            wheelchair: record.wheelchair.is_some(),
+            visual_aids: record.visual_aids.is_some(),
+            audio_aids: record.audio_aids.is_some(),
+            elevator: record.elevator.is_some(),
+            elevation_m: record.elevation_m.is_some(),
+        }
+    }
+
+    /// The columns of the known attributes of `accessibility`
+    fn of_accessibility(accessibility: &Accessibility) -> OptionalPoiColumns {
+        OptionalPoiColumns {
+            wheelchair: accessibility.wheelchair.is_some(),
+            visual_aids: accessibility.visual_aids.is_some(),
+            audio_aids: accessibility.audio_aids.is_some(),
+            elevator: accessibility.elevator.is_some(),
+            ..OptionalPoiColumns::default()
+        }
+    }
+
+    /// The columns needed by either `self` or `other`
+    fn or(self, other: OptionalPoiColumns) -> OptionalPoiColumns {
+        OptionalPoiColumns {
+            created_at: self.created_at || other.created_at,
+            updated_at: self.updated_at || other.updated_at,
+            wheelchair: self.wheelchair || other.wheelchair,
+            visual_aids: self.visual_aids || other.visual_aids,
+            audio_aids: self.audio_aids || other.audio_aids,
+            elevator: self.elevator || other.elevator,
+            elevation_m: self.elevation_m || other.elevation_m,
+        }
+    }
+
+    /// Names of the columns written, in the order of [PoiRow]
+    pub(crate) fn names(&self) -> Vec<&'static str> {
+        [
+            ("poi_created_at", self.created_at),
+            ("poi_updated_at", self.updated_at),
+            ("poi_wheelchair", self.wheelchair),
+            ("poi_visual_aids", self.visual_aids),
+            ("poi_audio_aids", self.audio_aids),
+            ("poi_elevator", self.elevator),
+            ("poi_elevation", self.elevation_m),
+        ]
+        .iter()
+        .filter(|(_, written)| *written)
+        .map(|(name, _)| *name)
+        .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.names().len()
+    }
+}
+
+/// A [PoiRecord] serialized with the given optional columns only, with
+/// `poi_visible` in the given format, and with its projected coordinates if
+/// a projection is given
+struct PoiRow {
+    record: PoiRecord,
+    columns: OptionalPoiColumns,
+    visible_format: BoolFormat,
+    projection: Option<Projection>,
+}
+
+impl Serialize for PoiRow {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeStruct;
+        let (record, columns) = (&self.record, &self.columns);
+        let projected = if self.projection.is_some() { 2 } else { 0 };
+        let len = 7 + columns.len() + projected;
+        let mut row = serializer.serialize_struct("PoiRecord", len)?;
+        row.serialize_field("poi_id", &record.id)?;
+        row.serialize_field("poi_type_id", &record.type_id)?;
+        row.serialize_field("poi_name", &record.name)?;
+        row.serialize_field("poi_lat", &record.lat)?;
+        row.serialize_field("poi_lon", &record.lon)?;
+        row.serialize_field("poi_weight", &record.weight)?;
+        match self.visible_format {
+            BoolFormat::Numeric => row.serialize_field("poi_visible", &u8::from(record.visible))?,
+            BoolFormat::Text => row.serialize_field("poi_visible", &record.visible)?,
+        }
+        if columns.created_at {
+            row.serialize_field("poi_created_at", &record.created_at)?;
+        }
+        if columns.updated_at {
+            row.serialize_field("poi_updated_at", &record.updated_at)?;
+        }
+        if columns.wheelchair {
+            row.serialize_field("poi_wheelchair", &record.wheelchair)?;
+        }
+        if columns.visual_aids {
+            row.serialize_field("poi_visual_aids", &record.visual_aids)?;
+        }
+        if columns.audio_aids {
+            row.serialize_field("poi_audio_aids", &record.audio_aids)?;
+        }
+        if columns.elevator {
+            row.serialize_field("poi_elevator", &record.elevator)?;
+        }
+        if columns.elevation_m {
+            row.serialize_field("poi_elevation", &record.elevation_m)?;
+        }
+        if let Some(projection) = self.projection {
+            let projected = projection.project(&Coord::new(record.lon, record.lat));
+            row.serialize_field("poi_x", &projected.map(|(x, _)| x))?;
+            row.serialize_field("poi_y", &projected.map(|(_, y)| y))?;
+        }
+        row.end()
+    }
+}
+
+impl PoiRecord {
+    /// The coordinate of the record, failing when the latitude or the
+    /// longitude is out of range
+    pub fn coord(&self) -> Result<Coord> {
+        Coord::try_new(self.lon, self.lat)
+            .with_context(|| format!("invalid coordinates for poi '{}'", self.id))
+    }
+}
+
+impl From<PoiRecord> for Poi {
+    fn from(record: PoiRecord) -> Poi {
+        Poi {
+            id: record.id,
+            name: record.name,
+            coord: Coord::new(record.lon, record.lat),
+            poi_type_id: record.type_id,
+            properties: BTreeMap::default(),
+            visible: record.visible,
+            weight: record.weight,
+            accessibility: Accessibility {
+                wheelchair: record.wheelchair,
+                visual_aids: record.visual_aids,
+                audio_aids: record.audio_aids,
+                elevator: record.elevator,
+            },
+            elevation_m: record.elevation_m,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Ord, PartialOrd, Eq, PartialEq)]
+struct PoiProperty {
+    pub poi_id: String,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Ord, PartialOrd, Eq, PartialEq)]
+struct PoiTypeTranslationRecord {
+    poi_type_id: String,
+    lang: String,
+    poi_type_name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct PoiRelationRecord {
+    parent_poi_id: String,
+    child_poi_id: String,
+    relation_type: String,
+}
+
+impl From<&PoiRelation> for PoiRelationRecord {
+    fn from(relation: &PoiRelation) -> PoiRelationRecord {
+        PoiRelationRecord {
+            parent_poi_id: relation.parent_id.clone(),
+            child_poi_id: relation.child_id.clone(),
+            relation_type: relation.relation_type.clone(),
+        }
+    }
+}
+
+impl From<PoiRelationRecord> for PoiRelation {
+    fn from(record: PoiRelationRecord) -> PoiRelation {
+        PoiRelation {
+            parent_id: record.parent_poi_id,
+            child_id: record.child_poi_id,
+            relation_type: record.relation_type,
+        }
+    }
+}
+
+impl From<PoiProperty> for Property {
+    fn from(property: PoiProperty) -> Property {
+        Property {
+            key: property.key,
+            value: property.value,
+        }
+    }
+}
+
+/// A type of POI
+/// We use a different type for serialization, because we want to make sure
+/// we have adequate headers. In some files (json?) the headers are just
+/// 'id', and 'name', while in others it's 'poi_type_id' and 'poi_type_name'
+#[derive(Debug, Deserialize, Serialize, Ord, PartialOrd, Eq, PartialEq)]
+pub struct PoiTypeRecord {
+    /// Unique id of the POI type
+    #[serde(rename = "poi_type_id")]
+    pub id: String,
+
+    /// Name of the POI type.
+    #[serde(rename = "poi_type_name")]
+    pub name: String,
+
+    /// Id of the parent type, if any
+    #[serde(rename = "poi_type_parent_id", default)]
+    pub parent_id: Option<String>,
+
+    /// Icon used to render the POIs of this type, if any
+    #[serde(rename = "poi_type_icon", default)]
+    pub icon: Option<String>,
+
+    /// Color used to render the POIs of this type, if any
+    #[serde(rename = "poi_type_color", default)]
+    pub color: Option<String>,
+
+    /// Minimum zoom level at which the POIs of this type are displayed
+    #[serde(rename = "poi_type_zoom_min", default)]
+    pub zoom_min: Option<u8>,
+
+    /// Whether the POIs of this type are displayed by default, if known.
+    /// Written as `0` or `1`.
+    #[serde(
+        rename = "poi_type_visible_by_default",
+        default,
+        deserialize_with = "de_from_optional_u8"
+    )]
+    pub visible_by_default: Option<bool>,
+}
+
+/// The optional columns of `poi_type.txt` holding a value for at least
+/// one POI type
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct PoiTypeColumns {
+    parent_id: bool,
+    icon: bool,
+    color: bool,
+    zoom_min: bool,
+    visible_by_default: bool,
+}
+
+impl PoiTypeColumns {
+    pub(crate) fn of<'a>(poi_types: impl Iterator<Item = &'a PoiType>) -> PoiTypeColumns {
+        poi_types.fold(PoiTypeColumns::default(), |columns, poi_type| {
+            PoiTypeColumns {
+                parent_id: columns.parent_id || poi_type.parent_id.is_some(),
+                icon: columns.icon || poi_type.icon.is_some(),
+                color: columns.color || poi_type.color.is_some(),
+                zoom_min: columns.zoom_min || poi_type.zoom_min.is_some(),
+                visible_by_default: columns.visible_by_default
+                    || poi_type.visible_by_default.is_some(),
+            }
+        })
+    }
+
+    /// Names of the columns written, in the order of [PoiTypeRow]
+    pub(crate) fn names(&self) -> Vec<&'static str> {
+        [
+            ("poi_type_parent_id", self.parent_id),
+            ("poi_type_icon", self.icon),
+            ("poi_type_color", self.color),
+            ("poi_type_zoom_min", self.zoom_min),
+            ("poi_type_visible_by_default", self.visible_by_default),
+        ]
+        .iter()
+        .filter(|(_, written)| *written)
+        .map(|(name, _)| *name)
+        .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.names().len()
+    }
+}
+
+/// A [PoiTypeRecord] serialized with the given optional columns only
+struct PoiTypeRow {
+    record: PoiTypeRecord,
+    columns: PoiTypeColumns,
+}
+
+impl Serialize for PoiTypeRow {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeStruct;
+        let (record, columns) = (&self.record, &self.columns);
+        let mut row = serializer.serialize_struct("PoiTypeRecord", 2 + columns.len())?;
+        row.serialize_field("poi_type_id", &record.id)?;
+        row.serialize_field("poi_type_name", &record.name)?;
+        if columns.parent_id {
+            row.serialize_field("poi_type_parent_id", &record.parent_id)?;
+        }
+        if columns.icon {
+            row.serialize_field("poi_type_icon", &record.icon)?;
+        }
+        if columns.color {
+            row.serialize_field("poi_type_color", &record.color)?;
+        }
+        if columns.zoom_min {
+            row.serialize_field("poi_type_zoom_min", &record.zoom_min)?;
+        }
+        if columns.visible_by_default {
+            row.serialize_field(
+                "poi_type_visible_by_default",
+                &record.visible_by_default.map(u8::from),
+            )?;
+        }
+        row.end()
+    }
+}
+
+impl From<PoiTypeRecord> for PoiType {
+    fn from(record: PoiTypeRecord) -> PoiType {
+        PoiType {
+            id: record.id,
+            name: record.name,
+            parent_id: record.parent_id,
+            icon: record.icon,
+            color: record.color,
+            zoom_min: record.zoom_min,
+            visible_by_default: record.visible_by_default,
+            translations: BTreeMap::new(),
+        }
+    }
+}
+
+impl From<PoiType> for PoiTypeRecord {
+    fn from(poi_type: PoiType) -> PoiTypeRecord {
+        PoiTypeRecord {
+            id: poi_type.id,
+            name: poi_type.name,
+            parent_id: poi_type.parent_id,
+            icon: poi_type.icon,
+            color: poi_type.color,
+            zoom_min: poi_type.zoom_min,
+            visible_by_default: poi_type.visible_by_default,
+        }
+    }
+}
+
+/// Converts items into CSV, and streams them to a writer.
+fn write_csv<W, I, T>(writer: W, options: &CsvOptions, items: I) -> Result<()>
+where
+    W: std::io::Write,
+    I: Iterator<Item = T>,
+    T: Serialize,
+{
+    let mut builder = csv::WriterBuilder::new();
+    builder
+        .has_headers(true)
+        .delimiter(options.delimiter)
+        .quote(options.quote)
+        .quote_style(options.quote_style)
+        .double_quote(options.escape.is_none());
+    if let Some(escape) = options.escape {
+        builder.escape(escape);
+    }
+    if let Some(terminator) = options.terminator {
+        builder.terminator(terminator);
+    }
+    let mut csv_writer = builder.from_writer(writer);
+    for item in items {
+        csv_writer.serialize(item)?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// Streams records from a CSV member of an archive, along with the line at
+/// which each of them starts, reporting the progress. The errors are
+/// [CsvError]s locating the offending record.
+fn read_csv<'a, T>(
+    options: &LoadOptions,
+    zipper: ArchiveMember<'a>,
+) -> impl Iterator<Item = (Option<u64>, Result<T>)> + 'a
+where
+    T: DeserializeOwned + 'a,
+{
+    read_csv_with(options, zipper, |headers| {
+        let headers = headers.clone();
+        Ok(move |record: &ByteRecord| record.deserialize(Some(&headers)).map_err(Into::into))
+    })
+}
+
+/// Same as [read_csv], the records being decoded by the decoder that
+/// `make_decoder` builds from the headers, on the first record (files
+/// without records have no headers). A single buffer is used for all the
+/// records.
+fn read_csv_with<'a, T, F, D>(
+    options: &LoadOptions,
+    zipper: ArchiveMember<'a>,
+    make_decoder: F,
+) -> impl Iterator<Item = (Option<u64>, Result<T>)> + 'a
+where
+    T: 'a,
+    F: FnOnce(&ByteRecord) -> Result<D> + 'a,
+    D: FnMut(&ByteRecord) -> Result<T> + 'a,
+{
+    let name = zipper.name().to_string();
+    let total_bytes = zipper.size();
+    let reader = Counting::new(zipper);
+    let tracker = Tracker::new(
+        options.progress.as_ref(),
+        options.metrics.as_ref(),
+        &name,
+        Some(total_bytes),
+        reader.counter(),
+    );
+    let mut builder = csv::ReaderBuilder::new();
+    builder
+        .delimiter(options.csv.delimiter)
+        .quote(options.csv.quote)
+        .double_quote(options.csv.escape.is_none())
+        .escape(options.csv.escape)
+        .buffer_capacity(CSV_BUFFER_CAPACITY);
+    if let Some(terminator) = options.csv.terminator {
+        builder.terminator(terminator);
+    }
+    let reader = DecodingReader::new(reader, options.encoding);
+    let mut csv_reader = builder.from_reader(reader);
+    let delimiter = options.csv.delimiter;
+
+    let records = match csv_reader.byte_headers() {
+        Ok(headers) => {
+            let renamed = dialect::rename_headers(&name, headers, &options.header_aliases);
+            for (header, current) in headers.iter().zip(renamed.iter()) {
+                if header != current {
+                    warnings::warn(
+                        options.warnings.as_ref(),
+                        Warning::DeprecatedHeader {
+                            file: name.clone(),
+                            header: String::from_utf8_lossy(header).into_owned(),
+                            current: String::from_utf8_lossy(current).into_owned(),
+                        },
+                    );
+                }
+            }
+            let headers = renamed;
+            let mut make_decoder = Some(make_decoder);
+            let mut decoder = None;
+            let mut record = ByteRecord::new();
+            Either::Left(std::iter::from_fn(move || {
+                let error = |line: Option<u64>, record: &ByteRecord, e: Error| -> Result<T> {
+                    Err(CsvError::new(&name, line, Some((record, delimiter)), e).into())
+                };
+                match csv_reader.read_byte_record(&mut record) {
+                    Ok(true) => {}
+                    Ok(false) => return None,
+                    Err(e) => {
+                        let line = e.position().map(csv::Position::line);
+                        return Some((line, error(line, &record, e.into())));
+                    }
+                }
+                let line = record.position().map(csv::Position::line);
+                if decoder.is_none() {
+                    // a file whose headers cannot be decoded is skipped
+                    match make_decoder.take()?(&headers) {
+                        Ok(decode) => decoder = Some(decode),
+                        Err(e) => return Some((line, error(line, &headers, e))),
+                    }
+                }
+                let decode = decoder.as_mut()?;
+                Some((line, decode(&record).or_else(|e| error(line, &record, e))))
+            }))
+        }
+        Err(e) => {
+            let line = e.position().map(csv::Position::line);
+            let error = CsvError::new(&name, line, None, e.into());
+            Either::Right(std::iter::once((line, Err(error.into()))))
+        }
+    };
+    Tracked::new(records, tracker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        load_model_from_paths, load_model_from_reader, load_model_lenient, write_model_to_path,
+        write_model_to_writer, LoadOptions, WriteOptions,
+    };
+    use crate::accessibility::{Access, Accessibility};
+    use crate::{MergeStrategy, Model, Poi};
+    use std::io::{Read, Write};
+
+    /// An archive made of the given files, stored so that their bytes can
+    /// be altered in place
+    fn archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (name, content) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// The content of `file` in `archive`
+    fn member(archive: &[u8], file: &str) -> String {
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive)).unwrap();
+        let mut content = String::new();
+        zip.by_name(file)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    fn round_trip(model: &Model) -> (Vec<u8>, Model) {
+        let mut archive = Vec::new();
+        write_model_to_writer(model, &mut archive, &WriteOptions::default()).unwrap();
+        let loaded = load_model_from_reader(archive.as_slice(), &LoadOptions::default()).unwrap();
+        (archive, loaded)
+    }
+
+    fn poi(properties: &[(&str, &str)], accessibility: Accessibility) -> Poi {
+        Poi {
+            id: "poi:1".to_string(),
+            properties: properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            accessibility,
+            ..Poi::default()
+        }
+    }
+
+    #[test]
+    fn accessibility_properties_without_accessibility_round_trip() {
+        let model: Model = vec![poi(&[("lift", "yes")], Accessibility::default())]
+            .into_iter()
+            .collect();
+        let (archive, loaded) = round_trip(&model);
+        assert!(member(&archive, "poi.txt").contains("poi_elevator"));
+        assert_eq!(loaded, model);
+    }
+
+    #[test]
+    fn accessibility_is_mirrored_in_the_properties() {
+        let accessibility = Accessibility {
+            wheelchair: Some(Access::Limited),
+            ..Accessibility::default()
+        };
+        let mut mirrored = poi(&[("wheelchair", "no")], Accessibility::default());
+        mirrored.set_accessibility(accessibility);
+        let model: Model = vec![mirrored].into_iter().collect();
+        let (archive, loaded) = round_trip(&model);
+        let properties = member(&archive, "poi_properties.txt");
+        assert!(properties.contains("poi:1;wheelchair;limited"));
+        assert!(!properties.contains("poi:1;wheelchair;no"));
+        assert_eq!(loaded, model);
+
+        // set without being mirrored, it is mirrored when saved all the same
+        let model: Model = vec![poi(&[], accessibility)].into_iter().collect();
+        let (archive, loaded) = round_trip(&model);
+        let properties = member(&archive, "poi_properties.txt");
+        assert!(properties.contains("poi:1;wheelchair;limited"));
+        assert_eq!(loaded.pois["poi:1"].accessibility, accessibility);
+    }
+
+    #[test]
+    fn accessibility_is_parsed_from_the_properties_without_its_columns() {
+        let archive = archive(&[
+            (
+                "poi.txt",
+                "poi_id;poi_type_id;poi_name;poi_lat;poi_lon;poi_wheelchair\n\
+                 poi:1;amenity:parking;Parking;48.8;2.3;\n",
+            ),
+            (
+                "poi_type.txt",
+                "poi_type_id;poi_type_name\namenity:parking;Parking\n",
+            ),
+            (
+                "poi_properties.txt",
+                "poi_id;key;value\npoi:1;lift;yes\npoi:1;wheelchair_access;yes\n",
+            ),
+        ]);
+        let model = load_model_from_reader(archive.as_slice(), &LoadOptions::default()).unwrap();
+        let expected = Accessibility {
+            elevator: Some(Access::Yes),
+            ..Accessibility::default()
+        };
+        assert_eq!(model.pois["poi:1"].accessibility, expected);
+    }
+
+    #[test]
+    fn paths_are_merged_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<_> = (0..40)
+            .map(|index| {
+                let model: Model = vec![
+                    Poi {
+                        id: "poi:shared".to_string(),
+                        name: index.to_string(),
+                        ..Poi::default()
+                    },
+                    Poi {
+                        id: format!("poi:{}", index),
+                        ..Poi::default()
+                    },
+                ]
+                .into_iter()
+                .collect();
+                let path = dir.path().join(format!("{}.poi", index));
+                write_model_to_path(&model, &path).unwrap();
+                path
+            })
+            .collect();
+        let model = load_model_from_paths(&paths, &MergeStrategy::KeepLast).unwrap();
+        assert_eq!(model.pois.len(), 41);
+        assert_eq!(model.pois["poi:shared"].name, "39");
+
+        let mut paths = paths;
+        paths.insert(20, dir.path().join("missing.poi"));
+        let error = load_model_from_paths(&paths, &MergeStrategy::KeepLast).unwrap_err();
+        assert!(error.to_string().contains("missing.poi"));
+    }
+
+    const POIS: &str = "poi_id;poi_type_id;poi_name;poi_lat;poi_lon\n\
+                        poi:1;amenity:parking;Parking Gare;48.84;2.37\n\
+                        poi:2;amenity:parking;Parking Bercy;48.83;2.38\n";
+
+    const POI_TYPES: &str = "poi_type_id;poi_type_name\namenity:parking;Parking\n";
+
+    /// Loads `archive` in lenient mode, and returns the ids of the POIs
+    /// loaded and the locations (`file:line`) of the issues
+    fn load_lenient(archive: &[u8]) -> crate::Result<(Vec<String>, Vec<String>)> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lenient.poi");
+        std::fs::write(&path, archive).unwrap();
+        let (model, issues) = load_model_lenient(&path)?;
+        let issues = issues
+            .into_iter()
+            .map(|issue| format!("{}:{}", issue.file, issue.line.unwrap_or_default()))
+            .collect();
+        Ok((model.pois.into_keys().collect(), issues))
+    }
+
+    #[test]
+    fn lenient_loading_skips_bad_floats() {
+        let pois = POIS.replace("48.83", "north");
+        let (pois, issues) =
+            load_lenient(&archive(&[("poi.txt", &pois), ("poi_type.txt", POI_TYPES)])).unwrap();
+        assert_eq!(pois, ["poi:1"]);
+        assert_eq!(issues, ["poi.txt:3"]);
+    }
+
+    #[test]
+    fn lenient_loading_skips_records_missing_columns() {
+        let pois = POIS.replace(";2.38", "");
+        let (pois, issues) =
+            load_lenient(&archive(&[("poi.txt", &pois), ("poi_type.txt", POI_TYPES)])).unwrap();
+        assert_eq!(pois, ["poi:1"]);
+        assert_eq!(issues, ["poi.txt:3"]);
+    }
+
+    #[test]
+    fn lenient_loading_skips_properties_of_unknown_pois() {
+        let properties = "poi_id;key;value\npoi:1;capacity;120\npoi:3;capacity;80\n";
+        let archive = archive(&[
+            ("poi.txt", POIS),
+            ("poi_type.txt", POI_TYPES),
+            ("poi_properties.txt", properties),
+        ]);
+        let (pois, issues) = load_lenient(&archive).unwrap();
+        assert_eq!(pois, ["poi:1", "poi:2"]);
+        assert_eq!(issues, ["poi_properties.txt:3"]);
+    }
+
+    #[test]
+    fn lenient_loading_fails_on_corrupt_members() {
+        for file in &["poi.txt", "poi_properties.txt"] {
+            let properties = "poi_id;key;value\npoi:1;capacity;120\n";
+            let mut archive = archive(&[
+                ("poi.txt", POIS),
+                ("poi_type.txt", POI_TYPES),
+                ("poi_properties.txt", properties),
+            ]);
+            // flips a byte of the id of the first POI of the member
+            let content = if *file == "poi.txt" { POIS } else { properties };
+            let start = archive
+                .windows(content.len())
+                .position(|window| window == content.as_bytes())
+                .unwrap();
+            let offset = start + content.find("poi:1").unwrap() + 4;
+            archive[offset] = b'7';
+            let error = load_lenient(&archive).unwrap_err();
+            assert!(format!("{:#}", error).contains(file), "{:#}", error);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use crate::{Model, Poi, PoiType, Result};
+use itertools::Itertools;
+use serde::Serialize;
+use std::io::Write;
+
+/// Coordinates, as strings like in the navitia API
+#[derive(Serialize)]
+struct NavitiaCoord {
+    lon: String,
+    lat: String,
+}
+
+#[derive(Serialize)]
+struct NavitiaPoiType<'a> {
+    id: &'a str,
+    name: &'a str,
+}
+
+/// A POI, shaped like the POIs of the navitia API
+#[derive(Serialize)]
+struct NavitiaPoi<'a> {
+    id: &'a str,
+    name: &'a str,
+    label: &'a str,
+    coord: NavitiaCoord,
+    poi_type: NavitiaPoiType<'a>,
+    properties: &'a std::collections::BTreeMap<String, String>,
+}
+
+impl<'a> NavitiaPoi<'a> {
+    fn new(poi: &'a Poi, poi_type: Option<&'a PoiType>) -> Self {
+        NavitiaPoi {
+            id: &poi.id,
+            name: &poi.name,
+            label: &poi.name,
+            coord: NavitiaCoord {
+                lon: poi.coord.lon().to_string(),
+                lat: poi.coord.lat().to_string(),
+            },
+            poi_type: NavitiaPoiType {
+                id: &poi.poi_type_id,
+                name: poi_type.map_or("", |poi_type| &poi_type.name),
+            },
+            properties: &poi.properties,
+        }
+    }
+}
+
+/// Writes the model as the JSON document expected by Tyr and Kraken for
+/// POI datasets: an object with a `pois` and a `poi_types` list.
+///
+/// POIs are shaped like those of the navitia API: `id`, `name`, `label`,
+/// `coord` (`lon` and `lat`, as strings), `poi_type` (`id` and `name`) and
+/// `properties` (an object of strings). POI types have an `id` and a
+/// `name`, and are ordered by id. The document is streamed, POI by POI.
+pub fn write_navitia_json<W>(model: &Model, mut writer: W) -> Result<()>
+where
+    W: Write,
+{
+    write!(writer, "{{\"pois\":[")?;
+    for (index, poi) in model.pois.values().enumerate() {
+        if index > 0 {
+            write!(writer, ",")?;
+        }
+        let poi_type = model.poi_types.get(&poi.poi_type_id);
+        serde_json::to_writer(&mut writer, &NavitiaPoi::new(poi, poi_type))?;
+    }
+    write!(writer, "],\"poi_types\":")?;
+    let poi_types: Vec<NavitiaPoiType<'_>> = model
+        .poi_types
+        .values()
+        .sorted_by(|lhs, rhs| lhs.id.cmp(&rhs.id))
+        .map(|poi_type| NavitiaPoiType {
+            id: &poi_type.id,
+            name: &poi_type.name,
+        })
+        .collect();
+    serde_json::to_writer(&mut writer, &poi_types)?;
+    writeln!(writer, "}}")?;
+    Ok(())
+}
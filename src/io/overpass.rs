@@ -0,0 +1,122 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Fetching of POIs from the Overpass API, with the `overpass` feature
+
+use crate::ids::PoiId;
+use crate::mapping::{Mapping, RuleWeight, WeightPolicy};
+use crate::{Coord, Model, Result};
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Public endpoint of the Overpass API
+pub const OVERPASS_ENDPOINT: &str = "https://overpass-api.de/api/interpreter";
+
+#[derive(Deserialize)]
+struct Response {
+    elements: Vec<Element>,
+}
+
+#[derive(Deserialize)]
+struct Center {
+    lat: f64,
+    lon: f64,
+}
+
+/// A node, way or relation. Ways and relations have a center only when
+/// the query asks for it, with `out center`.
+#[derive(Deserialize)]
+struct Element {
+    #[serde(rename = "type")]
+    kind: String,
+    id: i64,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    center: Option<Center>,
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+}
+
+impl Element {
+    fn coord(&self) -> Option<Coord> {
+        match (self.lat, self.lon, &self.center) {
+            (Some(lat), Some(lon), _) => Coord::try_new(lon, lat).ok(),
+            (_, _, Some(center)) => Coord::try_new(center.lon, center.lat).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Runs the Overpass QL `query` on `endpoint` (like [OVERPASS_ENDPOINT]),
+/// and builds the POIs of the resulting OSM objects with `mapping`.
+///
+/// The query must output JSON (`[out:json];`), with the center of ways and
+/// relations (`out center;`): the objects without coordinates, or not
+/// matched by any rule of the mapping, are skipped. POIs are identified as
+/// `poi:osm:<type>:<id>`, like `poi:osm:node:42`.
+///
+/// ```no_run
+/// use navitia_poi_model::io::{load_model_from_overpass, OVERPASS_ENDPOINT};
+/// use navitia_poi_model::mapping::Mapping;
+///
+/// # fn main() -> navitia_poi_model::Result<()> {
+/// let mapping = Mapping::from_path("mapping.toml")?;
+/// let query = r#"[out:json];area[name="Nantes"]->.a;nwr(area.a)[amenity=parking];out center;"#;
+/// let model = load_model_from_overpass(query, OVERPASS_ENDPOINT, &mapping)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_model_from_overpass(query: &str, endpoint: &str, mapping: &Mapping) -> Result<Model> {
+    load_model_from_overpass_with_weights(query, endpoint, mapping, &RuleWeight)
+}
+
+/// Same as [load_model_from_overpass], the weights of the POIs being given
+/// by `weights` (see [Mapping::to_weighted_poi])
+pub fn load_model_from_overpass_with_weights(
+    query: &str,
+    endpoint: &str,
+    mapping: &Mapping,
+    weights: &dyn WeightPolicy,
+) -> Result<Model> {
+    let response = ureq::post(endpoint)
+        .send_form(&[("data", query)])
+        .with_context(|| format!("cannot run the Overpass query on '{}'", endpoint))?;
+    let response: Response = serde_json::from_reader(response.into_reader())
+        .with_context(|| format!("invalid Overpass response from '{}'", endpoint))?;
+
+    let mut pois = BTreeMap::new();
+    let mut poi_types = HashMap::new();
+    for element in &response.elements {
+        let coord = match element.coord() {
+            Some(coord) => coord,
+            None => continue,
+        };
+        let id = PoiId::from_source("osm", &format!("{}:{}", element.kind, element.id));
+        if let Some(rule) = mapping.rule_for(&element.tags) {
+            poi_types
+                .entry(rule.poi_type.id.clone())
+                .or_insert_with(|| rule.poi_type.clone());
+        }
+        if let Some(poi) = mapping.to_weighted_poi(id.as_str(), coord, &element.tags, weights) {
+            pois.insert(poi.id.clone(), poi);
+        }
+    }
+    Ok(Model {
+        pois,
+        poi_types,
+        metadata: None,
+        relations: BTreeSet::new(),
+    })
+}
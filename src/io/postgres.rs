@@ -0,0 +1,94 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use crate::{Model, Result};
+use std::io::Write;
+
+/// Columns written by [write_postgres_copy], in order
+pub const POSTGRES_COLUMNS: [&str; 8] = [
+    "id",
+    "name",
+    "poi_type_id",
+    "poi_type_name",
+    "weight",
+    "visible",
+    "properties",
+    "geom",
+];
+
+/// Returns the SQL creating the table `table`, suited to receive the output
+/// of [write_postgres_copy], followed by the matching `COPY` statement.
+pub fn postgres_schema(table: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    poi_type_id TEXT NOT NULL,
+    poi_type_name TEXT,
+    weight DOUBLE PRECISION NOT NULL,
+    visible BOOLEAN NOT NULL,
+    properties JSONB NOT NULL,
+    geom GEOMETRY(Point, 4326) NOT NULL
+);
+CREATE INDEX IF NOT EXISTS {table}_geom_idx ON {table} USING GIST (geom);
+-- COPY {table} ({columns}) FROM STDIN;
+",
+        table = table,
+        columns = POSTGRES_COLUMNS.join(", ")
+    )
+}
+
+/// Writes the POIs of the model in the text format of PostgreSQL's `COPY`
+/// (tab separated, `\N` for NULL), with the columns of [POSTGRES_COLUMNS].
+/// Properties are written as a JSON object, and coordinates as an EWKT
+/// point in WGS84 (`SRID=4326;POINT(lon lat)`).
+pub fn write_postgres_copy<W>(model: &Model, mut writer: W) -> Result<()>
+where
+    W: Write,
+{
+    for poi in model.pois.values() {
+        let poi_type_name = model
+            .poi_types
+            .get(&poi.poi_type_id)
+            .map_or_else(|| "\\N".to_string(), |poi_type| escape(&poi_type.name));
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\tSRID=4326;{}",
+            escape(&poi.id),
+            escape(&poi.name),
+            escape(&poi.poi_type_id),
+            poi_type_name,
+            poi.weight,
+            if poi.visible { 't' } else { 'f' },
+            escape(&serde_json::to_string(&poi.properties)?),
+            poi.coord,
+        )?;
+    }
+    Ok(())
+}
+
+/// Escapes a value for the text format of `COPY`
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,204 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use super::metrics::{SharedMetrics, BYTES_READ, BYTES_WRITTEN, RECORDS_READ, RECORDS_WRITTEN};
+use std::cell::Cell;
+use std::io::{Read, Write};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Number of records between two reports of the progress of a file
+const REPORT_INTERVAL: u64 = 10_000;
+
+/// Progress of the reading or writing of a file of an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// Name of the file, in the archive
+    pub file: String,
+
+    /// Number of records processed so far
+    pub records: u64,
+
+    /// Number of (uncompressed) bytes processed so far
+    pub bytes: u64,
+
+    /// Uncompressed size of the file, known when reading only
+    pub total_bytes: Option<u64>,
+
+    /// True for the last report of the file, once fully processed
+    pub done: bool,
+}
+
+/// A function called with the [Progress] of a load or save, every few
+/// thousand records and once each file is done
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl ProgressCallback {
+    /// Creates a callback from a function
+    pub fn new<F>(f: F) -> ProgressCallback
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        ProgressCallback(Arc::new(f))
+    }
+}
+
+impl std::fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Counts the bytes going through a reader or a writer
+pub(crate) struct Counting<T> {
+    inner: T,
+    count: Rc<Cell<u64>>,
+}
+
+impl<T> Counting<T> {
+    pub(crate) fn new(inner: T) -> Self {
+        Counting {
+            inner,
+            count: Rc::new(Cell::new(0)),
+        }
+    }
+
+    /// The counter, still updated once the reader or writer is moved
+    pub(crate) fn counter(&self) -> Rc<Cell<u64>> {
+        self.count.clone()
+    }
+}
+
+impl<T: Read> Read for Counting<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
+
+impl<T: Write> Write for Counting<T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Keeps track of the progress of a file, and reports it to the callback,
+/// if any
+pub(crate) struct Tracker {
+    callback: Option<ProgressCallback>,
+    metrics: Option<SharedMetrics>,
+    progress: Progress,
+    bytes: Rc<Cell<u64>>,
+}
+
+impl Tracker {
+    pub(crate) fn new(
+        callback: Option<&ProgressCallback>,
+        metrics: Option<&SharedMetrics>,
+        file: &str,
+        total_bytes: Option<u64>,
+        bytes: Rc<Cell<u64>>,
+    ) -> Self {
+        Tracker {
+            callback: callback.cloned(),
+            metrics: metrics.cloned(),
+            progress: Progress {
+                file: file.to_string(),
+                records: 0,
+                bytes: 0,
+                total_bytes,
+                done: false,
+            },
+            bytes,
+        }
+    }
+
+    /// Number of records processed so far
+    pub(crate) fn records(&self) -> u64 {
+        self.progress.records
+    }
+
+    /// Counts a processed record
+    pub(crate) fn record(&mut self) {
+        self.progress.records += 1;
+        if self.progress.records.is_multiple_of(REPORT_INTERVAL) {
+            self.report();
+        }
+    }
+
+    /// Reports the file as done, only the first time
+    pub(crate) fn finish(&mut self) {
+        if !self.progress.done {
+            self.progress.done = true;
+            self.report();
+            if let Some(metrics) = &self.metrics {
+                // only the files being read have a known size
+                let (records, bytes) = match self.progress.total_bytes {
+                    Some(_) => (RECORDS_READ, BYTES_READ),
+                    None => (RECORDS_WRITTEN, BYTES_WRITTEN),
+                };
+                let labels = [("file", self.progress.file.as_str())];
+                metrics.counter(records, &labels, self.progress.records);
+                metrics.counter(bytes, &labels, self.bytes.get());
+            }
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                file = %self.progress.file,
+                records = self.progress.records,
+                bytes = self.bytes.get(),
+                "file done"
+            );
+        }
+    }
+
+    fn report(&mut self) {
+        if let Some(callback) = &self.callback {
+            self.progress.bytes = self.bytes.get();
+            (callback.0)(self.progress.clone());
+        }
+    }
+}
+
+/// Iterates over the records of a file, keeping track of the progress
+pub(crate) struct Tracked<I> {
+    records: I,
+    tracker: Tracker,
+}
+
+impl<I> Tracked<I> {
+    pub(crate) fn new(records: I, tracker: Tracker) -> Self {
+        Tracked { records, tracker }
+    }
+}
+
+impl<I: Iterator> Iterator for Tracked<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let record = self.records.next();
+        match record {
+            Some(_) => self.tracker.record(),
+            None => self.tracker.finish(),
+        }
+        record
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Loading and saving of archives on object storage (S3, HTTP), with the
+//! `object_store` feature
+
+use super::{load_model_from_content, reject_violations, write_archive, LoadOptions, WriteOptions};
+use crate::{Model, Result};
+use futures::StreamExt;
+use object_store::{ObjectStore, PutPayload};
+use std::io::Cursor;
+use std::path::Path;
+
+/// Loads the archive at `url`, like `s3://bucket/fr.poi` or
+/// `https://example.com/fr.poi`. The archive is downloaded in memory, as a
+/// stream, then loaded.
+///
+/// S3 credentials and settings are read from the `AWS_*` environment
+/// variables. The call blocks, and must not be made from an asynchronous
+/// runtime.
+pub fn load_model_from_url(url: &str, options: &LoadOptions) -> Result<Model> {
+    let (store, path) = parse_url(url)?;
+    let content = block_on(async {
+        let download = store.get(&path).await?;
+        let mut content = Vec::with_capacity(download.meta.size as usize);
+        let mut chunks = download.into_stream();
+        while let Some(chunk) = chunks.next().await {
+            content.extend_from_slice(&chunk?);
+        }
+        Ok(content)
+    })?;
+    load_model_from_content(Path::new(url), &content, options, None)
+}
+
+/// Saves the model to `url`, see [load_model_from_url]. The archive is
+/// written in memory, then uploaded.
+pub fn write_model_to_url(model: &Model, url: &str, options: &WriteOptions) -> Result<()> {
+    reject_violations(model, options)?;
+    let (store, path) = parse_url(url)?;
+    let content = write_archive(model, Cursor::new(Vec::new()), options)?.into_inner();
+    block_on(async {
+        store.put(&path, PutPayload::from(content)).await?;
+        Ok(())
+    })
+}
+
+fn parse_url(url: &str) -> Result<(Box<dyn ObjectStore>, object_store::path::Path)> {
+    let url = url::Url::parse(url)?;
+    let mut settings: Vec<(String, String)> = std::env::vars()
+        .map(|(key, value)| (key.to_ascii_lowercase(), value))
+        .filter(|(key, _)| key.starts_with("aws_"))
+        .collect();
+    // plain HTTP is refused by default, but asked for here
+    if url.scheme() == "http" {
+        settings.push(("allow_http".to_string(), "true".to_string()));
+    }
+    Ok(object_store::parse_url_opts(&url, settings)?)
+}
+
+fn block_on<F, T>(future: F) -> Result<T>
+where
+    F: std::future::Future<Output = Result<T>>,
+{
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(future)
+}
@@ -16,8 +16,45 @@
 
 #![deny(missing_docs, missing_debug_implementations)]
 
-mod io;
+pub mod accessibility;
+pub mod admin;
+#[cfg(feature = "proptest")]
+mod arbitrary;
+pub mod cancel;
+pub mod changes;
+#[cfg(feature = "compact")]
+pub mod compact;
+pub mod conformance;
+pub mod constraints;
+#[cfg(feature = "disk")]
+pub mod disk;
+pub mod duplicates;
+pub mod enrich;
+pub mod extensions;
+pub mod filter;
+#[cfg(feature = "geojson")]
+mod geojson;
+pub mod ids;
+pub mod io;
+pub mod mapping;
+#[cfg(feature = "mvt")]
+pub mod mvt;
+pub mod normalize;
 pub mod objects;
+pub mod observer;
+#[cfg(feature = "persistent")]
+pub mod persistent;
+pub mod poi_types;
+pub mod privacy;
+pub mod projection;
+#[cfg(feature = "rstar")]
+mod rtree;
+pub mod search;
+pub mod shared;
+pub mod tiles;
+mod trace;
+pub mod transaction;
+pub mod warnings;
 
 pub use objects::*;
 
@@ -0,0 +1,397 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Mapping of OSM objects to POIs
+//!
+//! A [Mapping] is a list of [Rule]s, usually loaded from a JSON or TOML
+//! file. The first rule whose tag filters all match the tags of an OSM
+//! object gives the POI type, name, weight and properties of the POI.
+//!
+//! ```toml
+//! [[rules]]
+//! tags = { amenity = "parking" }
+//! poi_type = { id = "amenity:parking", name = "Parking" }
+//! name = "{name}"
+//! weight = 1
+//! properties = ["capacity", "fee"]
+//! ```
+//!
+//! The weight of the rule can instead be derived from the importance of
+//! the OSM object with a [WeightPolicy], like [ImportanceWeight].
+
+use crate::accessibility::Accessibility;
+use crate::{Coord, Poi, PoiType, Result, Weight};
+use anyhow::Context;
+use geo::prelude::HaversineDistance;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Access to the tags of an OSM object
+pub trait Tags {
+    /// Returns the value of the tag `key`, if any
+    fn tag(&self, key: &str) -> Option<&str>;
+
+    /// Iterates over all the tags
+    fn tags(&self) -> Box<dyn Iterator<Item = (&str, &str)> + '_>;
+}
+
+impl Tags for BTreeMap<String, String> {
+    fn tag(&self, key: &str) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+
+    fn tags(&self) -> Box<dyn Iterator<Item = (&str, &str)> + '_> {
+        Box::new(self.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+    }
+}
+
+impl Tags for HashMap<String, String> {
+    fn tag(&self, key: &str) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+
+    fn tags(&self) -> Box<dyn Iterator<Item = (&str, &str)> + '_> {
+        Box::new(self.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+    }
+}
+
+fn default_name() -> String {
+    "{name}".to_string()
+}
+
+/// A rule turning the OSM objects it matches into POIs
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Rule {
+    /// Tags the object must have. The value `*` accepts any value, and
+    /// alternatives can be separated by `|` (e.g. `parking|bicycle_parking`)
+    pub tags: BTreeMap<String, String>,
+
+    /// Type of the POIs built by this rule
+    pub poi_type: PoiType,
+
+    /// Template of the name of the POIs, where `{key}` is replaced by the
+    /// value of the tag `key` (empty if absent). `{name}` by default.
+    #[serde(default = "default_name")]
+    pub name: String,
+
+    /// Weight of the POIs, 0 by default
+    #[serde(default)]
+    pub weight: Weight,
+
+    /// Tags kept as properties of the POI. `*` keeps all of them.
+    #[serde(default)]
+    pub properties: Vec<String>,
+}
+
+impl Rule {
+    /// Returns true if all the tag filters of the rule match `tags`
+    pub fn matches<T: Tags + ?Sized>(&self, tags: &T) -> bool {
+        self.tags.iter().all(|(key, expected)| match tags.tag(key) {
+            Some(value) => expected == "*" || expected.split('|').any(|e| e == value),
+            None => false,
+        })
+    }
+
+    /// Renders the name template with the given tags
+    pub fn render_name<T: Tags + ?Sized>(&self, tags: &T) -> String {
+        let mut name = String::new();
+        let mut rest = self.name.as_str();
+        while let Some(start) = rest.find('{') {
+            match rest[start..].find('}') {
+                Some(len) => {
+                    name.push_str(&rest[..start]);
+                    name.push_str(tags.tag(&rest[start + 1..start + len]).unwrap_or(""));
+                    rest = &rest[start + len + 1..];
+                }
+                None => break,
+            }
+        }
+        name.push_str(rest);
+        name.trim().to_string()
+    }
+
+    /// Returns the properties kept from the given tags
+    pub fn properties<T: Tags + ?Sized>(&self, tags: &T) -> BTreeMap<String, String> {
+        if self.properties.iter().any(|p| p == "*") {
+            tags.tags()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        } else {
+            self.properties
+                .iter()
+                .filter_map(|key| tags.tag(key).map(|value| (key.clone(), value.to_string())))
+                .collect()
+        }
+    }
+}
+
+/// An ordered list of rules
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Mapping {
+    /// The rules, the first matching one wins
+    pub rules: Vec<Rule>,
+}
+
+impl Mapping {
+    /// Parses a mapping from JSON
+    pub fn from_json_str(json: &str) -> Result<Mapping> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Parses a mapping from TOML
+    pub fn from_toml_str(toml: &str) -> Result<Mapping> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    /// Loads a mapping from a file, in TOML if its extension is `.toml`,
+    /// and in JSON otherwise
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Mapping> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        if path.extension() == Some("toml".as_ref()) {
+            Mapping::from_toml_str(&content)
+        } else {
+            Mapping::from_json_str(&content)
+        }
+        .with_context(|| format!("invalid mapping in '{}'", path.display()))
+    }
+
+    /// Returns the first rule matching the tags, if any
+    pub fn rule_for<T: Tags + ?Sized>(&self, tags: &T) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.matches(tags))
+    }
+
+    /// Returns the POI types of all the rules, without duplicates
+    pub fn poi_types(&self) -> Vec<PoiType> {
+        let mut poi_types: Vec<PoiType> = self.rules.iter().map(|r| r.poi_type.clone()).collect();
+        poi_types.sort();
+        poi_types.dedup_by(|l, r| l.id == r.id);
+        poi_types
+    }
+
+    /// Builds the POI of an OSM object, if a rule matches its tags
+    pub fn to_poi<T: Tags + ?Sized>(&self, id: &str, coord: Coord, tags: &T) -> Option<Poi> {
+        self.to_weighted_poi(id, coord, tags, &RuleWeight)
+    }
+
+    /// Same as [Mapping::to_poi], the weight of the POI being given by
+    /// `weights`
+    pub fn to_weighted_poi<T: Tags + ?Sized>(
+        &self,
+        id: &str,
+        coord: Coord,
+        tags: &T,
+        weights: &dyn WeightPolicy,
+    ) -> Option<Poi> {
+        let rule = self.rule_for(tags)?;
+        let weight = weights.weight(rule, &coord, &TagsRef(tags));
+        let properties = rule.properties(tags);
+        Some(Poi {
+            id: id.to_string(),
+            name: rule.render_name(tags),
+            coord,
+            poi_type_id: rule.poi_type.id.clone(),
+            accessibility: Accessibility::from_properties(&properties),
+            elevation_m: None,
+            properties,
+            visible: true,
+            weight,
+            created_at: None,
+            updated_at: None,
+        })
+    }
+}
+
+/// Gives the weight of the POI built by a rule from an OSM object
+pub trait WeightPolicy {
+    /// Returns the weight of the POI built by `rule` from the OSM object at
+    /// `coord` with `tags`
+    fn weight(&self, rule: &Rule, coord: &Coord, tags: &dyn Tags) -> Weight;
+}
+
+/// The weight of the rule, whatever the object
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RuleWeight;
+
+impl WeightPolicy for RuleWeight {
+    fn weight(&self, rule: &Rule, _coord: &Coord, _tags: &dyn Tags) -> Weight {
+        rule.weight
+    }
+}
+
+/// Gives the population of the place enclosing coordinates, like a city
+pub trait PopulationProvider {
+    /// Returns the population of the place enclosing `coord`
+    fn population(&self, coord: &Coord) -> Option<u64>;
+}
+
+impl<F> PopulationProvider for F
+where
+    F: Fn(&Coord) -> Option<u64>,
+{
+    fn population(&self, coord: &Coord) -> Option<u64> {
+        self(coord)
+    }
+}
+
+/// Radius, in meters, given to the OSM places of each type
+const PLACE_RADII: [(&str, f64); 7] = [
+    ("city", 10_000.),
+    ("town", 5_000.),
+    ("village", 2_000.),
+    ("suburb", 2_000.),
+    ("quarter", 1_000.),
+    ("hamlet", 500.),
+    ("neighbourhood", 500.),
+];
+
+/// A [PopulationProvider] made of places with a population, like the OSM
+/// objects tagged `place` and `population`, seen as circles. The place
+/// enclosing coordinates is the smallest circle containing them.
+#[derive(Debug, Clone, Default)]
+pub struct PlacePopulations {
+    /// Center, radius in meters and population of the places
+    places: Vec<(geo::Point<f64>, f64, u64)>,
+}
+
+impl PlacePopulations {
+    /// Adds a place of `population`, of `radius_m` meters around `coord`
+    pub fn insert(&mut self, coord: &Coord, radius_m: f64, population: u64) {
+        self.places
+            .push((geo::Point::from(coord), radius_m, population));
+    }
+
+    /// Adds the OSM object at `coord` if it is a place with a population,
+    /// its radius depending on the type of place (10 km for a city, 5 km
+    /// for a town, etc). Returns true if it has been added.
+    pub fn insert_osm<T: Tags + ?Sized>(&mut self, coord: &Coord, tags: &T) -> bool {
+        let radius_m = tags.tag("place").and_then(|place| {
+            PLACE_RADII
+                .iter()
+                .find(|(kind, _)| *kind == place)
+                .map(|(_, radius_m)| *radius_m)
+        });
+        // populations are sometimes written with separators, like 12 000
+        let population = tags.tag("population").and_then(|population| {
+            population
+                .chars()
+                .filter(|c| !c.is_whitespace() && *c != ',' && *c != '.')
+                .collect::<String>()
+                .parse()
+                .ok()
+        });
+        match (radius_m, population) {
+            (Some(radius_m), Some(population)) => {
+                self.insert(coord, radius_m, population);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl PopulationProvider for PlacePopulations {
+    fn population(&self, coord: &Coord) -> Option<u64> {
+        let point = geo::Point::from(coord);
+        self.places
+            .iter()
+            .filter(|(center, radius_m, _)| point.haversine_distance(center) <= *radius_m)
+            .min_by(|(_, lhs, _), (_, rhs, _)| lhs.total_cmp(rhs))
+            .map(|(_, _, population)| *population)
+    }
+}
+
+/// A [PopulationProvider] knowing no population
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoPopulation;
+
+impl PopulationProvider for NoPopulation {
+    fn population(&self, _coord: &Coord) -> Option<u64> {
+        None
+    }
+}
+
+/// Weight derived from signals of the importance of the OSM object, added
+/// to the weight of the rule:
+/// - `wikidata` if the object has a `wikidata` or `wikipedia` tag,
+/// - `population` times the decimal logarithm of the population of the
+///   enclosing place, given by `populations`,
+/// - `tag` for each of its tags, up to `max_tags`, richly described
+///   objects being usually the most important ones.
+#[derive(Debug, Clone)]
+pub struct ImportanceWeight<P = NoPopulation> {
+    /// Weight of a link to Wikidata or Wikipedia, 1 by default
+    pub wikidata: f64,
+
+    /// Weight of each decade of the population of the enclosing place, 0.2
+    /// by default
+    pub population: f64,
+
+    /// Weight of each tag, 0.02 by default
+    pub tag: f64,
+
+    /// Maximal number of tags counted, 25 by default
+    pub max_tags: usize,
+
+    /// Populations of the places
+    pub populations: P,
+}
+
+impl<P> ImportanceWeight<P> {
+    /// Weights with the default signals, and the populations of the places
+    /// given by `populations`
+    pub fn new(populations: P) -> ImportanceWeight<P> {
+        ImportanceWeight {
+            wikidata: 1.,
+            population: 0.2,
+            tag: 0.02,
+            max_tags: 25,
+            populations,
+        }
+    }
+}
+
+impl Default for ImportanceWeight {
+    fn default() -> ImportanceWeight {
+        ImportanceWeight::new(NoPopulation)
+    }
+}
+
+impl<P: PopulationProvider> WeightPolicy for ImportanceWeight<P> {
+    fn weight(&self, rule: &Rule, coord: &Coord, tags: &dyn Tags) -> Weight {
+        let mut weight = rule.weight.value();
+        if tags.tag("wikidata").is_some() || tags.tag("wikipedia").is_some() {
+            weight += self.wikidata;
+        }
+        if let Some(population) = self.populations.population(coord) {
+            weight += self.population * (1. + population as f64).log10();
+        }
+        weight += self.tag * tags.tags().take(self.max_tags).count() as f64;
+        Weight(weight)
+    }
+}
+
+/// Tags of an unsized type, seen as a trait object
+struct TagsRef<'a, T: ?Sized>(&'a T);
+
+impl<T: Tags + ?Sized> Tags for TagsRef<'_, T> {
+    fn tag(&self, key: &str) -> Option<&str> {
+        self.0.tag(key)
+    }
+
+    fn tags(&self) -> Box<dyn Iterator<Item = (&str, &str)> + '_> {
+        self.0.tags()
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Export of POIs as [Mapbox Vector Tiles]
+//!
+//! Each POI is encoded as a point feature with the attributes `id`, `name`,
+//! `poi_type`, `weight` and `visible`.
+//!
+//! [Mapbox Vector Tiles]: https://github.com/mapbox/vector-tile-spec/tree/master/2.1
+
+use crate::tiles::project;
+use crate::{Model, Poi};
+use std::collections::HashMap;
+
+const KEYS: [&str; 5] = ["id", "name", "poi_type", "weight", "visible"];
+
+/// Options used to encode a vector tile
+#[derive(Debug, Clone)]
+pub struct MvtOptions {
+    /// Name of the layer holding the POIs, `pois` by default
+    pub layer_name: String,
+
+    /// Extent of the tile, 4096 by default
+    pub extent: u32,
+
+    /// Minimum weight of the POIs kept in a tile, as `(zoom, min_weight)`
+    /// pairs: on a tile of zoom `z`, the threshold is the one of the highest
+    /// `zoom` lower or equal to `z`. Every POI is kept by default.
+    pub min_weights: Vec<(u8, f64)>,
+}
+
+impl Default for MvtOptions {
+    fn default() -> MvtOptions {
+        MvtOptions {
+            layer_name: "pois".to_string(),
+            extent: 4096,
+            min_weights: Vec::new(),
+        }
+    }
+}
+
+impl MvtOptions {
+    /// Minimum weight of the POIs kept at zoom `z`
+    pub fn min_weight(&self, z: u8) -> f64 {
+        self.min_weights
+            .iter()
+            .filter(|(zoom, _)| *zoom <= z)
+            .max_by_key(|(zoom, _)| *zoom)
+            .map(|(_, min_weight)| *min_weight)
+            .unwrap_or(0.)
+    }
+}
+
+impl Model {
+    /// Encodes the POIs of the tile `z/x/y` into a vector tile, with the
+    /// default options.
+    pub fn to_mvt_tile(&self, z: u8, x: u32, y: u32) -> Vec<u8> {
+        self.to_mvt_tile_with_options(z, x, y, &MvtOptions::default())
+    }
+
+    /// Encodes the POIs of the tile `z/x/y` into a vector tile.
+    ///
+    /// POIs with invalid coordinates, or with a weight lower than the
+    /// threshold of the zoom level, are left out.
+    pub fn to_mvt_tile_with_options(&self, z: u8, x: u32, y: u32, options: &MvtOptions) -> Vec<u8> {
+        let pois = self
+            .pois
+            .values()
+            .filter(|poi| poi.coord.is_valid() && poi.weight.value() >= options.min_weight(z));
+        encode_tile(pois, z, x, y, options)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Value {
+    String(String),
+    Uint(u64),
+    /// Bits of a double, so that values can be hashed
+    Double(u64),
+    Bool(bool),
+}
+
+/// Encodes the given POIs lying in the tile `z/x/y`
+pub(crate) fn encode_tile<'a, I>(pois: I, z: u8, x: u32, y: u32, options: &MvtOptions) -> Vec<u8>
+where
+    I: IntoIterator<Item = &'a Poi>,
+{
+    let extent = f64::from(options.extent);
+    let mut values = Vec::new();
+    let mut value_indexes = HashMap::new();
+    let mut features = Vec::new();
+    for poi in pois {
+        let (px, py) = project(&poi.coord, z);
+        let px = ((px - f64::from(x)) * extent).floor();
+        let py = ((py - f64::from(y)) * extent).floor();
+        if px < 0. || py < 0. || px >= extent || py >= extent {
+            continue;
+        }
+        let mut tags = Vec::with_capacity(2 * KEYS.len());
+        let poi_values = vec![
+            Value::String(poi.id.clone()),
+            Value::String(poi.name.clone()),
+            Value::String(poi.poi_type_id.clone()),
+            weight_value(poi.weight.value()),
+            Value::Bool(poi.visible),
+        ];
+        for (key, value) in poi_values.into_iter().enumerate() {
+            let index = *value_indexes.entry(value.clone()).or_insert_with(|| {
+                values.push(value);
+                values.len() - 1
+            });
+            tags.push(key as u32);
+            tags.push(index as u32);
+        }
+
+        let mut feature = Vec::new();
+        write_packed(&mut feature, 2, &tags);
+        write_uint(&mut feature, 3, 1); // POINT
+        write_packed(
+            &mut feature,
+            4,
+            &[9, zigzag(px as i64) as u32, zigzag(py as i64) as u32], // MoveTo(1)
+        );
+        features.push(feature);
+    }
+
+    let mut layer = Vec::new();
+    write_uint(&mut layer, 15, 2);
+    write_bytes(&mut layer, 1, options.layer_name.as_bytes());
+    for feature in &features {
+        write_bytes(&mut layer, 2, feature);
+    }
+    for key in KEYS.iter() {
+        write_bytes(&mut layer, 3, key.as_bytes());
+    }
+    for value in &values {
+        let mut encoded = Vec::new();
+        match value {
+            Value::String(s) => write_bytes(&mut encoded, 1, s.as_bytes()),
+            Value::Uint(u) => write_uint(&mut encoded, 5, *u),
+            Value::Double(bits) => write_fixed64(&mut encoded, 3, *bits),
+            Value::Bool(b) => write_uint(&mut encoded, 7, *b as u64),
+        }
+        write_bytes(&mut layer, 4, &encoded);
+    }
+    write_uint(&mut layer, 5, u64::from(options.extent));
+
+    let mut tile = Vec::new();
+    write_bytes(&mut tile, 3, &layer);
+    tile
+}
+
+/// Integral weights are encoded as integers, the others as doubles
+fn weight_value(weight: f64) -> Value {
+    if weight.fract() == 0. && 0. <= weight && weight <= u64::MAX as f64 {
+        Value::Uint(weight as u64)
+    } else {
+        Value::Double(weight.to_bits())
+    }
+}
+
+fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+fn write_uint(buf: &mut Vec<u8>, field: u32, v: u64) {
+    write_varint(buf, u64::from(field << 3));
+    write_varint(buf, v);
+}
+
+fn write_fixed64(buf: &mut Vec<u8>, field: u32, v: u64) {
+    write_varint(buf, u64::from(field << 3 | 1));
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn write_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    write_varint(buf, u64::from(field << 3 | 2));
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn write_packed(buf: &mut Vec<u8>, field: u32, values: &[u32]) {
+    let mut packed = Vec::new();
+    for v in values {
+        write_varint(&mut packed, u64::from(*v));
+    }
+    write_bytes(buf, field, &packed);
+}
@@ -0,0 +1,271 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Normalization of names, shared by the search and the detection of
+//! duplicates so that they agree on which names are the same, phonetic
+//! keys of names (see [phonetic_key]), and sanitization of texts for
+//! display (see [sanitize])
+//!
+//! Names are lowercased, their accents removed, and they are split into
+//! alphanumeric words, whose common abbreviations are expanded:
+//!
+//! ```
+//! use navitia_poi_model::normalize::normalize_name;
+//!
+//! assert_eq!(normalize_name("St.-Étienne  Châteaucreux"), "saint etienne chateaucreux");
+//! assert_eq!(normalize_name("Av. Foch"), "avenue foch");
+//! ```
+
+use crate::Poi;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Common abbreviations of the words of names, folded, and their expansion
+pub const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("st", "saint"),
+    ("ste", "sainte"),
+    ("sts", "saints"),
+    ("av", "avenue"),
+    ("ave", "avenue"),
+    ("bd", "boulevard"),
+    ("bld", "boulevard"),
+    ("blvd", "boulevard"),
+    ("pl", "place"),
+    ("sq", "square"),
+    ("rte", "route"),
+    ("imp", "impasse"),
+    ("fbg", "faubourg"),
+    ("mt", "mont"),
+    ("pte", "porte"),
+];
+
+/// Named HTML entities decoded by [sanitize]
+const HTML_ENTITIES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", ' '),
+];
+
+/// Maximal number of names whose normalization is cached by each thread
+const CACHE_CAPACITY: usize = 1 << 16;
+
+thread_local! {
+    static NORMALIZED_NAMES: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+/// Lowercases a text and removes its accents
+pub fn fold(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Returns the expansion of a folded word if it is a known abbreviation,
+/// else the word itself
+pub fn expand_abbreviation(word: &str) -> &str {
+    ABBREVIATIONS
+        .iter()
+        .find(|(abbreviation, _)| *abbreviation == word)
+        .map_or(word, |(_, expansion)| expansion)
+}
+
+/// Splits a text into its folded alphanumeric words, the abbreviations
+/// being expanded
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> {
+    fold(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| expand_abbreviation(word).to_string())
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+/// Returns the words of `name` given by [tokenize], separated by a space
+pub fn normalize_name(name: &str) -> String {
+    tokenize(name).collect::<Vec<_>>().join(" ")
+}
+
+/// Returns a key of the pronunciation of `text`, in French, so that names
+/// spelled differently but pronounced the same, like `Châtelet` and
+/// `Chatellet`, have the same key. Each word of the text (see [tokenize])
+/// gets its key, the keys being separated by a space.
+///
+/// ```
+/// use navitia_poi_model::normalize::phonetic_key;
+///
+/// assert_eq!(phonetic_key("Châtelet"), phonetic_key("chatellet"));
+/// assert_eq!(phonetic_key("St-Lazare"), phonetic_key("Saint Lasare"));
+/// assert_eq!(phonetic_key("Place Foch"), phonetic_key("plasse foche"));
+/// assert_ne!(phonetic_key("Gare"), phonetic_key("Car"));
+/// ```
+pub fn phonetic_key(text: &str) -> String {
+    tokenize(text)
+        .map(|word| phonetic_word(&word))
+        .filter(|key| !key.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Groups of letters pronounced as a single sound, and their replacement,
+/// applied in order
+const PHONETIC_GROUPS: &[(&str, &str)] = &[
+    ("eaux", "o"),
+    ("eau", "o"),
+    ("aux", "o"),
+    ("au", "o"),
+    ("sch", "s"),
+    ("ch", "s"),
+    ("sh", "s"),
+    ("ph", "f"),
+    ("th", "t"),
+    ("qu", "k"),
+    ("ck", "k"),
+    ("gn", "n"),
+    ("ge", "je"),
+    ("gi", "ji"),
+    ("gy", "ji"),
+    ("ce", "se"),
+    ("ci", "si"),
+    ("cy", "si"),
+    ("ain", "in"),
+    ("ein", "in"),
+    ("ai", "e"),
+    ("ei", "e"),
+    ("em", "an"),
+    ("en", "an"),
+    ("am", "an"),
+    ("ou", "u"),
+    ("oi", "oa"),
+];
+
+/// Phonetic key of a folded word
+fn phonetic_word(word: &str) -> String {
+    // the final consonants, then the final e, are usually silent
+    let mut word = word.to_string();
+    while word.len() > 2 && word.ends_with(['s', 't', 'd', 'x']) {
+        word.pop();
+    }
+    for (group, sound) in PHONETIC_GROUPS {
+        word = word.replace(group, sound);
+    }
+    if word.len() > 2 && word.ends_with('e') {
+        word.pop();
+    }
+    let mut sounds: Vec<char> = word
+        .chars()
+        .flat_map(|c| match c {
+            'c' | 'q' => vec!['k'],
+            'w' => vec!['v'],
+            'y' => vec!['i'],
+            'z' => vec!['s'],
+            'x' => vec!['k', 's'],
+            'h' => vec![],
+            c => vec![c],
+        })
+        .collect();
+    // doubled letters sound like single ones
+    sounds.dedup();
+    sounds.into_iter().collect()
+}
+
+/// Cleans a text up for display: decodes the HTML entities (like `&amp;`
+/// or `&#233;`), replaces the control characters by spaces, collapses the
+/// runs of whitespaces into a single space, and trims the text. Returns the
+/// text itself when it is already clean.
+///
+/// ```
+/// use navitia_poi_model::normalize::sanitize;
+///
+/// assert_eq!(sanitize("  Caf&#233; &amp;\tBar&#x21;  "), "Café & Bar!");
+/// assert_eq!(sanitize("Fish &chips"), "Fish &chips");
+/// assert_eq!(sanitize("Café"), "Café");
+/// ```
+pub fn sanitize(text: &str) -> Cow<'_, str> {
+    let mut sanitized = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        sanitized.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match decode_entity(rest) {
+            Some((c, len)) => {
+                sanitized.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                sanitized.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    sanitized.push_str(rest);
+    let sanitized = sanitized
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if sanitized == text {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(sanitized)
+    }
+}
+
+/// Decodes the HTML entity at the start of `text`, returning the character
+/// and the length of the entity
+fn decode_entity(text: &str) -> Option<(char, usize)> {
+    // the longest entities are like `&#x10FFFF;`
+    let (end, _) = text.char_indices().take(12).find(|(_, c)| *c == ';')?;
+    let entity = &text[1..end];
+    let c = match entity.strip_prefix('#') {
+        Some(code) => {
+            let code = match code.strip_prefix('x').or_else(|| code.strip_prefix('X')) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+        None => HTML_ENTITIES
+            .iter()
+            .find(|(name, _)| *name == entity)
+            .map(|(_, c)| *c)?,
+    };
+    Some((c, end + 1))
+}
+
+impl Poi {
+    /// Returns the normalized name of the POI, see [normalize_name].
+    ///
+    /// Normalizations are cached by name, up to a few tens of thousands of
+    /// names per thread, so that comparing names again and again is cheap.
+    pub fn normalized_name(&self) -> String {
+        NORMALIZED_NAMES.with(|cache| {
+            if let Some(normalized) = cache.borrow().get(&self.name) {
+                return normalized.clone();
+            }
+            let normalized = normalize_name(&self.name);
+            let mut cache = cache.borrow_mut();
+            if cache.len() >= CACHE_CAPACITY {
+                cache.clear();
+            }
+            cache.insert(self.name.clone(), normalized.clone());
+            normalized
+        })
+    }
+}
@@ -20,21 +20,61 @@
 //! POI providers supply data that are transformed into `.poi` files.
 //!
 
+use crate::accessibility::Accessibility;
+use crate::cancel::{self, CancellationToken};
+use crate::trace::Traced;
+use crate::warnings::{self, Warning, WarningCallback};
 use crate::{io, Result};
+use chrono::{DateTime, Utc};
+use geo::prelude::{BoundingRect, Centroid, Contains, ConvexHull};
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::collections::{
-    btree_map::Entry as BTreeMapEntry, hash_map::Entry as HashMapEntry, BTreeMap, HashMap,
+    btree_map::Entry as BTreeMapEntry, hash_map::Entry as HashMapEntry, BTreeMap, BTreeSet,
+    HashMap, HashSet,
 };
+use std::convert::TryFrom;
 use std::path::Path;
 
-/// A thin wrapper around [geo::Coordinate]
+/// A thin wrapper around [geo_types::Coord]
 #[derive(Debug, Clone, PartialEq)]
-pub struct Coord(pub geo::Coordinate<f64>);
+pub struct Coord(pub geo_types::Coord<f64>);
 impl Coord {
     /// Create a new Coord from longitude and latitude.
     /// Values should be expressed in degrees
+    ///
+    /// No check is made: NaN, infinite and out-of-range values give a
+    /// coordinate that is not [valid](Coord::is_valid). Use
+    /// [Coord::try_new] to fail on them instead.
     pub fn new(lon: f64, lat: f64) -> Coord {
-        Coord(geo::Coordinate { x: lon, y: lat })
+        Coord(geo_types::Coord { x: lon, y: lat })
+    }
+
+    /// Create a new Coord from longitude and latitude in degrees, failing
+    /// when one of them is not a finite number in its range.
+    ///
+    /// Unlike [Coord::is_valid], the default `(0, 0)` coordinate is accepted.
+    ///
+    /// ```
+    /// use navitia_poi_model::Coord;
+    ///
+    /// assert!(Coord::try_new(2.37, 48.84).is_ok());
+    /// assert!(Coord::try_new(48.84, 182.).is_err());
+    /// assert!(Coord::try_new(f64::NAN, 48.84).is_err());
+    /// ```
+    pub fn try_new(lon: f64, lat: f64) -> Result<Coord> {
+        Ok(Coord::from_lat_lon(Lat::new(lat)?, Lon::new(lon)?))
+    }
+
+    /// Create a new Coord from a validated latitude and longitude
+    pub fn from_lat_lon(lat: Lat, lon: Lon) -> Coord {
+        Coord::new(lon.value(), lat.value())
+    }
+
+    /// Return the validated latitude and longitude of the coordinate, failing
+    /// when one of them is out of range
+    pub fn lat_lon(&self) -> Result<(Lat, Lon)> {
+        Ok((Lat::new(self.lat())?, Lon::new(self.lon())?))
     }
 
     /// Return the longitude
@@ -53,22 +93,37 @@ impl Coord {
         self.lat() == 0. && self.lon() == 0.
     }
 
-    /// Returns true if latitude and longitude are in
+    /// Returns true if neither the latitude nor the longitude is NaN or
+    /// infinite
+    pub fn is_finite(&self) -> bool {
+        self.lat().is_finite() && self.lon().is_finite()
+    }
+
+    /// Returns true if latitude and longitude are finite numbers in
     /// a valid range:
     ///
     /// - -90 < lat < 90
     /// - -180 < lon < 180
     pub fn is_valid(&self) -> bool {
-        !self.is_default()
+        self.is_finite()
+            && !self.is_default()
             && -90. <= self.lat()
             && self.lat() <= 90.
             && -180. <= self.lon()
             && self.lon() <= 180.
     }
+
+    /// Returns true if the coordinate lies in `rect`, bounds included.
+    pub fn is_in(&self, rect: &geo::Rect<f64>) -> bool {
+        rect.min().x <= self.lon()
+            && self.lon() <= rect.max().x
+            && rect.min().y <= self.lat()
+            && self.lat() <= rect.max().y
+    }
 }
 
 impl ::std::ops::Deref for Coord {
-    type Target = geo::Coordinate<f64>;
+    type Target = geo_types::Coord<f64>;
     fn deref(&self) -> &Self::Target {
         &self.0
     }
@@ -76,13 +131,193 @@ impl ::std::ops::Deref for Coord {
 
 impl Default for Coord {
     fn default() -> Coord {
-        Coord(geo::Coordinate { x: 0., y: 0. })
+        Coord(geo_types::Coord { x: 0., y: 0. })
     }
 }
 
 impl From<geo::Point<f64>> for Coord {
     fn from(point: geo::Point<f64>) -> Self {
-        Coord::new(point.lng(), point.lat())
+        Coord::new(point.x(), point.y())
+    }
+}
+
+impl From<Coord> for geo::Point<f64> {
+    fn from(coord: Coord) -> Self {
+        geo::Point(coord.0)
+    }
+}
+
+impl From<&Coord> for geo::Point<f64> {
+    fn from(coord: &Coord) -> Self {
+        geo::Point(coord.0)
+    }
+}
+
+/// Formats the coordinate as a WKT point, `POINT(lon lat)`
+impl std::fmt::Display for Coord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "POINT({} {})", self.lon(), self.lat())
+    }
+}
+
+/// Parses a WKT point, `POINT(lon lat)`. The keyword is case insensitive,
+/// and an EWKT `SRID=4326;` prefix is accepted.
+impl std::str::FromStr for Coord {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Coord> {
+        let invalid = || anyhow::anyhow!("'{}' is not a valid WKT point", s);
+        let mut wkt = s.trim();
+        if let Some((srid, point)) = wkt.split_once(';') {
+            if !srid.trim().eq_ignore_ascii_case("SRID=4326") {
+                anyhow::bail!("'{}' is not in WGS84 (SRID=4326)", s);
+            }
+            wkt = point.trim();
+        }
+        let keyword = wkt.get(..5).ok_or_else(invalid)?;
+        if !keyword.eq_ignore_ascii_case("POINT") {
+            return Err(invalid());
+        }
+        let coords = wkt[5..]
+            .trim_start()
+            .strip_prefix('(')
+            .and_then(|coords| coords.strip_suffix(')'))
+            .ok_or_else(invalid)?;
+        let mut coords = coords.split_whitespace().map(str::parse::<f64>);
+        match (coords.next(), coords.next(), coords.next()) {
+            (Some(Ok(lon)), Some(Ok(lat)), None) => Coord::try_new(lon, lat),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A latitude in degrees, a finite number between -90 and 90
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct Lat(f64);
+
+impl Lat {
+    /// Creates a latitude, failing when `lat` is not a finite number
+    /// between -90 and 90
+    pub fn new(lat: f64) -> Result<Lat> {
+        if !(-90. ..=90.).contains(&lat) {
+            anyhow::bail!("latitude {} is not between -90 and 90", lat);
+        }
+        Ok(Lat(lat))
+    }
+
+    /// Returns the latitude in degrees
+    pub fn value(self) -> f64 {
+        self.0
+    }
+}
+
+impl TryFrom<f64> for Lat {
+    type Error = crate::Error;
+
+    fn try_from(lat: f64) -> Result<Lat> {
+        Lat::new(lat)
+    }
+}
+
+impl From<Lat> for f64 {
+    fn from(lat: Lat) -> f64 {
+        lat.0
+    }
+}
+
+impl std::fmt::Display for Lat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A longitude in degrees, a finite number between -180 and 180
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct Lon(f64);
+
+impl Lon {
+    /// Creates a longitude, failing when `lon` is not a finite number
+    /// between -180 and 180
+    pub fn new(lon: f64) -> Result<Lon> {
+        if !(-180. ..=180.).contains(&lon) {
+            anyhow::bail!("longitude {} is not between -180 and 180", lon);
+        }
+        Ok(Lon(lon))
+    }
+
+    /// Returns the longitude in degrees
+    pub fn value(self) -> f64 {
+        self.0
+    }
+}
+
+impl TryFrom<f64> for Lon {
+    type Error = crate::Error;
+
+    fn try_from(lon: f64) -> Result<Lon> {
+        Lon::new(lon)
+    }
+}
+
+impl From<Lon> for f64 {
+    fn from(lon: Lon) -> f64 {
+        lon.0
+    }
+}
+
+impl std::fmt::Display for Lon {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The weight of a [Poi]
+///
+/// Weights are floating-point numbers. Integral weights are serialized as
+/// integers, so that archives with integer weights keep their format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Deserialize)]
+#[serde(transparent)]
+pub struct Weight(pub f64);
+
+impl Weight {
+    /// Returns the weight as a floating-point number
+    pub fn value(self) -> f64 {
+        self.0
+    }
+}
+
+impl From<u32> for Weight {
+    fn from(weight: u32) -> Weight {
+        Weight(f64::from(weight))
+    }
+}
+
+impl From<f64> for Weight {
+    fn from(weight: f64) -> Weight {
+        Weight(weight)
+    }
+}
+
+impl std::fmt::Display for Weight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Serialize for Weight {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        // 2^53, the largest range where all integers are exact f64 values
+        const MAX_EXACT: f64 = 9_007_199_254_740_992.;
+        if self.0.fract() == 0. && self.0.abs() <= MAX_EXACT {
+            serializer.serialize_i64(self.0 as i64)
+        } else {
+            serializer.serialize_f64(self.0)
+        }
     }
 }
 
@@ -98,7 +333,11 @@ pub struct Property {
 }
 
 /// A Poi
-#[derive(Debug, Clone)]
+///
+/// Two POIs are equal when all their fields are. Coordinates and weights are
+/// compared exactly, so a POI with a NaN coordinate is not equal to itself:
+/// use [Poi::approx_eq] to compare computed coordinates.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Poi {
     /// Unique id of the POI
     pub id: String,
@@ -119,21 +358,297 @@ pub struct Poi {
     pub visible: bool,
 
     /// Weight
-    pub weight: u32,
+    pub weight: Weight,
+
+    /// Accessibility, mirrored in the properties when saved: see
+    /// [Poi::set_accessibility] to mirror it in the model as well
+    pub accessibility: Accessibility,
+
+    /// Elevation above sea level, in meters, if known
+    pub elevation_m: Option<f64>,
+
+    /// When the POI was created, if known
+    pub created_at: Option<DateTime<Utc>>,
+
+    /// When the POI was last modified, if known. Bumped by the mutation
+    /// APIs of [Model].
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// How far two objects may differ and still be considered equal by
+/// [Model::approx_eq]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Maximum difference, in degrees, between two latitudes or longitudes
+    pub coord_epsilon: f64,
+
+    /// Ignore the weights of the POIs
+    pub ignore_weights: bool,
+}
+
+impl Default for Tolerance {
+    fn default() -> Tolerance {
+        Tolerance {
+            coord_epsilon: 1e-9,
+            ignore_weights: false,
+        }
+    }
+}
+
+impl Poi {
+    /// Returns true if both POIs are equal, within the given tolerance
+    pub fn approx_eq(&self, other: &Poi, tolerance: Tolerance) -> bool {
+        self.id == other.id
+            && self.name == other.name
+            && (self.coord.lon() - other.coord.lon()).abs() <= tolerance.coord_epsilon
+            && (self.coord.lat() - other.coord.lat()).abs() <= tolerance.coord_epsilon
+            && self.poi_type_id == other.poi_type_id
+            && self.properties == other.properties
+            && self.visible == other.visible
+            && (tolerance.ignore_weights || self.weight == other.weight)
+            && self.accessibility == other.accessibility
+            && self.elevation_m == other.elevation_m
+            && self.created_at == other.created_at
+            && self.updated_at == other.updated_at
+    }
+}
+
+/// The location of the POI, to use it in [geo] algorithms
+impl From<&Poi> for geo::Point<f64> {
+    fn from(poi: &Poi) -> Self {
+        geo::Point::from(&poi.coord)
+    }
+}
+
+impl Default for Poi {
+    /// An empty, visible POI
+    fn default() -> Poi {
+        Poi {
+            id: String::default(),
+            name: String::default(),
+            coord: Coord::default(),
+            poi_type_id: String::default(),
+            properties: BTreeMap::default(),
+            visible: true,
+            weight: Weight::default(),
+            accessibility: Accessibility::default(),
+            elevation_m: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
 }
 
 /// A type of POI
-#[derive(Debug, Clone, Deserialize, Serialize, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Ord, PartialOrd, Eq, PartialEq)]
 pub struct PoiType {
     /// Unique id of the POI type
     pub id: String,
 
     /// Name of the POI type.
     pub name: String,
+
+    /// Id of the parent type, if any (e.g. `amenity` for `amenity:parking`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+
+    /// Icon used to render the POIs of this type (e.g. `parking.svg`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+
+    /// Color used to render the POIs of this type (e.g. `#3366ff`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+
+    /// Minimum zoom level at which the POIs of this type are displayed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zoom_min: Option<u8>,
+
+    /// Whether the POIs of this type are displayed by default, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visible_by_default: Option<bool>,
+
+    /// Names of the POI type in other languages, by language tag (e.g.
+    /// `en` or `fr-CA`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub translations: BTreeMap<String, String>,
+}
+
+impl PoiType {
+    /// The name of the POI type in `lang`. Falls back to the language
+    /// without its region (`fr` for `fr-CA`), then to the default name.
+    pub fn name_in(&self, lang: &str) -> &str {
+        let language = lang.split(['-', '_']).next().unwrap_or(lang);
+        self.translations
+            .get(lang)
+            .or_else(|| self.translations.get(language))
+            .unwrap_or(&self.name)
+    }
+
+    /// A POI type standing for an unknown one, named after its id
+    pub(crate) fn placeholder(id: &str) -> PoiType {
+        PoiType {
+            id: id.to_string(),
+            name: id.to_string(),
+            ..PoiType::default()
+        }
+    }
+}
+
+/// Quality statistics of the POIs of a type, see [Model::stats_by_type].
+/// Shares are between 0 and 1, and 0 for a type without POI.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PoiTypeStats {
+    /// Number of POIs of the type
+    pub count: usize,
+
+    /// Share of the POIs whose name is empty or blank
+    pub empty_names: f64,
+
+    /// Share of the POIs whose coordinates are not [valid](Coord::is_valid)
+    pub invalid_coords: f64,
+
+    /// Share of the POIs with a zero weight
+    pub zero_weights: f64,
+
+    /// Average number of properties of the POIs
+    pub average_properties: f64,
+}
+
+/// What to do with the POI types that are no longer referenced by any POI,
+/// after some POIs have been removed from a [Model].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnusedPoiTypes {
+    /// Keep them in the model
+    Keep,
+
+    /// Remove them from the model
+    Drop,
+}
+
+/// How [Model::try_merge_with] handles a POI whose id is already used by a
+/// different POI of the model
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum IdCollision {
+    /// Fail, like [Model::try_merge]
+    #[default]
+    Fail,
+
+    /// Rename the POI to `<id><suffix>`
+    Suffix(String),
+
+    /// Rename the POI to `<namespace>:<id>`
+    Namespace(String),
+}
+
+/// How POIs sharing the same id are handled when merging several models,
+/// see [Model::try_merge_with_strategy]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Fail, unless the POIs are identical
+    #[default]
+    Fail,
+
+    /// Keep the POI of the model merged first
+    KeepFirst,
+
+    /// Keep the POI of the model merged last
+    KeepLast,
+
+    /// Rename the POI of the model merged last, see [Model::try_merge_with]
+    Rename(IdCollision),
+}
+
+/// A link between two POIs of a [Model], like a station and one of its
+/// entrances
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PoiRelation {
+    /// Id of the parent POI, like the station
+    pub parent_id: String,
+
+    /// Id of the child POI, like the entrance
+    pub child_id: String,
+
+    /// Type of the relation, like [PoiRelation::ENTRANCE]
+    pub relation_type: String,
+}
+
+impl PoiRelation {
+    /// The child is an entrance of the parent
+    pub const ENTRANCE: &'static str = "entrance";
+
+    /// The child is a part of the parent, like a building of a campus
+    pub const PART: &'static str = "part";
+}
+
+/// How [Model::repair_references] handles the POIs referencing a POI type
+/// that is not in the model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceRepair {
+    /// Remove the POIs
+    Drop,
+
+    /// Register a placeholder POI type, named after its id
+    Placeholder,
+
+    /// Give the POIs the POI type associated to their unknown type in the
+    /// table. POIs whose type is not in the table, or associated to a type
+    /// that is not in the model either, are left as they are.
+    Remap(HashMap<String, String>),
+}
+
+/// What to do with the POIs referencing a POI type that is not in the
+/// [Model] they are added to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownPoiTypes {
+    /// Register a placeholder POI type, named after its id
+    #[default]
+    Placeholder,
+
+    /// Fail
+    Error,
+}
+
+/// Information about a dataset, stored in the `metadata.json` file of the
+/// archive
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Metadata {
+    /// Name of the provider of the data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+
+    /// When the data were extracted from their source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extracted_at: Option<DateTime<Utc>>,
+
+    /// Version of the source of the data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_version: Option<String>,
+
+    /// License of the data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+
+    /// Bounding box of the data, as `[min_lon, min_lat, max_lon, max_lat]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<[f64; 4]>,
+
+    /// Dialect of the CSV files of the loaded archive, when it is not the
+    /// expected one (see [io::LoadOptions::csv]). It is not saved.
+    #[serde(skip)]
+    pub dialect: Option<io::Dialect>,
 }
 
 /// A data structure used for exporting and importing data to and from file.
-#[derive(Debug, Default)]
+///
+/// Two models are equal when their POIs, POI types, metadata and relations
+/// are, with the same exact comparison of floats as [Poi]: use
+/// [Model::approx_eq] to allow for rounding errors.
+///
+/// Cloning a model copies all its data, and so does taking a snapshot of
+/// it. For clones and snapshots that share their data, convert it to a
+/// `persistent::PersistentModel`, behind the `persistent` feature.
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Model {
     /// A list of POIs.
     ///
@@ -145,6 +660,12 @@ pub struct Model {
     /// We use a hashmap to list poi types, as the main purpose is to search
     /// for a PoiType based on its id. (Poi only stores the type's id)
     pub poi_types: HashMap<String, PoiType>,
+
+    /// Information about the dataset, if any
+    pub metadata: Option<Metadata>,
+
+    /// Links between POIs
+    pub relations: BTreeSet<PoiRelation>,
 }
 
 impl Model {
@@ -153,11 +674,158 @@ impl Model {
         io::load_model_from_path(path.as_ref())
     }
 
+    /// Creates a new model based on data found in `path`, using the given
+    /// options.
+    pub fn try_from_path_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &io::LoadOptions,
+    ) -> Result<Model> {
+        io::load_model_from_path_with_options(path.as_ref(), options)
+    }
+
+    /// Creates a new model from the archives in `paths`, loaded in parallel
+    /// and merged in order. See [io::load_model_from_paths].
+    pub fn try_from_paths<I, P>(paths: I, strategy: &MergeStrategy) -> Result<Model>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        io::load_model_from_paths(paths, strategy)
+    }
+
+    /// Creates a new model from the archives matching the glob `pattern`
+    /// (e.g. `data/*.poi`). See [io::load_model_from_glob].
+    pub fn try_from_glob(pattern: &str, strategy: &MergeStrategy) -> Result<Model> {
+        io::load_model_from_glob(pattern, strategy)
+    }
+
+    /// Creates a new model from the archive at `url`, like
+    /// `s3://bucket/fr.poi`. See [io::load_model_from_url].
+    #[cfg(feature = "object_store")]
+    pub fn try_from_url(url: &str) -> Result<Model> {
+        io::load_model_from_url(url, &io::LoadOptions::default())
+    }
+
+    /// Creates a new model from the OSM objects returned by the Overpass QL
+    /// `query` on `endpoint`, turned into POIs by `mapping`. See
+    /// [io::load_model_from_overpass].
+    #[cfg(feature = "overpass")]
+    pub fn try_from_overpass(
+        query: &str,
+        endpoint: &str,
+        mapping: &crate::mapping::Mapping,
+    ) -> Result<Model> {
+        io::load_model_from_overpass(query, endpoint, mapping)
+    }
+
+    /// Creates a new model from the archive read from `reader`, like the
+    /// standard input. See [io::load_model_from_reader].
+    pub fn try_from_reader<R: std::io::Read>(reader: R) -> Result<Model> {
+        io::load_model_from_reader(reader, &io::LoadOptions::default())
+    }
+
+    /// Creates a new model from the stops of the GTFS or NTFS feed found in
+    /// `path`. See [io::load_model_from_gtfs_stops].
+    pub fn try_from_gtfs_stops<P: AsRef<Path>>(
+        path: P,
+        mapping: &io::StopMapping,
+    ) -> Result<Model> {
+        io::load_model_from_gtfs_stops(path.as_ref(), mapping)
+    }
+
+    /// Creates a new model from the JSON records read from `reader`, whose
+    /// fields are found according to `mapping`. See
+    /// [io::load_model_from_json_records].
+    pub fn try_from_json_records<R: std::io::Read>(
+        reader: R,
+        mapping: &io::FieldMapping,
+    ) -> Result<Model> {
+        io::load_model_from_json_records(reader, mapping)
+    }
+
+    /// Creates a new model from the binary snapshot at `path`, written by
+    /// [Model::save_binary]. See [io::load_model_from_binary].
+    #[cfg(feature = "binary")]
+    pub fn load_binary<P: AsRef<Path>>(path: P) -> Result<Model> {
+        io::load_model_from_binary(path.as_ref())
+    }
+
     /// Saves the model to file.
     pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         io::write_model_to_path(self, path.as_ref())
     }
 
+    /// Saves the model to `url`, like `s3://bucket/fr.poi`. See
+    /// [io::write_model_to_url].
+    #[cfg(feature = "object_store")]
+    pub fn save_to_url(&self, url: &str) -> Result<()> {
+        io::write_model_to_url(self, url, &io::WriteOptions::default())
+    }
+
+    /// Writes the model as an archive to `writer`, like the standard
+    /// output. See [io::write_model_to_writer].
+    pub fn save_to_writer<W: std::io::Write>(&self, writer: W) -> Result<()> {
+        io::write_model_to_writer(self, writer, &io::WriteOptions::default())
+    }
+
+    /// Saves the model as a binary snapshot at `path`, loaded back much
+    /// faster than an archive by [Model::load_binary]. See
+    /// [io::write_model_to_binary].
+    #[cfg(feature = "binary")]
+    pub fn save_binary<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        io::write_model_to_binary(self, path.as_ref())
+    }
+
+    /// Saves the model to file, using the given options.
+    pub fn save_to_path_with_options<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &io::WriteOptions,
+    ) -> Result<()> {
+        io::write_model_to_path_with_options(self, path.as_ref(), options)
+    }
+
+    /// Returns the POIs, ordered by id
+    pub fn pois(&self) -> impl Iterator<Item = &Poi> {
+        self.pois.values()
+    }
+
+    /// Returns the POIs, ordered by id, for modification.
+    ///
+    /// The id of the POIs must not be changed this way, as the model is
+    /// indexed by id: use [Model::update_pois_where] if in doubt.
+    pub fn pois_mut(&mut self) -> impl Iterator<Item = &mut Poi> {
+        self.pois.values_mut()
+    }
+
+    /// Returns the visible POIs, ordered by id
+    pub fn visible_pois(&self) -> impl Iterator<Item = &Poi> {
+        self.pois.values().filter(|poi| poi.visible)
+    }
+
+    /// Returns the POI types, in no particular order
+    pub fn poi_types(&self) -> impl Iterator<Item = &PoiType> {
+        self.poi_types.values()
+    }
+
+    /// Returns the POI with the given id, if any
+    pub fn get_poi(&self, id: &str) -> Option<&Poi> {
+        self.pois.get(id)
+    }
+
+    /// Returns the POI type with the given id, if any
+    pub fn get_poi_type(&self, id: &str) -> Option<&PoiType> {
+        self.poi_types.get(id)
+    }
+
+    /// Returns the name in `lang` of the POI type with the given id, if any
+    /// (see [PoiType::name_in])
+    pub fn poi_type_name(&self, id: &str, lang: &str) -> Option<&str> {
+        self.poi_types
+            .get(id)
+            .map(|poi_type| poi_type.name_in(lang))
+    }
+
     /// Tries to merge a Model into another.
     pub fn try_merge(mut self, rhs: Model) -> Result<Model> {
         let merged_pois = rhs
@@ -173,25 +841,878 @@ impl Model {
                 }
             })?;
         self.pois = merged_pois;
+        self.merge_poi_types_and_metadata(rhs.poi_types, rhs.metadata)?;
+        self.relations.extend(rhs.relations);
+        Ok(self)
+    }
 
-        let merged_poi_types =
-            rhs.poi_types
-                .into_iter()
-                .try_fold(self.poi_types, |mut acc, (k, v)| match acc.entry(k) {
-                    HashMapEntry::Occupied(entry) => {
-                        if *entry.get() == v {
-                            Ok(acc) // If the poi_types in both map are identical (id and label), it's ok
-                        } else {
-                            anyhow::bail!("Trying to override POI Type with id {}", entry.key())
+    /// Merges a Model into another, like [Model::try_merge], but resolves
+    /// the collisions of POI ids according to `collision`.
+    ///
+    /// POIs identical to a POI of the model are merged into it. When the
+    /// new id of a renamed POI is taken too, `-2`, `-3`, ... is appended to
+    /// it. Returns the merged model, along with the new id of each renamed
+    /// POI of `rhs`.
+    pub fn try_merge_with(
+        self,
+        rhs: Model,
+        collision: &IdCollision,
+    ) -> Result<(Model, BTreeMap<String, String>)> {
+        self.merge_with(rhs, collision, None)
+    }
+
+    /// Same as [Model::try_merge_with], stopping with a
+    /// [Cancelled](crate::cancel::Cancelled) error once `token` is cancelled
+    pub fn try_merge_with_cancellable(
+        self,
+        rhs: Model,
+        collision: &IdCollision,
+        token: &CancellationToken,
+    ) -> Result<(Model, BTreeMap<String, String>)> {
+        self.merge_with(rhs, collision, Some(token))
+    }
+
+    fn merge_with(
+        self,
+        rhs: Model,
+        collision: &IdCollision,
+        token: Option<&CancellationToken>,
+    ) -> Result<(Model, BTreeMap<String, String>)> {
+        let traced = Traced::start("merge", "");
+        let result = self.merge_renaming(rhs, collision, token);
+        traced.finish_with(result, |(model, _)| model.pois.len())
+    }
+
+    fn merge_renaming(
+        mut self,
+        rhs: Model,
+        collision: &IdCollision,
+        token: Option<&CancellationToken>,
+    ) -> Result<(Model, BTreeMap<String, String>)> {
+        let mut remapped = BTreeMap::new();
+        let incoming_ids: HashSet<String> = rhs.pois.keys().cloned().collect();
+        for (id, mut poi) in rhs.pois {
+            cancel::check(token)?;
+            match self.pois.get(&id) {
+                None => {
+                    self.pois.insert(id, poi);
+                }
+                Some(existing) if *existing == poi => {}
+                Some(_) => {
+                    let base = match collision {
+                        IdCollision::Fail => {
+                            anyhow::bail!("POI with id {} already in the model", id)
                         }
+                        IdCollision::Suffix(suffix) => format!("{}{}", id, suffix),
+                        IdCollision::Namespace(namespace) => format!("{}:{}", namespace, id),
+                    };
+                    let new_id = std::iter::once(base.clone())
+                        .chain((2..).map(|n| format!("{}-{}", base, n)))
+                        .find(|candidate| {
+                            !self.pois.contains_key(candidate) && !incoming_ids.contains(candidate)
+                        })
+                        .expect("an unused id is always found");
+                    poi.id = new_id.clone();
+                    self.pois.insert(new_id.clone(), poi);
+                    remapped.insert(id, new_id);
+                }
+            }
+        }
+        self.merge_poi_types_and_metadata(rhs.poi_types, rhs.metadata)?;
+        let remap = |id: String| remapped.get(&id).cloned().unwrap_or(id);
+        self.relations
+            .extend(rhs.relations.into_iter().map(|relation| PoiRelation {
+                parent_id: remap(relation.parent_id),
+                child_id: remap(relation.child_id),
+                ..relation
+            }));
+        Ok((self, remapped))
+    }
+
+    /// Merges a Model into another, handling the POIs sharing the same id
+    /// according to `strategy`. The POI types must be compatible, like with
+    /// [Model::try_merge].
+    pub fn try_merge_with_strategy(self, rhs: Model, strategy: &MergeStrategy) -> Result<Model> {
+        self.merge_with_strategy(rhs, strategy, None)
+    }
+
+    /// Same as [Model::try_merge_with_strategy], reporting to `warnings`
+    /// the POIs replaced, dropped or renamed, and the metadata of `rhs`
+    /// dropped
+    pub fn try_merge_with_warnings(
+        self,
+        rhs: Model,
+        strategy: &MergeStrategy,
+        warnings: &WarningCallback,
+    ) -> Result<Model> {
+        self.merge_with_strategy(rhs, strategy, Some(warnings))
+    }
+
+    fn merge_with_strategy(
+        mut self,
+        rhs: Model,
+        strategy: &MergeStrategy,
+        callback: Option<&WarningCallback>,
+    ) -> Result<Model> {
+        if self.metadata.is_some() && rhs.metadata.is_some() && self.metadata != rhs.metadata {
+            warnings::warn(callback, Warning::MetadataDropped);
+        }
+        let collision = match strategy {
+            MergeStrategy::Fail => Some(&IdCollision::Fail),
+            MergeStrategy::Rename(collision) => Some(collision),
+            MergeStrategy::KeepFirst | MergeStrategy::KeepLast => None,
+        };
+        if let Some(collision) = collision {
+            let (merged, remapped) = self.try_merge_with(rhs, collision)?;
+            for (from, to) in remapped {
+                warnings::warn(callback, Warning::PoiRenamed { from, to });
+            }
+            return Ok(merged);
+        }
+        let traced = Traced::start("merge", "");
+        let keep_last = *strategy == MergeStrategy::KeepLast;
+        for (id, poi) in rhs.pois {
+            match self.pois.get(&id) {
+                None => {
+                    self.pois.insert(id, poi);
+                }
+                Some(existing) if *existing == poi => {}
+                Some(_) if keep_last => {
+                    warnings::warn(callback, Warning::PoiReplaced { poi_id: id.clone() });
+                    self.pois.insert(id, poi);
+                }
+                Some(_) => warnings::warn(callback, Warning::PoiDropped { poi_id: id }),
+            }
+        }
+        let result = self.merge_poi_types_and_metadata(rhs.poi_types, rhs.metadata);
+        self.relations.extend(rhs.relations);
+        traced.finish_with(result.map(|_| self), |model| model.pois.len())
+    }
+
+    fn merge_poi_types_and_metadata(
+        &mut self,
+        poi_types: HashMap<String, PoiType>,
+        metadata: Option<Metadata>,
+    ) -> Result<()> {
+        let merged_poi_types = poi_types.into_iter().try_fold(
+            std::mem::take(&mut self.poi_types),
+            |mut acc, (k, v)| match acc.entry(k) {
+                HashMapEntry::Occupied(entry) => {
+                    if *entry.get() == v {
+                        Ok(acc) // If the poi_types in both map are identical (id and label), it's ok
+                    } else {
+                        anyhow::bail!("Trying to override POI Type with id {}", entry.key())
                     }
-                    HashMapEntry::Vacant(entry) => {
-                        entry.insert(v);
-                        Ok(acc)
-                    }
-                })?;
+                }
+                HashMapEntry::Vacant(entry) => {
+                    entry.insert(v);
+                    Ok(acc)
+                }
+            },
+        )?;
 
         self.poi_types = merged_poi_types;
-        Ok(self)
+        self.metadata = self.metadata.take().or(metadata);
+        Ok(())
+    }
+
+    /// Returns true if both models hold the same POIs, POI types, relations
+    /// and metadata, within the given tolerance.
+    pub fn approx_eq(&self, other: &Model, tolerance: Tolerance) -> bool {
+        let bbox_eq = match (
+            self.metadata.as_ref().and_then(|m| m.bbox),
+            other.metadata.as_ref().and_then(|m| m.bbox),
+        ) {
+            (Some(lhs), Some(rhs)) => lhs
+                .iter()
+                .zip(rhs.iter())
+                .all(|(l, r)| (l - r).abs() <= tolerance.coord_epsilon),
+            (lhs, rhs) => lhs.is_none() && rhs.is_none(),
+        };
+        let without_bbox = |metadata: &Option<Metadata>| {
+            metadata.clone().map(|metadata| Metadata {
+                bbox: None,
+                ..metadata
+            })
+        };
+        self.pois.len() == other.pois.len()
+            && self
+                .pois
+                .values()
+                .zip(other.pois.values())
+                .all(|(lhs, rhs)| lhs.approx_eq(rhs, tolerance))
+            && self.poi_types == other.poi_types
+            && self.relations == other.relations
+            && bbox_eq
+            && without_bbox(&self.metadata) == without_bbox(&other.metadata)
+    }
+
+    /// Returns the ancestors of the POI type `id`, from its parent up to the
+    /// root of its hierarchy. Unknown parents end the walk, and cycles are
+    /// cut.
+    pub fn poi_type_ancestors(&self, id: &str) -> Vec<&PoiType> {
+        let mut ancestors: Vec<&PoiType> = Vec::new();
+        let mut current = self.poi_types.get(id);
+        while let Some(parent) = current
+            .and_then(|poi_type| poi_type.parent_id.as_ref())
+            .and_then(|parent_id| self.poi_types.get(parent_id))
+        {
+            if parent.id == id || ancestors.iter().any(|a| a.id == parent.id) {
+                break;
+            }
+            ancestors.push(parent);
+            current = Some(parent);
+        }
+        ancestors
+    }
+
+    /// Returns the POIs whose type is `id` or one of its descendants.
+    pub fn pois_under_type_subtree(&self, id: &str) -> Vec<&Poi> {
+        let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+        for poi_type in self.poi_types.values() {
+            if let Some(parent_id) = &poi_type.parent_id {
+                children
+                    .entry(parent_id.as_str())
+                    .or_default()
+                    .push(poi_type.id.as_str());
+            }
+        }
+        let mut subtree: HashSet<&str> = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            if subtree.insert(current) {
+                stack.extend(children.get(current).into_iter().flatten());
+            }
+        }
+        self.pois
+            .values()
+            .filter(|poi| subtree.contains(poi.poi_type_id.as_str()))
+            .collect()
+    }
+
+    /// Keeps only the POIs for which `predicate` returns true, and returns
+    /// the number of removed POIs.
+    pub fn retain_pois<F>(&mut self, mut predicate: F, unused_poi_types: UnusedPoiTypes) -> usize
+    where
+        F: FnMut(&Poi) -> bool,
+    {
+        let len = self.pois.len();
+        self.pois.retain(|_, poi| predicate(poi));
+        self.drop_dangling_relations();
+        if unused_poi_types == UnusedPoiTypes::Drop {
+            self.prune_poi_types();
+        }
+        len - self.pois.len()
+    }
+
+    /// Removes the POI with the given id, and returns it. With
+    /// [UnusedPoiTypes::Drop], its type and the ancestors of its type are
+    /// removed too, unless still used (see [Model::prune_poi_types]).
+    pub fn remove_poi(&mut self, id: &str, unused_poi_types: UnusedPoiTypes) -> Option<Poi> {
+        let poi = self.pois.remove(id)?;
+        self.relations
+            .retain(|relation| relation.parent_id != id && relation.child_id != id);
+        if unused_poi_types == UnusedPoiTypes::Drop {
+            let mut candidates: Vec<String> = self
+                .poi_type_ancestors(&poi.poi_type_id)
+                .into_iter()
+                .map(|poi_type| poi_type.id.clone())
+                .collect();
+            candidates.push(poi.poi_type_id.clone());
+            let used = self.used_poi_type_ids();
+            let unused: Vec<String> = candidates
+                .into_iter()
+                .filter(|id| !used.contains(id.as_str()))
+                .collect();
+            for id in unused {
+                self.poi_types.remove(&id);
+            }
+        }
+        Some(poi)
+    }
+
+    /// Applies `update` to every POI for which `predicate` returns true,
+    /// and returns the number of updated POIs. Their `updated_at` is set to
+    /// the current time.
+    ///
+    /// The id of the POIs cannot be changed this way: it is restored after
+    /// `update` if needed, so that the model stays indexed by id.
+    pub fn update_pois_where<P, F>(&mut self, mut predicate: P, mut update: F) -> usize
+    where
+        P: FnMut(&Poi) -> bool,
+        F: FnMut(&mut Poi),
+    {
+        let now = Utc::now();
+        let mut count = 0;
+        for (id, poi) in self.pois.iter_mut().filter(|(_, poi)| predicate(poi)) {
+            update(poi);
+            if &poi.id != id {
+                poi.id = id.clone();
+            }
+            poi.updated_at = Some(now);
+            count += 1;
+        }
+        count
+    }
+
+    /// Returns the POIs whose type is not in the model, ordered by id
+    pub fn check_references(&self) -> Vec<&Poi> {
+        let traced = Traced::start("check_references", "");
+        let pois: Vec<&Poi> = self
+            .pois
+            .values()
+            .filter(|poi| !self.poi_types.contains_key(&poi.poi_type_id))
+            .collect();
+        traced.finish(pois.len());
+        pois
+    }
+
+    /// Repairs the POIs whose type is not in the model, and returns the
+    /// number of repaired POIs. The `updated_at` of remapped POIs is set to
+    /// the current time.
+    pub fn repair_references(&mut self, repair: &ReferenceRepair) -> usize {
+        match repair {
+            ReferenceRepair::Drop => {
+                let poi_types = &self.poi_types;
+                let len = self.pois.len();
+                self.pois
+                    .retain(|_, poi| poi_types.contains_key(&poi.poi_type_id));
+                self.drop_dangling_relations();
+                len - self.pois.len()
+            }
+            ReferenceRepair::Placeholder => {
+                let unknown: Vec<String> = self
+                    .check_references()
+                    .iter()
+                    .map(|poi| poi.poi_type_id.clone())
+                    .collect();
+                for id in &unknown {
+                    self.poi_types
+                        .entry(id.clone())
+                        .or_insert_with(|| PoiType::placeholder(id));
+                }
+                unknown.len()
+            }
+            ReferenceRepair::Remap(table) => {
+                let poi_types = &self.poi_types;
+                let now = Utc::now();
+                let mut count = 0;
+                for poi in self.pois.values_mut() {
+                    if poi_types.contains_key(&poi.poi_type_id) {
+                        continue;
+                    }
+                    if let Some(poi_type_id) = table
+                        .get(&poi.poi_type_id)
+                        .filter(|id| poi_types.contains_key(*id))
+                    {
+                        poi.poi_type_id = poi_type_id.clone();
+                        poi.updated_at = Some(now);
+                        count += 1;
+                    }
+                }
+                count
+            }
+        }
+    }
+
+    pub(crate) fn drop_dangling_relations(&mut self) {
+        let pois = &self.pois;
+        self.relations.retain(|relation| {
+            pois.contains_key(&relation.parent_id) && pois.contains_key(&relation.child_id)
+        });
+    }
+
+    /// Links the POI `child_id` to its parent `parent_id`. Both POIs must be
+    /// in the model.
+    pub fn add_relation<T: Into<String>>(
+        &mut self,
+        parent_id: &str,
+        child_id: &str,
+        relation_type: T,
+    ) -> Result<()> {
+        for id in &[parent_id, child_id] {
+            if !self.pois.contains_key(*id) {
+                anyhow::bail!("cannot find poi '{}' for relation", id);
+            }
+        }
+        self.relations.insert(PoiRelation {
+            parent_id: parent_id.to_string(),
+            child_id: child_id.to_string(),
+            relation_type: relation_type.into(),
+        });
+        Ok(())
+    }
+
+    /// Returns the children of the POI, of any relation type, ordered by id
+    pub fn children_of(&self, poi_id: &str) -> Vec<&Poi> {
+        self.relations
+            .iter()
+            .filter(|relation| relation.parent_id == poi_id)
+            .filter_map(|relation| self.pois.get(&relation.child_id))
+            .sorted_by_key(|poi| &poi.id)
+            .dedup_by(|lhs, rhs| lhs.id == rhs.id)
+            .collect()
+    }
+
+    /// Returns the parents of the POI, of any relation type, ordered by id
+    pub fn parents_of(&self, poi_id: &str) -> Vec<&Poi> {
+        self.relations
+            .iter()
+            .filter(|relation| relation.child_id == poi_id)
+            .filter_map(|relation| self.pois.get(&relation.parent_id))
+            .sorted_by_key(|poi| &poi.id)
+            .dedup_by(|lhs, rhs| lhs.id == rhs.id)
+            .collect()
+    }
+
+    /// Returns quality statistics of the POIs of each type, by POI type id.
+    /// The types without POI are included, as well as the unknown types
+    /// referenced by POIs.
+    ///
+    /// ```
+    /// use navitia_poi_model::{Coord, Model, Poi};
+    ///
+    /// let mut model = Model::default();
+    /// model.extend(vec![
+    ///     Poi { id: "a".into(), name: "Gare".into(), coord: Coord::new(2.37, 48.84), poi_type_id: "station".into(), ..Poi::default() },
+    ///     Poi { id: "b".into(), poi_type_id: "station".into(), ..Poi::default() },
+    /// ]);
+    /// let stats = &model.stats_by_type()["station"];
+    /// assert_eq!(stats.count, 2);
+    /// assert_eq!(stats.empty_names, 0.5);
+    /// assert_eq!(stats.invalid_coords, 0.5);
+    /// assert_eq!(stats.zero_weights, 1.);
+    /// ```
+    pub fn stats_by_type(&self) -> BTreeMap<String, PoiTypeStats> {
+        let mut stats: BTreeMap<String, PoiTypeStats> = self
+            .poi_types
+            .keys()
+            .map(|id| (id.clone(), PoiTypeStats::default()))
+            .collect();
+        // sums first, divided by the counts at the end
+        for poi in self.pois.values() {
+            let stats = match stats.get_mut(&poi.poi_type_id) {
+                Some(stats) => stats,
+                None => stats.entry(poi.poi_type_id.clone()).or_default(),
+            };
+            stats.count += 1;
+            if poi.name.trim().is_empty() {
+                stats.empty_names += 1.;
+            }
+            if !poi.coord.is_valid() {
+                stats.invalid_coords += 1.;
+            }
+            if poi.weight.value() == 0. {
+                stats.zero_weights += 1.;
+            }
+            stats.average_properties += poi.properties.len() as f64;
+        }
+        for stats in stats.values_mut().filter(|stats| stats.count > 0) {
+            let count = stats.count as f64;
+            stats.empty_names /= count;
+            stats.invalid_coords /= count;
+            stats.zero_weights /= count;
+            stats.average_properties /= count;
+        }
+        stats
+    }
+
+    /// Removes the POI types that are neither the type of a POI, nor an
+    /// ancestor of such a type, and returns them ordered by id.
+    pub fn prune_poi_types(&mut self) -> Vec<PoiType> {
+        let used: HashSet<String> = self
+            .used_poi_type_ids()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let unused: Vec<String> = self
+            .poi_types
+            .keys()
+            .filter(|id| !used.contains(*id))
+            .cloned()
+            .collect();
+        unused
+            .iter()
+            .filter_map(|id| self.poi_types.remove(id))
+            .sorted_by(|lhs, rhs| lhs.id.cmp(&rhs.id))
+            .collect()
+    }
+
+    /// Ids of the types of the POIs, and of their ancestors
+    pub(crate) fn used_poi_type_ids(&self) -> HashSet<&str> {
+        let mut used = HashSet::new();
+        for poi in self.pois.values() {
+            if used.insert(poi.poi_type_id.as_str()) {
+                used.extend(
+                    self.poi_type_ancestors(&poi.poi_type_id)
+                        .into_iter()
+                        .map(|poi_type| poi_type.id.as_str()),
+                );
+            }
+        }
+        used
+    }
+
+    /// Splits the model into a grid of square cells of `cell_size` degrees.
+    ///
+    /// The cells are indexed by `(x, y)`, where `x = floor(lon / cell_size)`
+    /// and `y = floor(lat / cell_size)`. Each resulting model contains the
+    /// POIs of its cell, and the POI types they reference along with their
+    /// ancestors. Empty cells are not returned.
+    ///
+    /// Fails if `cell_size` is not a finite, positive number.
+    pub fn split_by_grid(&self, cell_size: f64) -> Result<BTreeMap<(i64, i64), Model>> {
+        if !(cell_size.is_finite() && cell_size > 0.0) {
+            anyhow::bail!(
+                "invalid cell size {}, it must be finite and positive",
+                cell_size
+            );
+        }
+        let mut cells: BTreeMap<(i64, i64), Vec<&Poi>> = BTreeMap::new();
+        for poi in self.pois.values() {
+            let x = (poi.coord.lon() / cell_size).floor() as i64;
+            let y = (poi.coord.lat() / cell_size).floor() as i64;
+            cells.entry((x, y)).or_default().push(poi);
+        }
+        Ok(cells
+            .into_iter()
+            .map(|(cell, pois)| (cell, self.sub_model(pois)))
+            .collect())
+    }
+
+    /// Splits the model into one model per POI type, indexed by POI type id.
+    ///
+    /// Each resulting model contains the POIs of its type, the POI type
+    /// itself and its ancestors, and the relations between these POIs. POI types without
+    /// POIs are not returned.
+    pub fn split_by_poi_type(&self) -> BTreeMap<String, Model> {
+        let mut types: BTreeMap<&str, Vec<&Poi>> = BTreeMap::new();
+        for poi in self.pois.values() {
+            types.entry(&poi.poi_type_id).or_default().push(poi);
+        }
+        types
+            .into_iter()
+            .map(|(id, pois)| (id.to_string(), self.sub_model(pois)))
+            .collect()
+    }
+
+    /// Splits the model into one model per bounding box, in the same order.
+    ///
+    /// Bounds are inclusive, so a POI lying on the edge of several
+    /// (or in overlapping) boxes is part of each of them.
+    pub fn split_by_bboxes(&self, bboxes: &[geo::Rect<f64>]) -> Vec<Model> {
+        bboxes
+            .iter()
+            .map(|bbox| self.sub_model(self.pois.values().filter(|poi| poi.coord.is_in(bbox))))
+            .collect()
+    }
+
+    /// Returns the smallest box containing the POIs with
+    /// [valid](Coord::is_valid) coordinates, or `None` when there is none.
+    ///
+    /// As the fields of the model are public, the box is computed at each
+    /// call. [ObservedModel](crate::observer::ObservedModel) caches it.
+    ///
+    /// ```
+    /// use navitia_poi_model::{Coord, Model, Poi};
+    ///
+    /// let mut model = Model::default();
+    /// model.extend(vec![
+    ///     Poi { id: "a".into(), coord: Coord::new(2.35, 48.85), ..Poi::default() },
+    ///     Poi { id: "b".into(), coord: Coord::new(4.83, 45.76), ..Poi::default() },
+    ///     Poi { id: "c".into(), coord: Coord::default(), ..Poi::default() },
+    /// ]);
+    /// let bbox = model.bounding_box().unwrap();
+    /// assert_eq!((bbox.min().x, bbox.min().y), (2.35, 45.76));
+    /// assert_eq!((bbox.max().x, bbox.max().y), (4.83, 48.85));
+    /// ```
+    pub fn bounding_box(&self) -> Option<geo::Rect<f64>> {
+        self.pois
+            .values()
+            .map(|poi| &poi.coord)
+            .filter(|coord| coord.is_valid())
+            .fold(None, |bbox: Option<geo::Rect<f64>>, coord| {
+                Some(match bbox {
+                    None => geo::Rect::new(coord.0, coord.0),
+                    Some(bbox) => geo::Rect::new(
+                        geo_types::Coord {
+                            x: bbox.min().x.min(coord.lon()),
+                            y: bbox.min().y.min(coord.lat()),
+                        },
+                        geo_types::Coord {
+                            x: bbox.max().x.max(coord.lon()),
+                            y: bbox.max().y.max(coord.lat()),
+                        },
+                    ),
+                })
+            })
+    }
+
+    /// Returns the centroid of the POIs with [valid](Coord::is_valid)
+    /// coordinates, or `None` when there is none.
+    ///
+    /// Like the other [geo] algorithms, it is computed in the plane of the
+    /// longitudes and latitudes, which is fine for the extent of a city or
+    /// a country, but not across the antimeridian.
+    pub fn centroid(&self) -> Option<Coord> {
+        self.valid_points().centroid().map(Coord::from)
+    }
+
+    /// Returns the convex hull of the POIs with [valid](Coord::is_valid)
+    /// coordinates, or `None` when there is none. The hull of a single POI
+    /// is a degenerate polygon. See [Model::centroid] about the plane in
+    /// which it is computed.
+    ///
+    /// ```
+    /// use geo::prelude::Area;
+    /// use navitia_poi_model::{Coord, Model, Poi};
+    ///
+    /// let mut model = Model::default();
+    /// model.extend(vec![
+    ///     Poi { id: "a".into(), coord: Coord::new(1., 1.), ..Poi::default() },
+    ///     Poi { id: "b".into(), coord: Coord::new(3., 1.), ..Poi::default() },
+    ///     Poi { id: "c".into(), coord: Coord::new(3., 3.), ..Poi::default() },
+    ///     Poi { id: "d".into(), coord: Coord::new(1., 3.), ..Poi::default() },
+    ///     Poi { id: "e".into(), coord: Coord::new(2., 2.), ..Poi::default() },
+    /// ]);
+    /// assert_eq!(model.convex_hull().unwrap().unsigned_area(), 4.);
+    /// assert_eq!(model.centroid(), Some(Coord::new(2., 2.)));
+    /// ```
+    pub fn convex_hull(&self) -> Option<geo::Polygon<f64>> {
+        let points = self.valid_points();
+        if points.0.is_empty() {
+            return None;
+        }
+        Some(points.convex_hull())
+    }
+
+    /// The points of the POIs with valid coordinates
+    fn valid_points(&self) -> geo::MultiPoint<f64> {
+        self.pois
+            .values()
+            .filter(|poi| poi.coord.is_valid())
+            .map(|poi| geo::Point::from(&poi.coord))
+            .collect()
+    }
+
+    /// Returns the POIs lying inside the polygon, ordered by id. POIs on
+    /// the boundary of the polygon are not part of it.
+    pub fn pois_in_polygon(&self, polygon: &geo::Polygon<f64>) -> Vec<&Poi> {
+        self.pois_in_shape(polygon.bounding_rect(), |point| polygon.contains(point))
+    }
+
+    /// Returns the POIs lying inside one of the polygons, ordered by id.
+    /// POIs on the boundary of a polygon are not part of it.
+    pub fn pois_in_multi_polygon(&self, multi_polygon: &geo::MultiPolygon<f64>) -> Vec<&Poi> {
+        self.pois_in_shape(multi_polygon.bounding_rect(), |point| {
+            multi_polygon.contains(point)
+        })
+    }
+
+    /// The POIs contained in a shape. The POIs out of the bounding box of
+    /// the shape are discarded before the (costly) containment check.
+    fn pois_in_shape<F>(&self, bbox: Option<geo::Rect<f64>>, contains: F) -> Vec<&Poi>
+    where
+        F: Fn(&geo::Point<f64>) -> bool,
+    {
+        let bbox = match bbox {
+            Some(bbox) => bbox,
+            None => return Vec::new(),
+        };
+        self.pois
+            .values()
+            .filter(|poi| poi.coord.is_in(&bbox))
+            .filter(|poi| contains(&geo::Point::from(poi.coord.0)))
+            .collect()
+    }
+
+    /// Builds a model made of clones of the given POIs, along with the POI
+    /// types they reference and the ancestors of these types.
+    pub(crate) fn sub_model<'a, I>(&self, pois: I) -> Model
+    where
+        I: IntoIterator<Item = &'a Poi>,
+    {
+        let pois: BTreeMap<String, Poi> = pois
+            .into_iter()
+            .map(|poi| (poi.id.clone(), poi.clone()))
+            .collect();
+        let mut poi_types = HashMap::new();
+        for poi in pois.values() {
+            if poi_types.contains_key(&poi.poi_type_id) {
+                continue;
+            }
+            let poi_type = self.poi_types.get(&poi.poi_type_id);
+            for poi_type in poi_type
+                .into_iter()
+                .chain(self.poi_type_ancestors(&poi.poi_type_id))
+            {
+                poi_types.insert(poi_type.id.clone(), poi_type.clone());
+            }
+        }
+        // the bounding box no longer holds for a subset of the POIs
+        let metadata = self.metadata.clone().map(|metadata| Metadata {
+            bbox: None,
+            ..metadata
+        });
+        let relations = self
+            .relations
+            .iter()
+            .filter(|relation| {
+                pois.contains_key(&relation.parent_id) && pois.contains_key(&relation.child_id)
+            })
+            .cloned()
+            .collect();
+        Model {
+            pois,
+            poi_types,
+            metadata,
+            relations,
+        }
+    }
+}
+
+impl Model {
+    /// Adds the POIs to the model, replacing those with the same id, and
+    /// handles their unknown POI types according to `policy`.
+    ///
+    /// With [UnknownPoiTypes::Error], the POIs preceding the first POI with
+    /// an unknown type are added before the error is returned.
+    pub fn try_extend<I>(&mut self, pois: I, policy: UnknownPoiTypes) -> Result<()>
+    where
+        I: IntoIterator<Item = Poi>,
+    {
+        for poi in pois {
+            if !self.poi_types.contains_key(&poi.poi_type_id) {
+                match policy {
+                    UnknownPoiTypes::Placeholder => {
+                        let poi_type = PoiType::placeholder(&poi.poi_type_id);
+                        self.poi_types.insert(poi_type.id.clone(), poi_type);
+                    }
+                    UnknownPoiTypes::Error => anyhow::bail!(
+                        "cannot find poi type '{}' of poi '{}'",
+                        poi.poi_type_id,
+                        poi.id
+                    ),
+                }
+            }
+            self.pois.insert(poi.id.clone(), poi);
+        }
+        Ok(())
+    }
+}
+
+/// Adds the POIs, registering a placeholder for their unknown POI types
+/// (see [UnknownPoiTypes::Placeholder])
+impl Extend<Poi> for Model {
+    fn extend<I: IntoIterator<Item = Poi>>(&mut self, pois: I) {
+        self.try_extend(pois, UnknownPoiTypes::Placeholder)
+            .expect("placeholder POI types cannot fail");
+    }
+}
+
+/// Builds a model from POIs, registering a placeholder for their POI types
+/// (see [UnknownPoiTypes::Placeholder])
+impl std::iter::FromIterator<Poi> for Model {
+    fn from_iter<I: IntoIterator<Item = Poi>>(pois: I) -> Model {
+        let mut model = Model::default();
+        model.extend(pois);
+        model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Coord, Model, Poi, PoiType, UnusedPoiTypes};
+
+    /// A model with the types `amenity` > `amenity:parking` >
+    /// `amenity:parking:underground`, and a POI of each leaf type
+    fn hierarchy() -> Model {
+        let mut model = Model::default();
+        for (id, parent_id) in &[
+            ("amenity", None),
+            ("amenity:parking", Some("amenity")),
+            ("amenity:parking:underground", Some("amenity:parking")),
+            ("amenity:bench", Some("amenity")),
+        ] {
+            model.poi_types.insert(
+                id.to_string(),
+                PoiType {
+                    id: id.to_string(),
+                    parent_id: parent_id.map(str::to_string),
+                    ..PoiType::default()
+                },
+            );
+        }
+        for (id, poi_type_id, lon) in &[
+            ("underground", "amenity:parking:underground", 0.5),
+            ("bench", "amenity:bench", 1.5),
+        ] {
+            model.pois.insert(
+                id.to_string(),
+                Poi {
+                    id: id.to_string(),
+                    poi_type_id: poi_type_id.to_string(),
+                    coord: Coord::new(*lon, 0.5),
+                    ..Poi::default()
+                },
+            );
+        }
+        model
+    }
+
+    fn poi_type_ids(model: &Model) -> Vec<&str> {
+        let mut ids: Vec<&str> = model.poi_types.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn remove_poi_keeps_the_ancestors_still_used() {
+        let mut model = hierarchy();
+        model.pois.insert(
+            "parking".to_string(),
+            Poi {
+                id: "parking".to_string(),
+                poi_type_id: "amenity:parking".to_string(),
+                ..Poi::default()
+            },
+        );
+        model.remove_poi("parking", UnusedPoiTypes::Drop).unwrap();
+        assert_eq!(
+            poi_type_ids(&model),
+            vec![
+                "amenity",
+                "amenity:bench",
+                "amenity:parking",
+                "amenity:parking:underground"
+            ]
+        );
+
+        model
+            .remove_poi("underground", UnusedPoiTypes::Drop)
+            .unwrap();
+        assert_eq!(poi_type_ids(&model), vec!["amenity", "amenity:bench"]);
+    }
+
+    #[test]
+    fn split_models_keep_the_ancestors() {
+        let model = hierarchy();
+        let cells = model.split_by_grid(1.0).unwrap();
+        assert_eq!(
+            poi_type_ids(&cells[&(0, 0)]),
+            vec!["amenity", "amenity:parking", "amenity:parking:underground"]
+        );
+        assert_eq!(
+            poi_type_ids(&cells[&(1, 0)]),
+            vec!["amenity", "amenity:bench"]
+        );
+    }
+
+    #[test]
+    fn split_by_grid_rejects_invalid_cell_sizes() {
+        let model = hierarchy();
+        for cell_size in &[0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(model.split_by_grid(*cell_size).is_err(), "{}", cell_size);
+        }
     }
 }
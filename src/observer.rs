@@ -0,0 +1,178 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+//! Notification of the changes made to a model
+//!
+//! An [ObservedModel] wraps a [Model] and notifies its [ModelObserver]s of
+//! every POI added, removed or updated through it, so that secondary
+//! indexes, caches or audit logs can be kept in sync.
+
+use crate::{Model, Poi, UnusedPoiTypes};
+use chrono::Utc;
+use std::cell::Cell;
+
+/// Receives the changes made to an [ObservedModel]. All the methods do
+/// nothing by default.
+pub trait ModelObserver {
+    /// Called after `poi` was added to the model
+    fn on_poi_added(&mut self, _poi: &Poi) {}
+
+    /// Called after `poi` was removed from the model
+    fn on_poi_removed(&mut self, _poi: &Poi) {}
+
+    /// Called after a POI was updated from `old` to `new`
+    fn on_poi_updated(&mut self, _old: &Poi, _new: &Poi) {}
+}
+
+/// A model whose changes are notified to observers
+///
+/// The model can be read freely, but must be modified through the methods
+/// of the wrapper for the observers to be notified.
+#[derive(Default)]
+pub struct ObservedModel {
+    model: Model,
+    observers: Vec<Box<dyn ModelObserver>>,
+    /// Bounding box of the model, computed on first use and reset by the
+    /// changes made through the wrapper
+    bounding_box: Cell<Option<Option<geo::Rect<f64>>>>,
+}
+
+impl std::fmt::Debug for ObservedModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObservedModel")
+            .field("model", &self.model)
+            .field("observers", &self.observers.len())
+            .finish()
+    }
+}
+
+impl ObservedModel {
+    /// Wraps the model, without any observer
+    pub fn new(model: Model) -> ObservedModel {
+        ObservedModel {
+            model,
+            observers: Vec::new(),
+            bounding_box: Cell::new(None),
+        }
+    }
+
+    /// Registers an observer, notified of the changes made from now on
+    pub fn subscribe<O: ModelObserver + 'static>(&mut self, observer: O) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Returns the model
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Returns the bounding box of the model, see [Model::bounding_box].
+    /// It is computed once, then only after a change to the POIs.
+    pub fn bounding_box(&self) -> Option<geo::Rect<f64>> {
+        if let Some(bounding_box) = self.bounding_box.get() {
+            return bounding_box;
+        }
+        let bounding_box = self.model.bounding_box();
+        self.bounding_box.set(Some(bounding_box));
+        bounding_box
+    }
+
+    /// Unwraps the model, dropping the observers
+    pub fn into_model(self) -> Model {
+        self.model
+    }
+
+    /// Adds a POI, replacing the one with the same id if any, and returns
+    /// the replaced POI. A placeholder is registered for an unknown POI
+    /// type (see [crate::UnknownPoiTypes::Placeholder]).
+    pub fn insert_poi(&mut self, poi: Poi) -> Option<Poi> {
+        let id = poi.id.clone();
+        let previous = self.model.pois.remove(&id);
+        self.bounding_box.set(None);
+        self.model.extend(std::iter::once(poi));
+        let poi = &self.model.pois[&id];
+        for observer in &mut self.observers {
+            match &previous {
+                Some(previous) => observer.on_poi_updated(previous, poi),
+                None => observer.on_poi_added(poi),
+            }
+        }
+        previous
+    }
+
+    /// Removes the POI with the given id, and returns it.
+    /// See [Model::remove_poi].
+    pub fn remove_poi(&mut self, id: &str, unused_poi_types: UnusedPoiTypes) -> Option<Poi> {
+        let poi = self.model.remove_poi(id, unused_poi_types)?;
+        self.bounding_box.set(None);
+        for observer in &mut self.observers {
+            observer.on_poi_removed(&poi);
+        }
+        Some(poi)
+    }
+
+    /// Keeps only the POIs for which `predicate` returns true, and returns
+    /// the number of removed POIs. See [Model::retain_pois].
+    pub fn retain_pois<F>(&mut self, mut predicate: F, unused_poi_types: UnusedPoiTypes) -> usize
+    where
+        F: FnMut(&Poi) -> bool,
+    {
+        let removed: Vec<String> = self
+            .model
+            .pois
+            .values()
+            .filter(|poi| !predicate(poi))
+            .map(|poi| poi.id.clone())
+            .collect();
+        for id in &removed {
+            self.remove_poi(id, UnusedPoiTypes::Keep);
+        }
+        if unused_poi_types == UnusedPoiTypes::Drop {
+            // removing nothing drops the POI types left unused
+            self.model.retain_pois(|_| true, UnusedPoiTypes::Drop);
+        }
+        removed.len()
+    }
+
+    /// Applies `update` to every POI for which `predicate` returns true,
+    /// and returns the number of updated POIs. See
+    /// [Model::update_pois_where].
+    pub fn update_pois_where<P, F>(&mut self, mut predicate: P, mut update: F) -> usize
+    where
+        P: FnMut(&Poi) -> bool,
+        F: FnMut(&mut Poi),
+    {
+        let now = Utc::now();
+        let mut count = 0;
+        for (id, poi) in self.model.pois.iter_mut().filter(|(_, poi)| predicate(poi)) {
+            let old = poi.clone();
+            update(poi);
+            if &poi.id != id {
+                poi.id = id.clone();
+            }
+            poi.updated_at = Some(now);
+            self.bounding_box.set(None);
+            for observer in &mut self.observers {
+                observer.on_poi_updated(&old, poi);
+            }
+            count += 1;
+        }
+        count
+    }
+}
+
+impl From<Model> for ObservedModel {
+    fn from(model: Model) -> ObservedModel {
+        ObservedModel::new(model)
+    }
+}
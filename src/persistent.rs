@@ -0,0 +1,352 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! A model backed by persistent maps, behind the `persistent` feature
+//!
+//! The POIs, POI types and relations of a [PersistentModel] are stored in
+//! the structurally shared maps of [im]: cloning the model, taking a
+//! [Snapshot] of it and restoring it are O(1), and a change only copies the
+//! nodes of the maps it touches. Clones that are changed apart keep sharing
+//! the rest of their data, so comparing two variants of a large model does
+//! not double its memory.
+//!
+//! Only the [PersistentModel] is cheap to clone: a [Model] stores its data
+//! in the maps of the standard library, and cloning it copies all of it.
+//! A [Model] is converted to a [PersistentModel] and back with [From], each
+//! conversion copying the whole model: tools comparing variants of a model
+//! convert it once, then clone and change the [PersistentModel], and only
+//! convert back the variants to save or to query with the API of [Model].
+//! [Model::snapshot] and [Model::restore] go through such a conversion, so
+//! they cost as much as a clone: to undo the edits of a large model
+//! cheaply, edit a [PersistentModel] instead.
+//!
+//! ```
+//! use navitia_poi_model::persistent::PersistentModel;
+//! use navitia_poi_model::{Model, Poi};
+//!
+//! let model: Model = vec![Poi { id: "poi:1".into(), ..Poi::default() }]
+//!     .into_iter()
+//!     .collect();
+//! let mut edited = PersistentModel::from(model);
+//! let before = edited.snapshot();
+//! edited.update_poi("poi:1", |poi| poi.name = "Gare".to_string());
+//! assert_eq!(edited.get_poi("poi:1").unwrap().name, "Gare");
+//! edited.restore(before);
+//! assert_eq!(edited.get_poi("poi:1").unwrap().name, "");
+//! ```
+
+use crate::{Metadata, Model, Poi, PoiRelation, PoiType, Result};
+use chrono::Utc;
+use im::{HashMap, OrdMap, OrdSet};
+
+/// A [Model] whose clones share their data, see the [module
+/// documentation](crate::persistent)
+#[derive(Debug, Clone, Default)]
+pub struct PersistentModel {
+    pois: OrdMap<String, Poi>,
+    poi_types: HashMap<String, PoiType>,
+    metadata: Option<Metadata>,
+    relations: OrdSet<PoiRelation>,
+
+    /// The relations of each POI, as parent or as child
+    relations_by_poi: OrdMap<String, OrdSet<PoiRelation>>,
+}
+
+/// The state of a model at some point, see [PersistentModel::snapshot]
+/// and [Model::snapshot]
+#[derive(Debug, Clone)]
+pub struct Snapshot(PersistentModel);
+
+impl PersistentModel {
+    /// Captures the current state of the model, in O(1), to
+    /// [restore](PersistentModel::restore) it later. The snapshot is not
+    /// affected by the later changes of the model.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(self.clone())
+    }
+
+    /// Restores the state captured by `snapshot`, in O(1), discarding the
+    /// changes made since. Returns the state that was discarded, so that
+    /// the restoration itself can be undone.
+    pub fn restore(&mut self, snapshot: Snapshot) -> Snapshot {
+        Snapshot(std::mem::replace(self, snapshot.0))
+    }
+
+    /// Returns the number of POIs
+    pub fn len(&self) -> usize {
+        self.pois.len()
+    }
+
+    /// Returns true if there is no POI
+    pub fn is_empty(&self) -> bool {
+        self.pois.is_empty()
+    }
+
+    /// Returns an iterator over the POIs, ordered by id
+    pub fn pois(&self) -> impl Iterator<Item = &Poi> {
+        self.pois.values()
+    }
+
+    /// Returns an iterator over the POI types, in no particular order
+    pub fn poi_types(&self) -> impl Iterator<Item = &PoiType> {
+        self.poi_types.values()
+    }
+
+    /// Returns an iterator over the relations, ordered by parent
+    pub fn relations(&self) -> impl Iterator<Item = &PoiRelation> {
+        self.relations.iter()
+    }
+
+    /// Returns an iterator over the relations of the POI with the given
+    /// id, as parent or as child, ordered by parent
+    pub fn relations_of(&self, id: &str) -> impl Iterator<Item = &PoiRelation> {
+        self.relations_by_poi.get(id).into_iter().flatten()
+    }
+
+    /// Returns the information about the dataset, if any
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Returns the POI with the given id
+    pub fn get_poi(&self, id: &str) -> Option<&Poi> {
+        self.pois.get(id)
+    }
+
+    /// Returns the POI type with the given id
+    pub fn get_poi_type(&self, id: &str) -> Option<&PoiType> {
+        self.poi_types.get(id)
+    }
+
+    /// Inserts `poi`, and returns the POI it replaces, if any
+    pub fn insert_poi(&mut self, poi: Poi) -> Option<Poi> {
+        self.pois.insert(poi.id.clone(), poi)
+    }
+
+    /// Inserts `poi_type`, and returns the POI type it replaces, if any
+    pub fn insert_poi_type(&mut self, poi_type: PoiType) -> Option<PoiType> {
+        self.poi_types.insert(poi_type.id.clone(), poi_type)
+    }
+
+    /// Applies `update` to the POI with the given id, copying only the
+    /// nodes leading to it, and returns true if there is such a POI. Its
+    /// `updated_at` is set to the current time, and its id is restored
+    /// after `update` if needed.
+    pub fn update_poi<F>(&mut self, id: &str, update: F) -> bool
+    where
+        F: FnOnce(&mut Poi),
+    {
+        match self.pois.get_mut(id) {
+            Some(poi) => {
+                update(poi);
+                if poi.id != id {
+                    poi.id = id.to_string();
+                }
+                poi.updated_at = Some(Utc::now());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the POI with the given id, along with its relations, and
+    /// returns it
+    pub fn remove_poi(&mut self, id: &str) -> Option<Poi> {
+        let poi = self.pois.remove(id)?;
+        for relation in self.relations_by_poi.remove(id).into_iter().flatten() {
+            self.relations.remove(&relation);
+            let other = if relation.parent_id == id {
+                &relation.child_id
+            } else {
+                &relation.parent_id
+            };
+            if let Some(relations) = self.relations_by_poi.get_mut(other) {
+                relations.remove(&relation);
+                if relations.is_empty() {
+                    self.relations_by_poi.remove(other);
+                }
+            }
+        }
+        Some(poi)
+    }
+
+    /// Links the POI `child_id` to its parent `parent_id`. Both POIs must be
+    /// in the model.
+    pub fn add_relation<T: Into<String>>(
+        &mut self,
+        parent_id: &str,
+        child_id: &str,
+        relation_type: T,
+    ) -> Result<()> {
+        for id in &[parent_id, child_id] {
+            if !self.pois.contains_key(*id) {
+                anyhow::bail!("cannot find poi '{}' for relation", id);
+            }
+        }
+        self.insert_relation(PoiRelation {
+            parent_id: parent_id.to_string(),
+            child_id: child_id.to_string(),
+            relation_type: relation_type.into(),
+        });
+        Ok(())
+    }
+
+    /// Adds `relation` to the relations and to their index
+    fn insert_relation(&mut self, relation: PoiRelation) {
+        for id in [&relation.parent_id, &relation.child_id] {
+            self.relations_by_poi
+                .entry(id.clone())
+                .or_default()
+                .insert(relation.clone());
+        }
+        self.relations.insert(relation);
+    }
+
+    /// Removes the POI type with the given id, and returns it. The POIs of
+    /// this type are kept.
+    pub fn remove_poi_type(&mut self, id: &str) -> Option<PoiType> {
+        self.poi_types.remove(id)
+    }
+}
+
+impl Model {
+    /// Captures the current state of the model, to [restore](Model::restore)
+    /// it later.
+    ///
+    /// The model is copied in persistent maps, which costs as much as a
+    /// clone: see the [module documentation](crate::persistent) to take
+    /// snapshots in O(1).
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(PersistentModel::from(self.clone()))
+    }
+
+    /// Restores the state captured by `snapshot`, discarding the changes
+    /// made since, in O(n). Returns the state that was discarded, so that
+    /// the restoration itself can be undone.
+    pub fn restore(&mut self, snapshot: Snapshot) -> Snapshot {
+        let discarded = std::mem::replace(self, Model::from(snapshot.0));
+        Snapshot(PersistentModel::from(discarded))
+    }
+}
+
+impl From<Model> for PersistentModel {
+    fn from(model: Model) -> PersistentModel {
+        let mut persistent = PersistentModel {
+            pois: model.pois.into_iter().collect(),
+            poi_types: model.poi_types.into_iter().collect(),
+            metadata: model.metadata,
+            ..PersistentModel::default()
+        };
+        for relation in model.relations {
+            persistent.insert_relation(relation);
+        }
+        persistent
+    }
+}
+
+impl From<PersistentModel> for Model {
+    fn from(model: PersistentModel) -> Model {
+        Model {
+            pois: model.pois.into_iter().collect(),
+            poi_types: model.poi_types.into_iter().collect(),
+            metadata: model.metadata,
+            relations: model.relations.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PersistentModel;
+    use crate::{Model, Poi, PoiRelation};
+
+    #[test]
+    fn remove_poi_removes_its_relations() {
+        let mut model: Model = ["station", "entrance", "campus"]
+            .iter()
+            .map(|id| Poi {
+                id: id.to_string(),
+                ..Poi::default()
+            })
+            .collect();
+        let relation = |parent: &str, child: &str, relation_type: &str| PoiRelation {
+            parent_id: parent.to_string(),
+            child_id: child.to_string(),
+            relation_type: relation_type.to_string(),
+        };
+        model
+            .relations
+            .insert(relation("station", "entrance", PoiRelation::ENTRANCE));
+        model
+            .relations
+            .insert(relation("campus", "station", PoiRelation::PART));
+        model
+            .relations
+            .insert(relation("campus", "entrance", PoiRelation::PART));
+        let mut model = PersistentModel::from(model);
+        let reference = model.clone();
+
+        assert!(model.remove_poi("station").is_some());
+        let relations: Vec<_> = model.relations().cloned().collect();
+        assert_eq!(
+            relations,
+            vec![relation("campus", "entrance", PoiRelation::PART)]
+        );
+        assert!(model.relations_of("station").next().is_none());
+        assert_eq!(model.relations_of("entrance").count(), 1);
+        assert_eq!(model.relations_of("campus").count(), 1);
+        assert_eq!(reference.relations().count(), 3);
+        assert_eq!(reference.relations_of("station").count(), 2);
+    }
+
+    #[test]
+    fn add_relation_indexes_it() {
+        let model: Model = ["station", "entrance"]
+            .iter()
+            .map(|id| Poi {
+                id: id.to_string(),
+                ..Poi::default()
+            })
+            .collect();
+        let mut model = PersistentModel::from(model);
+        assert!(model
+            .add_relation("station", "unknown", PoiRelation::ENTRANCE)
+            .is_err());
+        model
+            .add_relation("station", "entrance", PoiRelation::ENTRANCE)
+            .unwrap();
+        assert_eq!(model.relations().count(), 1);
+        assert_eq!(model.relations_of("entrance").count(), 1);
+        model.remove_poi("station");
+        assert_eq!(model.relations().count(), 0);
+        assert_eq!(model.relations_of("entrance").count(), 0);
+    }
+
+    #[test]
+    fn restore_a_snapshot_of_a_model() {
+        let mut model: Model = vec![Poi {
+            id: "poi:1".to_string(),
+            ..Poi::default()
+        }]
+        .into_iter()
+        .collect();
+        let original = model.clone();
+        let snapshot = model.snapshot();
+        model.pois.clear();
+        let discarded = model.restore(snapshot);
+        assert_eq!(model, original);
+        model.restore(discarded);
+        assert!(model.pois.is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! The canonical POI types of navitia
+//!
+//! Importers should use these constants rather than hard-coded ids, which
+//! are easy to get wrong. navitia exposes these types with the
+//! [NAVITIA_PREFIX], like `poi_type:amenity:townhall`: [canonical] accepts
+//! both forms.
+//!
+//! ```
+//! use navitia_poi_model::poi_types::well_known;
+//!
+//! assert_eq!(well_known::canonical("poi_type:amenity:parking"), Some(well_known::PARKING));
+//! assert_eq!(well_known::canonical("amenity:parkings"), None);
+//! assert_eq!(well_known::poi_type(well_known::TOWNHALL).unwrap().name, "Mairie");
+//! ```
+
+use crate::{Model, PoiType};
+
+/// Prefix of the ids of POI types in the navitia API
+pub const NAVITIA_PREFIX: &str = "poi_type:";
+
+/// Car parks
+pub const PARKING: &str = "amenity:parking";
+/// Bicycle parkings
+pub const BICYCLE_PARKING: &str = "amenity:bicycle_parking";
+/// Bike share stations
+pub const BICYCLE_RENTAL: &str = "amenity:bicycle_rental";
+/// Charging stations for electric vehicles
+pub const CHARGING_STATION: &str = "amenity:charging_station";
+/// Colleges and schools
+pub const COLLEGE: &str = "amenity:college";
+/// Universities
+pub const UNIVERSITY: &str = "amenity:university";
+/// Theatres
+pub const THEATRE: &str = "amenity:theatre";
+/// Hospitals
+pub const HOSPITAL: &str = "amenity:hospital";
+/// Post offices
+pub const POST_OFFICE: &str = "amenity:post_office";
+/// Police stations
+pub const POLICE: &str = "amenity:police";
+/// Town halls
+pub const TOWNHALL: &str = "amenity:townhall";
+/// Gardens
+pub const GARDEN: &str = "leisure:garden";
+/// Parks
+pub const PARK: &str = "leisure:park";
+
+/// The well-known POI types, as `(id, default name)` pairs. The names are
+/// those of the default configuration of navitia.
+pub const ALL: &[(&str, &str)] = &[
+    (PARKING, "Parking"),
+    (BICYCLE_PARKING, "Parking vélo"),
+    (BICYCLE_RENTAL, "Station VLS"),
+    (CHARGING_STATION, "Station de recharge"),
+    (COLLEGE, "École"),
+    (UNIVERSITY, "Université"),
+    (THEATRE, "Théâtre"),
+    (HOSPITAL, "Hôpital"),
+    (POST_OFFICE, "Bureau de poste"),
+    (POLICE, "Police, gendarmerie"),
+    (TOWNHALL, "Mairie"),
+    (GARDEN, "Jardin"),
+    (PARK, "Zone Parc. Zone verte ouverte, pour flâner"),
+];
+
+/// The well-known id matching `id`, with or without the [NAVITIA_PREFIX]
+pub fn canonical(id: &str) -> Option<&'static str> {
+    let id = id.strip_prefix(NAVITIA_PREFIX).unwrap_or(id);
+    ALL.iter()
+        .map(|(well_known, _)| *well_known)
+        .find(|well_known| *well_known == id)
+}
+
+/// Whether `id` is the id of a well-known type, with or without the
+/// [NAVITIA_PREFIX]
+pub fn is_well_known(id: &str) -> bool {
+    canonical(id).is_some()
+}
+
+/// `id` as exposed by navitia, with the [NAVITIA_PREFIX]
+pub fn navitia_id(id: &str) -> String {
+    if id.starts_with(NAVITIA_PREFIX) {
+        id.to_string()
+    } else {
+        format!("{}{}", NAVITIA_PREFIX, id)
+    }
+}
+
+/// The well-known POI type of id `id`, with its default name
+pub fn poi_type(id: &str) -> Option<PoiType> {
+    let id = id.strip_prefix(NAVITIA_PREFIX).unwrap_or(id);
+    ALL.iter()
+        .find(|(well_known, _)| *well_known == id)
+        .map(|(id, name)| PoiType {
+            id: id.to_string(),
+            name: name.to_string(),
+            ..PoiType::default()
+        })
+}
+
+impl Model {
+    /// Adds the well-known POI types missing from the model, with their
+    /// default names. Types already in the model are left untouched.
+    /// Returns the number of added types.
+    pub fn ensure_well_known_types(&mut self) -> usize {
+        let mut count = 0;
+        for (id, name) in ALL {
+            if !self.poi_types.contains_key(*id) {
+                let poi_type = PoiType {
+                    id: id.to_string(),
+                    name: name.to_string(),
+                    ..PoiType::default()
+                };
+                self.poi_types.insert(poi_type.id.clone(), poi_type);
+                count += 1;
+            }
+        }
+        count
+    }
+}
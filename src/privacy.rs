@@ -0,0 +1,161 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Pseudonymization of models, to share them with third parties
+//!
+//! [Model::pseudonymize] replaces the ids of the POIs by keyed hashes, the
+//! relations and the properties referencing POIs being updated accordingly,
+//! and removes the sensitive properties.
+//!
+//! ```
+//! use navitia_poi_model::privacy::{PseudonymizeOptions, Salt};
+//! use navitia_poi_model::{Model, Poi};
+//!
+//! # fn main() -> navitia_poi_model::Result<()> {
+//! let mut model = Model::default();
+//! let mut poi = Poi {
+//!     id: "poi:42".to_string(),
+//!     ..Poi::default()
+//! };
+//! poi.properties.insert("phone".to_string(), "+33 1 23 45 67 89".to_string());
+//! model.pois.insert(poi.id.clone(), poi);
+//!
+//! let options = PseudonymizeOptions {
+//!     sensitive_properties: vec!["phone".to_string()],
+//!     ..PseudonymizeOptions::default()
+//! };
+//! model.pseudonymize(&Salt::new("a secret"), &options)?;
+//! let poi = model.pois.values().next().unwrap();
+//! assert_ne!(poi.id, "poi:42");
+//! assert!(poi.properties.is_empty());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Model, PoiRelation, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+
+/// Secret mixed into the hashes of the ids, so that they cannot be computed
+/// back from the original ids without it. The same salt always gives the
+/// same pseudonyms, so that datasets shared at different times can be
+/// compared.
+#[derive(Clone)]
+pub struct Salt(Vec<u8>);
+
+impl Salt {
+    /// Builds a salt from a secret
+    pub fn new<S: Into<Vec<u8>>>(secret: S) -> Salt {
+        Salt(secret.into())
+    }
+
+    /// Returns the pseudonym of `id`, a truncated HMAC-SHA256 in hexadecimal
+    fn pseudonym(&self, prefix: &str, id: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC takes keys of any size");
+        mac.update(id.as_bytes());
+        let hash = mac.finalize().into_bytes();
+        let hex: String = hash[..16].iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}{}", prefix, hex)
+    }
+}
+
+// the secret is not printed
+impl std::fmt::Debug for Salt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Salt(..)")
+    }
+}
+
+/// Options of [Model::pseudonymize]
+#[derive(Debug, Clone)]
+pub struct PseudonymizeOptions {
+    /// Prefix of the pseudonyms, `poi:` by default
+    pub prefix: String,
+
+    /// Keys of the properties removed from the POIs, like phone numbers or
+    /// email addresses. None by default.
+    pub sensitive_properties: Vec<String>,
+
+    /// Keys of the properties whose values are ids of POIs, replaced by
+    /// their pseudonyms. None by default.
+    pub reference_properties: Vec<String>,
+}
+
+impl Default for PseudonymizeOptions {
+    fn default() -> PseudonymizeOptions {
+        PseudonymizeOptions {
+            prefix: "poi:".to_string(),
+            sensitive_properties: Vec::new(),
+            reference_properties: Vec::new(),
+        }
+    }
+}
+
+impl Model {
+    /// Replaces the ids of the POIs by their pseudonyms, keyed by `salt`,
+    /// in the POIs, their relations, and the properties listed in
+    /// [PseudonymizeOptions::reference_properties], and removes the
+    /// properties listed in [PseudonymizeOptions::sensitive_properties].
+    ///
+    /// Fails, leaving the model untouched, if two ids have the same
+    /// pseudonym, which is very unlikely.
+    pub fn pseudonymize(&mut self, salt: &Salt, options: &PseudonymizeOptions) -> Result<()> {
+        let pseudonyms: HashMap<String, String> = self
+            .pois
+            .keys()
+            .map(|id| (id.clone(), salt.pseudonym(&options.prefix, id)))
+            .collect();
+        let mut owners: HashMap<&str, &str> = HashMap::new();
+        for (id, pseudonym) in &pseudonyms {
+            if let Some(other) = owners.insert(pseudonym, id) {
+                anyhow::bail!("POIs with ids {} and {} have the same pseudonym", other, id);
+            }
+        }
+        // ids of POIs absent from the model are hashed as well, so that
+        // they do not leak
+        let pseudonym = |id: &str| {
+            pseudonyms
+                .get(id)
+                .cloned()
+                .unwrap_or_else(|| salt.pseudonym(&options.prefix, id))
+        };
+
+        let pois = std::mem::take(&mut self.pois);
+        self.pois = pois
+            .into_values()
+            .map(|mut poi| {
+                poi.id = pseudonym(&poi.id);
+                for key in &options.sensitive_properties {
+                    poi.properties.remove(key);
+                }
+                for key in &options.reference_properties {
+                    if let Some(value) = poi.properties.get_mut(key) {
+                        *value = pseudonym(value);
+                    }
+                }
+                (poi.id.clone(), poi)
+            })
+            .collect::<BTreeMap<_, _>>();
+        self.relations = std::mem::take(&mut self.relations)
+            .into_iter()
+            .map(|relation| PoiRelation {
+                parent_id: pseudonym(&relation.parent_id),
+                child_id: pseudonym(&relation.child_id),
+                ..relation
+            })
+            .collect();
+        Ok(())
+    }
+}
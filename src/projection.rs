@@ -0,0 +1,174 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Projection of WGS84 coordinates to metric coordinate systems
+//!
+//! Only a few systems, identified by their EPSG code, are supported: Web
+//! Mercator, Lambert-93 and the WGS84 UTM zones. They are written along
+//! with the coordinates of the POIs when
+//! [WriteOptions::projection](crate::io::WriteOptions::projection) is set.
+//!
+//! ```
+//! use navitia_poi_model::projection::Projection;
+//! use navitia_poi_model::Coord;
+//!
+//! let projection = Projection::from_epsg(3857).unwrap();
+//! let (x, y) = projection.project(&Coord::new(180., 0.)).unwrap();
+//! assert!((x - 20_037_508.342_789).abs() < 1e-3 && y.abs() < 1e-9);
+//! ```
+
+use crate::{Coord, Result};
+use anyhow::bail;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+/// Semi-major axis of the WGS84 and GRS80 ellipsoids, in meters
+const SEMI_MAJOR_AXIS: f64 = 6_378_137.;
+
+/// Flattening of the WGS84 ellipsoid
+const WGS84_FLATTENING: f64 = 1. / 298.257_223_563;
+
+/// Flattening of the GRS80 ellipsoid
+const GRS80_FLATTENING: f64 = 1. / 298.257_222_101;
+
+/// Latitude beyond which Web Mercator is not defined, in degrees
+const WEB_MERCATOR_MAX_LAT: f64 = 85.051_128_779_806_6;
+
+/// A metric coordinate system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    /// Web Mercator, EPSG:3857
+    WebMercator,
+
+    /// Lambert-93, the official projection of metropolitan France,
+    /// EPSG:2154
+    Lambert93,
+
+    /// A zone (1 to 60) of the WGS84 Universal Transverse Mercator, in the
+    /// northern hemisphere (EPSG:32601 to EPSG:32660) or in the southern one
+    /// (EPSG:32701 to EPSG:32760)
+    Utm {
+        /// Number of the zone
+        zone: u8,
+        /// True in the northern hemisphere
+        north: bool,
+    },
+}
+
+impl Projection {
+    /// The projection of EPSG code `epsg`, failing if it is not supported
+    pub fn from_epsg(epsg: u32) -> Result<Projection> {
+        match epsg {
+            3857 => Ok(Projection::WebMercator),
+            2154 => Ok(Projection::Lambert93),
+            32601..=32660 => Ok(Projection::Utm {
+                zone: (epsg - 32600) as u8,
+                north: true,
+            }),
+            32701..=32760 => Ok(Projection::Utm {
+                zone: (epsg - 32700) as u8,
+                north: false,
+            }),
+            _ => bail!("unsupported projection EPSG:{}", epsg),
+        }
+    }
+
+    /// EPSG code of the projection
+    pub fn epsg(self) -> u32 {
+        match self {
+            Projection::WebMercator => 3857,
+            Projection::Lambert93 => 2154,
+            Projection::Utm { zone, north: true } => 32600 + u32::from(zone),
+            Projection::Utm { zone, north: false } => 32700 + u32::from(zone),
+        }
+    }
+
+    /// The projected `(x, y)` of `coord`, in meters, or `None` if `coord`
+    /// is not [valid](Coord::is_valid), or beyond the latitudes covered by
+    /// Web Mercator
+    pub fn project(self, coord: &Coord) -> Option<(f64, f64)> {
+        if !coord.is_valid() {
+            return None;
+        }
+        let (lon, lat) = (coord.lon().to_radians(), coord.lat().to_radians());
+        match self {
+            Projection::WebMercator => {
+                if coord.lat().abs() > WEB_MERCATOR_MAX_LAT {
+                    return None;
+                }
+                let y = (FRAC_PI_4 + lat / 2.).tan().ln();
+                Some((SEMI_MAJOR_AXIS * lon, SEMI_MAJOR_AXIS * y))
+            }
+            Projection::Lambert93 => Some(lambert93(lon, lat)),
+            Projection::Utm { zone, north } => Some(utm(zone, north, lon, lat)),
+        }
+    }
+}
+
+/// Lambert conformal conic with two standard parallels (49° and 44°) on
+/// GRS80, centered on 46.5°N 3°E
+fn lambert93(lon: f64, lat: f64) -> (f64, f64) {
+    let e = (GRS80_FLATTENING * (2. - GRS80_FLATTENING)).sqrt();
+    let m = |phi: f64| phi.cos() / (1. - (e * phi.sin()).powi(2)).sqrt();
+    let t = |phi: f64| {
+        let e_sin = e * phi.sin();
+        (FRAC_PI_4 - phi / 2.).tan() / ((1. - e_sin) / (1. + e_sin)).powf(e / 2.)
+    };
+    let (lat0, lat1, lat2) = (46.5f64.to_radians(), 49f64.to_radians(), 44f64.to_radians());
+    let n = (m(lat1).ln() - m(lat2).ln()) / (t(lat1).ln() - t(lat2).ln());
+    let f = m(lat1) / (n * t(lat1).powf(n));
+    let rho = |phi: f64| {
+        if (phi.abs() - FRAC_PI_2).abs() < f64::EPSILON {
+            0.
+        } else {
+            SEMI_MAJOR_AXIS * f * t(phi).powf(n)
+        }
+    };
+    let theta = n * (lon - 3f64.to_radians());
+    let x = 700_000. + rho(lat) * theta.sin();
+    let y = 6_600_000. + rho(lat0) - rho(lat) * theta.cos();
+    (x, y)
+}
+
+/// Transverse Mercator of the UTM `zone` on WGS84, with the series of
+/// Snyder, Map Projections: A Working Manual, p. 61
+fn utm(zone: u8, north: bool, lon: f64, lat: f64) -> (f64, f64) {
+    let k0 = 0.9996;
+    let e2 = WGS84_FLATTENING * (2. - WGS84_FLATTENING);
+    let (e4, e6) = (e2 * e2, e2 * e2 * e2);
+    let ep2 = e2 / (1. - e2);
+    let lon0 = (f64::from(zone) * 6. - 183.).to_radians();
+
+    let n = SEMI_MAJOR_AXIS / (1. - e2 * lat.sin().powi(2)).sqrt();
+    let t = lat.tan().powi(2);
+    let c = ep2 * lat.cos().powi(2);
+    let a = lat.cos() * (lon - lon0);
+    let m = SEMI_MAJOR_AXIS
+        * ((1. - e2 / 4. - 3. * e4 / 64. - 5. * e6 / 256.) * lat
+            - (3. * e2 / 8. + 3. * e4 / 32. + 45. * e6 / 1024.) * (2. * lat).sin()
+            + (15. * e4 / 256. + 45. * e6 / 1024.) * (4. * lat).sin()
+            - (35. * e6 / 3072.) * (6. * lat).sin());
+
+    let x = 500_000.
+        + k0 * n
+            * (a + (1. - t + c) * a.powi(3) / 6.
+                + (5. - 18. * t + t * t + 72. * c - 58. * ep2) * a.powi(5) / 120.);
+    let y = k0
+        * (m + n
+            * lat.tan()
+            * (a * a / 2.
+                + (5. - t + 9. * c + 4. * c * c) * a.powi(4) / 24.
+                + (61. - 58. * t + t * t + 600. * c - 330. * ep2) * a.powi(6) / 720.));
+    let false_northing = if north { 0. } else { 10_000_000. };
+    (x, y + false_northing)
+}
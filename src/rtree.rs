@@ -0,0 +1,55 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+//! Implementations of [rstar]'s traits for [Poi], so that POIs can be
+//! stored as is in an [rstar::RTree]
+//!
+//! POIs are points at their `[lon, lat]` coordinates, and distances are
+//! planar distances in degrees, like in [geo] algorithms, not geodesic
+//! distances. POIs whose coordinates are not [finite](crate::Coord::is_finite)
+//! must be left out, NaN breaking the ordering of the tree.
+//!
+//! ```
+//! use navitia_poi_model::{Coord, Poi};
+//!
+//! let pois = vec![
+//!     Poi { id: "a".into(), coord: Coord::new(2.35, 48.85), ..Poi::default() },
+//!     Poi { id: "b".into(), coord: Coord::new(4.83, 45.76), ..Poi::default() },
+//! ];
+//! let tree = rstar::RTree::bulk_load(pois);
+//! let nearest = tree.nearest_neighbor(&[2.3, 48.8]).unwrap();
+//! assert_eq!(nearest.id, "a");
+//! ```
+
+use crate::Poi;
+use rstar::{PointDistance, RTreeObject, AABB};
+
+impl RTreeObject for Poi {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_point([self.coord.lon(), self.coord.lat()])
+    }
+}
+
+impl PointDistance for Poi {
+    fn distance_2(&self, point: &[f64; 2]) -> f64 {
+        let dx = self.coord.lon() - point[0];
+        let dy = self.coord.lat() - point[1];
+        dx * dx + dy * dy
+    }
+
+    fn contains_point(&self, point: &[f64; 2]) -> bool {
+        self.coord.lon() == point[0] && self.coord.lat() == point[1]
+    }
+}
@@ -0,0 +1,542 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Search of POIs by name, and ranking of the results
+//!
+//! [Model::rank] blends the text score of the candidates of a search with
+//! their weight and their distance to a focus point, so that all the
+//! consumers order the POIs the same way.
+
+use crate::normalize::{normalize_name, phonetic_key, tokenize};
+use crate::trace::Traced;
+use crate::{Coord, Model, Poi};
+use geo::prelude::HaversineDistance;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+
+/// Boost of the terms found in the name of a POI, relative to those found
+/// in its properties
+const NAME_BOOST: f64 = 2.;
+
+/// Factor applied to `ln(1 + weight)` before adding it to the text score
+const WEIGHT_FACTOR: f64 = 0.1;
+
+/// Coefficients of the components of the score given by [Model::rank].
+/// Each component is between 0 and 1, so the coefficients give their
+/// relative importance.
+#[derive(Debug, Clone)]
+pub struct RankConfig {
+    /// Coefficient of the text score of the candidates, divided by the best
+    /// one. 1 by default.
+    pub text: f64,
+
+    /// Coefficient of the weight of the POIs, as `ln(1 + weight)` divided
+    /// by the best one among the candidates. 0.3 by default.
+    pub weight: f64,
+
+    /// Coefficient of the proximity of the POIs to `focus`, as
+    /// `1 / (1 + distance / distance_scale_m)`. 1 by default, and unused
+    /// without a focus.
+    pub distance: f64,
+
+    /// Point near which the POIs are preferred, like the position of the
+    /// user. None by default.
+    pub focus: Option<Coord>,
+
+    /// Distance, in meters, at which the proximity is halved. 1 km by
+    /// default.
+    pub distance_scale_m: f64,
+}
+
+impl Default for RankConfig {
+    fn default() -> RankConfig {
+        RankConfig {
+            text: 1.,
+            weight: 0.3,
+            distance: 1.,
+            focus: None,
+            distance_scale_m: 1_000.,
+        }
+    }
+}
+
+/// Restrictions applied to a name search
+#[derive(Debug, Clone, Default)]
+pub struct NameSearchOptions {
+    /// Only search POIs of this type
+    pub poi_type_id: Option<String>,
+
+    /// Only search POIs in this bounding box
+    pub bbox: Option<geo::Rect<f64>>,
+
+    /// Minimum score (between 0 and 1) of the returned POIs
+    pub min_score: f64,
+}
+
+impl Model {
+    /// Returns at most `limit` POIs whose name is close to `query`, along
+    /// with their score, best matches first.
+    ///
+    /// See [Model::search_name_with_options].
+    pub fn search_name(&self, query: &str, limit: usize) -> Vec<(&Poi, f64)> {
+        self.search_name_with_options(query, limit, &NameSearchOptions::default())
+    }
+
+    /// Returns at most `limit` POIs whose name is close to `query`, along
+    /// with their score, best matches first.
+    ///
+    /// The score is the similarity of the trigrams of the normalized names
+    /// (see [normalize](crate::normalize)), between 0 (nothing in common)
+    /// and 1 (same trigrams). Equal scores are ordered by decreasing weight.
+    pub fn search_name_with_options(
+        &self,
+        query: &str,
+        limit: usize,
+        options: &NameSearchOptions,
+    ) -> Vec<(&Poi, f64)> {
+        let query = trigrams(&normalize_name(query));
+        let mut results: Vec<(&Poi, f64)> = self
+            .pois
+            .values()
+            .filter(|poi| {
+                options
+                    .poi_type_id
+                    .as_ref()
+                    .is_none_or(|id| &poi.poi_type_id == id)
+            })
+            .filter(|poi| options.bbox.is_none_or(|bbox| poi.coord.is_in(&bbox)))
+            .map(|poi| (poi, similarity(&query, &trigrams(&poi.normalized_name()))))
+            .filter(|(_, score)| *score > 0. && *score >= options.min_score)
+            .collect();
+        results.sort_by(|(lpoi, lscore), (rpoi, rscore)| {
+            rscore
+                .partial_cmp(lscore)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| rpoi.weight.value().total_cmp(&lpoi.weight.value()))
+        });
+        results.truncate(limit);
+        results
+    }
+}
+
+/// An inverted index on the names and property values of the POIs of a
+/// model, built with [Model::build_text_index].
+///
+/// The index owns its data, so the model can be modified afterwards, but
+/// the index is then out of date.
+#[derive(Debug, Clone, Default)]
+pub struct TextIndex {
+    /// Ids and weights of the indexed POIs
+    docs: Vec<(String, f64)>,
+
+    /// For each token, the documents containing it, along with the score
+    /// they get for it
+    postings: HashMap<String, Vec<(usize, f64)>>,
+}
+
+impl TextIndex {
+    /// Number of indexed POIs
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// Returns true if no POI is indexed
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Returns the ids of at most `limit` POIs matching at least one of the
+    /// terms of `query`, along with their score, best matches first.
+    ///
+    /// Each matching term scores its inverse document frequency, doubled
+    /// when found in the name of the POI. The weight of the POI is then
+    /// added as `0.1 * ln(1 + weight)`.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(&str, f64)> {
+        let n = self.docs.len() as f64;
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+        let mut tokens = tokenize(query).collect::<Vec<_>>();
+        tokens.sort_unstable();
+        tokens.dedup();
+        for token in tokens {
+            if let Some(postings) = self.postings.get(&token) {
+                let idf = (1. + n / postings.len() as f64).ln();
+                for (doc, boost) in postings {
+                    *scores.entry(*doc).or_default() += idf * boost;
+                }
+            }
+        }
+        let mut results: Vec<(&str, f64)> = scores
+            .into_iter()
+            .map(|(doc, score)| {
+                let (id, weight) = &self.docs[doc];
+                let score = score + WEIGHT_FACTOR * (1. + weight).ln();
+                (id.as_str(), score)
+            })
+            .collect();
+        results.sort_by(|(lid, lscore), (rid, rscore)| {
+            rscore
+                .partial_cmp(lscore)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| lid.cmp(rid))
+        });
+        results.truncate(limit);
+        results
+    }
+}
+
+impl Model {
+    /// Builds a full-text index on the names and property values of the
+    /// POIs. Texts are split into normalized words (see
+    /// [normalize](crate::normalize)), the queries as well.
+    pub fn build_text_index(&self) -> TextIndex {
+        let traced = Traced::start("build_text_index", "");
+        let mut index = TextIndex::default();
+        for poi in self.pois.values() {
+            let doc = index.docs.len();
+            index.docs.push((poi.id.clone(), poi.weight.value()));
+            let mut boosts: HashMap<String, f64> = HashMap::new();
+            for token in tokenize(&poi.name) {
+                boosts.insert(token, NAME_BOOST);
+            }
+            for token in poi.properties.values().flat_map(|v| tokenize(v)) {
+                boosts.entry(token).or_insert(1.);
+            }
+            for (token, boost) in boosts {
+                index.postings.entry(token).or_default().push((doc, boost));
+            }
+        }
+        traced.finish(index.docs.len());
+        index
+    }
+}
+
+impl Model {
+    /// Returns the POIs whose name sounds like `query` (see
+    /// [phonetic_key]), heaviest first, then ordered by id. This finds the
+    /// POIs whose name is misspelled, or spelled differently than the query.
+    pub fn find_phonetic_matches(&self, query: &str) -> Vec<&Poi> {
+        let key = phonetic_key(query);
+        if key.is_empty() {
+            return Vec::new();
+        }
+        let mut matches: Vec<&Poi> = self
+            .pois
+            .values()
+            .filter(|poi| phonetic_key(&poi.name) == key)
+            .collect();
+        matches.sort_by(|lhs, rhs| {
+            rhs.weight
+                .value()
+                .total_cmp(&lhs.weight.value())
+                .then_with(|| lhs.id.cmp(&rhs.id))
+        });
+        matches
+    }
+
+    /// Ranks the candidates of a search, given as the id of a POI and its
+    /// text score (like the results of [TextIndex::search], or 0 for a
+    /// search without text), by decreasing blended score (see
+    /// [RankConfig]). Equal scores are ordered by id. The ids that are not
+    /// in the model are skipped.
+    ///
+    /// ```
+    /// use navitia_poi_model::search::RankConfig;
+    /// use navitia_poi_model::{Coord, Model, Poi, Weight};
+    ///
+    /// let mut model = Model::default();
+    /// for (id, lon, weight) in [("near", 2.35, 0.), ("far", 2.45, 10.)] {
+    ///     let poi = Poi {
+    ///         id: id.to_string(),
+    ///         coord: Coord::new(lon, 48.85),
+    ///         weight: Weight(weight),
+    ///         ..Poi::default()
+    ///     };
+    ///     model.pois.insert(poi.id.clone(), poi);
+    /// }
+    /// let config = RankConfig {
+    ///     focus: Some(Coord::new(2.35, 48.85)),
+    ///     ..RankConfig::default()
+    /// };
+    /// let ranked = model.rank(vec![("far", 1.), ("near", 1.)], &config);
+    /// assert_eq!(ranked[0].0.id, "near");
+    /// ```
+    pub fn rank<I, S>(&self, candidates: I, config: &RankConfig) -> Vec<(&Poi, f64)>
+    where
+        I: IntoIterator<Item = (S, f64)>,
+        S: AsRef<str>,
+    {
+        let candidates: Vec<(&Poi, f64)> = candidates
+            .into_iter()
+            .filter_map(|(id, score)| self.pois.get(id.as_ref()).map(|poi| (poi, score)))
+            .collect();
+        let weight = |poi: &Poi| poi.weight.value().max(0.).ln_1p();
+        let best = |values: &mut dyn Iterator<Item = f64>| values.fold(0., f64::max);
+        let best_text = best(&mut candidates.iter().map(|(_, score)| *score));
+        let best_weight = best(&mut candidates.iter().map(|(poi, _)| weight(poi)));
+        let ratio = |value: f64, best: f64| if best > 0. { value / best } else { 0. };
+        let focus = config
+            .focus
+            .as_ref()
+            .filter(|focus| focus.is_valid())
+            .map(geo::Point::from);
+
+        let mut ranked: Vec<(&Poi, f64)> = candidates
+            .into_iter()
+            .map(|(poi, text)| {
+                let proximity = match focus {
+                    Some(focus) if poi.coord.is_valid() => {
+                        let distance = focus.haversine_distance(&geo::Point::from(&poi.coord));
+                        1. / (1. + distance / config.distance_scale_m)
+                    }
+                    _ => 0.,
+                };
+                let score = config.text * ratio(text, best_text)
+                    + config.weight * ratio(weight(poi), best_weight)
+                    + config.distance * proximity;
+                (poi, score)
+            })
+            .collect();
+        ranked.sort_by(|(lpoi, lscore), (rpoi, rscore)| {
+            rscore.total_cmp(lscore).then_with(|| lpoi.id.cmp(&rpoi.id))
+        });
+        ranked
+    }
+}
+
+/// An index of the names of the POIs of a model for prefix queries, like
+/// an autocompletion, built with [Model::build_prefix_index].
+///
+/// The names are normalized (see [normalize](crate::normalize)), so that
+/// queries ignore the case and the accents. Each name is indexed from each
+/// of its words, so that `lyon` finds `Gare de Lyon`. Like [TextIndex],
+/// the index owns its data.
+#[derive(Debug, Clone)]
+pub struct PrefixIndex {
+    /// Ids and weights of the indexed POIs, heaviest first then by id, so
+    /// that the lower the index of a document, the better its rank
+    docs: Vec<(String, f64)>,
+
+    /// Nodes of the trie, the root being the first one
+    nodes: Vec<TrieNode>,
+}
+
+/// A node of the trie of a [PrefixIndex]
+#[derive(Debug, Clone, Default)]
+struct TrieNode {
+    /// Children, ordered by character
+    children: Vec<(char, usize)>,
+
+    /// Documents whose indexed text ends at this node
+    docs: Vec<usize>,
+
+    /// The best documents of the subtree of this node, at most [TOP_DOCS]
+    /// of them, best first
+    top: Vec<usize>,
+}
+
+/// Number of best documents kept at each node of a [PrefixIndex], so that
+/// searching at most as many results does not go through the subtree
+const TOP_DOCS: usize = 10;
+
+impl PrefixIndex {
+    /// Number of indexed POIs
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// Returns true if no POI is indexed
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Returns the ids of at most `limit` POIs whose name, or the end of
+    /// their name from one of its words, starts with `prefix`, along with
+    /// their weight, heaviest first. Equal weights are ordered by id.
+    pub fn search(&self, prefix: &str, limit: usize) -> Vec<(&str, f64)> {
+        let prefix = normalize_name(prefix);
+        let mut node = 0;
+        for c in prefix.chars() {
+            match self.child(node, c) {
+                Some(child) => node = child,
+                None => return Vec::new(),
+            }
+        }
+        let docs: Vec<usize> = if limit <= TOP_DOCS {
+            self.nodes[node].top.iter().copied().take(limit).collect()
+        } else {
+            // the best documents of the subtree, never more than `limit`
+            let mut best = BTreeSet::new();
+            let mut stack = vec![node];
+            while let Some(node) = stack.pop() {
+                let node = &self.nodes[node];
+                for &doc in &node.docs {
+                    best.insert(doc);
+                    if best.len() > limit {
+                        best.pop_last();
+                    }
+                }
+                stack.extend(node.children.iter().map(|(_, child)| *child));
+            }
+            best.into_iter().collect()
+        };
+        docs.into_iter()
+            .map(|doc| (self.docs[doc].0.as_str(), self.docs[doc].1))
+            .collect()
+    }
+
+    fn child(&self, node: usize, c: char) -> Option<usize> {
+        let children = &self.nodes[node].children;
+        children
+            .binary_search_by_key(&c, |(c, _)| *c)
+            .ok()
+            .map(|index| children[index].1)
+    }
+
+    /// Adds `text` to the trie, ending at the node of `doc`
+    fn insert(&mut self, text: &str, doc: usize) {
+        let mut node = 0;
+        for c in text.chars() {
+            node = match self.nodes[node]
+                .children
+                .binary_search_by_key(&c, |(c, _)| *c)
+            {
+                Ok(index) => self.nodes[node].children[index].1,
+                Err(index) => {
+                    let child = self.nodes.len();
+                    self.nodes.push(TrieNode::default());
+                    self.nodes[node].children.insert(index, (c, child));
+                    child
+                }
+            };
+        }
+        self.nodes[node].docs.push(doc);
+    }
+
+    /// Fills the best documents of every node. A child is always pushed
+    /// after its parent, so going backward fills the children first.
+    fn fill_top_docs(&mut self) {
+        for node in (0..self.nodes.len()).rev() {
+            let mut top = self.nodes[node].docs.clone();
+            for (_, child) in &self.nodes[node].children {
+                top.extend_from_slice(&self.nodes[*child].top);
+            }
+            top.sort_unstable();
+            top.dedup();
+            top.truncate(TOP_DOCS);
+            self.nodes[node].top = top;
+        }
+    }
+}
+
+impl Model {
+    /// Builds an index of the names of the POIs for prefix queries
+    pub fn build_prefix_index(&self) -> PrefixIndex {
+        let traced = Traced::start("build_prefix_index", "");
+        let mut index = PrefixIndex {
+            docs: Vec::with_capacity(self.pois.len()),
+            nodes: vec![TrieNode::default()],
+        };
+        let mut pois: Vec<&Poi> = self.pois.values().collect();
+        pois.sort_by(|lhs, rhs| {
+            rhs.weight
+                .value()
+                .total_cmp(&lhs.weight.value())
+                .then_with(|| lhs.id.cmp(&rhs.id))
+        });
+        for poi in pois {
+            let doc = index.docs.len();
+            index.docs.push((poi.id.clone(), poi.weight.value()));
+            let name = poi.normalized_name();
+            let starts = std::iter::once(0).chain(name.match_indices(' ').map(|(i, _)| i + 1));
+            for start in starts {
+                index.insert(&name[start..], doc);
+            }
+        }
+        index.fill_top_docs();
+        traced.finish(index.docs.len());
+        index
+    }
+}
+
+/// Sorted and deduplicated trigrams of a lowercased text, padded with spaces
+pub(crate) fn trigrams(text: &str) -> Vec<[char; 3]> {
+    let chars: Vec<char> = std::iter::repeat_n(' ', 2)
+        .chain(text.to_lowercase().chars())
+        .chain(std::iter::once(' '))
+        .collect();
+    let mut trigrams: Vec<[char; 3]> = chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect();
+    trigrams.sort_unstable();
+    trigrams.dedup();
+    trigrams
+}
+
+/// Dice coefficient of two sorted sets of trigrams
+pub(crate) fn similarity(lhs: &[[char; 3]], rhs: &[[char; 3]]) -> f64 {
+    if lhs.is_empty() && rhs.is_empty() {
+        return 0.;
+    }
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < lhs.len() && j < rhs.len() {
+        match lhs[i].cmp(&rhs[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                common += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    2. * common as f64 / (lhs.len() + rhs.len()) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Model, Poi, Weight};
+
+    #[test]
+    fn prefix_search_keeps_the_heaviest() {
+        let model: Model = (0..100)
+            .map(|i| Poi {
+                id: format!("poi:{:03}", i),
+                name: format!("Gare {}", i % 7),
+                weight: Weight((i % 13) as f64),
+                ..Poi::default()
+            })
+            .collect();
+        let index = model.build_prefix_index();
+        // the subtree is walked for more results than kept at each node
+        let all = index.search("ga", 100);
+        let mut expected: Vec<(&str, f64)> = model
+            .pois
+            .values()
+            .map(|poi| (poi.id.as_str(), poi.weight.0))
+            .collect();
+        expected.sort_by(|(lid, lweight), (rid, rweight)| {
+            rweight.total_cmp(lweight).then_with(|| lid.cmp(rid))
+        });
+        assert_eq!(all, expected);
+        for limit in 0..=20 {
+            assert_eq!(index.search("ga", limit), &expected[..limit]);
+            let expected: Vec<_> = expected
+                .iter()
+                .filter(|(id, _)| model.pois[*id].name == "Gare 3")
+                .take(limit)
+                .copied()
+                .collect();
+            assert_eq!(index.search("gare 3", limit), expected);
+            assert_eq!(index.search("3", limit), expected);
+        }
+    }
+}
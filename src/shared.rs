@@ -0,0 +1,124 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+//! Sharing of a model between threads
+//!
+//! A [SharedModel] holds a [FrozenModel]: an immutable model along with its
+//! indexes. Readers get the current [FrozenModel] and keep it for as long
+//! as they need it, without holding any lock, while a reload builds the new
+//! model and its indexes before swapping them in.
+//!
+//! ```no_run
+//! # fn main() -> navitia_poi_model::Result<()> {
+//! use navitia_poi_model::shared::SharedModel;
+//!
+//! let shared = SharedModel::try_from_path("pois.poi")?;
+//! let reader = shared.clone();
+//! std::thread::spawn(move || {
+//!     let model = reader.load();
+//!     let _ = model.text_index().search("gare", 10);
+//! });
+//! shared.reload_from_path("pois.poi")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::search::TextIndex;
+use crate::{Model, Result};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// An immutable model, along with its indexes
+#[derive(Debug, Default)]
+pub struct FrozenModel {
+    model: Model,
+    text_index: TextIndex,
+}
+
+impl FrozenModel {
+    /// Freezes the model and builds its indexes
+    pub fn new(model: Model) -> FrozenModel {
+        let text_index = model.build_text_index();
+        FrozenModel { model, text_index }
+    }
+
+    /// Returns the model
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Returns the full-text index of the model
+    pub fn text_index(&self) -> &TextIndex {
+        &self.text_index
+    }
+
+    /// Unfreezes the model, dropping its indexes
+    pub fn into_model(self) -> Model {
+        self.model
+    }
+}
+
+impl std::ops::Deref for FrozenModel {
+    type Target = Model;
+    fn deref(&self) -> &Model {
+        &self.model
+    }
+}
+
+/// A model shared between threads, which can be replaced while it is read
+///
+/// Cloning a [SharedModel] is cheap, and all the clones share the same
+/// model.
+#[derive(Debug, Clone, Default)]
+pub struct SharedModel {
+    current: Arc<RwLock<Arc<FrozenModel>>>,
+}
+
+impl SharedModel {
+    /// Shares the model, after building its indexes
+    pub fn new(model: Model) -> SharedModel {
+        SharedModel {
+            current: Arc::new(RwLock::new(Arc::new(FrozenModel::new(model)))),
+        }
+    }
+
+    /// Loads and shares the model of a .poi archive
+    pub fn try_from_path<P: AsRef<Path>>(path: P) -> Result<SharedModel> {
+        Model::try_from_path(path).map(SharedModel::new)
+    }
+
+    /// Returns the current model. The lock is only held while the model is
+    /// fetched, and the returned model is not affected by later reloads.
+    pub fn load(&self) -> Arc<FrozenModel> {
+        // the lock only protects the swap of an Arc, so a poisoned lock
+        // still holds a consistent value
+        let current = self.current.read().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(&current)
+    }
+
+    /// Replaces the model and returns the previous one. The indexes of the
+    /// new model are built before the lock is taken.
+    pub fn replace(&self, model: Model) -> Arc<FrozenModel> {
+        let frozen = Arc::new(FrozenModel::new(model));
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut *current, frozen)
+    }
+
+    /// Reloads the model from a .poi archive. If the archive cannot be
+    /// loaded, the current model is kept and the error is returned.
+    pub fn reload_from_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let model = Model::try_from_path(path)?;
+        self.replace(model);
+        Ok(())
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Pyramid of tiles of POIs, for the display of POIs at every zoom level
+//!
+//! Tiles follow the XYZ scheme of Web Mercator: the tile `z/x/y` is the
+//! tile of column `x` and row `y` (from the north) at zoom `z`. Each tile
+//! keeps only the POIs of highest weight, so that low zoom levels show the
+//! most important POIs, and more of them appear as the zoom increases.
+//!
+//! The pyramid is built as a quadtree, from the highest zoom level: the
+//! POIs kept in a tile are chosen among those kept in its four children.
+
+use crate::trace::Traced;
+use crate::{Coord, Model, Poi, Weight};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+/// Highest zoom level of a pyramid, so that tile indexes fit in a `u32`
+pub const MAX_ZOOM: u8 = 30;
+
+/// A tile of the pyramid, ordered by zoom level, then column and row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TileId {
+    /// Zoom level
+    pub z: u8,
+    /// Column, from the west
+    pub x: u32,
+    /// Row, from the north
+    pub y: u32,
+}
+
+impl TileId {
+    /// The tile of zoom `z` containing `coord`, if any: coordinates beyond
+    /// the latitudes covered by Web Mercator (about 85.05°) are in no tile.
+    pub fn containing(coord: &Coord, z: u8) -> Option<TileId> {
+        let (x, y) = project(coord, z);
+        let n = 2f64.powi(i32::from(z));
+        // the antimeridian at 180° belongs to the last column
+        let x = x.min(n - 1.);
+        if x.is_nan() || y.is_nan() || x < 0. || y < 0. || y >= n {
+            return None;
+        }
+        Some(TileId {
+            z,
+            x: x as u32,
+            y: y as u32,
+        })
+    }
+
+    /// The tile of the lower zoom level containing this one, if any
+    pub fn parent(&self) -> Option<TileId> {
+        Some(TileId {
+            z: self.z.checked_sub(1)?,
+            x: self.x / 2,
+            y: self.y / 2,
+        })
+    }
+}
+
+impl std::fmt::Display for TileId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}/{}", self.z, self.x, self.y)
+    }
+}
+
+/// Options used to build a [Pyramid]
+#[derive(Debug, Clone)]
+pub struct PyramidOptions {
+    /// Lowest zoom level of the pyramid, 0 by default
+    pub min_zoom: u8,
+
+    /// Highest zoom level of the pyramid, 14 by default. It is capped to
+    /// [MAX_ZOOM].
+    pub max_zoom: u8,
+
+    /// Maximum number of POIs kept in a tile, 100 by default
+    pub max_pois_per_tile: usize,
+}
+
+impl Default for PyramidOptions {
+    fn default() -> PyramidOptions {
+        PyramidOptions {
+            min_zoom: 0,
+            max_zoom: 14,
+            max_pois_per_tile: 100,
+        }
+    }
+}
+
+/// The POIs of each tile of a range of zoom levels, see [Model::tile_pyramid]
+#[derive(Debug, Clone, Default)]
+pub struct Pyramid<'a> {
+    tiles: BTreeMap<TileId, Vec<&'a Poi>>,
+}
+
+impl<'a> Pyramid<'a> {
+    /// The non-empty tiles, ordered by zoom level, then column and row
+    pub fn tiles(&self) -> impl Iterator<Item = TileId> + '_ {
+        self.tiles.keys().copied()
+    }
+
+    /// The POIs kept in `tile`, by decreasing weight, then by id. Empty if
+    /// the tile is not part of the pyramid.
+    pub fn pois(&self, tile: TileId) -> &[&'a Poi] {
+        self.tiles.get(&tile).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Encodes the POIs of `tile` as a GeoJSON feature collection of
+    /// points, with the properties `id`, `name`, `poi_type`, `weight` and
+    /// `visible`
+    pub fn to_geojson(&self, tile: TileId) -> String {
+        let collection = FeatureCollection {
+            kind: "FeatureCollection",
+            features: self
+                .pois(tile)
+                .iter()
+                .map(|poi| Feature {
+                    kind: "Feature",
+                    geometry: Geometry {
+                        kind: "Point",
+                        coordinates: [poi.coord.lon(), poi.coord.lat()],
+                    },
+                    properties: Properties {
+                        id: &poi.id,
+                        name: &poi.name,
+                        poi_type: &poi.poi_type_id,
+                        weight: poi.weight,
+                        visible: poi.visible,
+                    },
+                })
+                .collect(),
+        };
+        serde_json::to_string(&collection).expect("GeoJSON tiles are always serializable")
+    }
+
+    /// Encodes the POIs of `tile` into a vector tile. The POIs are those of
+    /// the pyramid, the `min_weights` of the options are not applied.
+    #[cfg(feature = "mvt")]
+    pub fn to_mvt(&self, tile: TileId, options: &crate::mvt::MvtOptions) -> Vec<u8> {
+        crate::mvt::encode_tile(
+            self.pois(tile).iter().copied(),
+            tile.z,
+            tile.x,
+            tile.y,
+            options,
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct FeatureCollection<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    features: Vec<Feature<'a>>,
+}
+
+#[derive(Serialize)]
+struct Feature<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    geometry: Geometry,
+    properties: Properties<'a>,
+}
+
+#[derive(Serialize)]
+struct Geometry {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    coordinates: [f64; 2],
+}
+
+#[derive(Serialize)]
+struct Properties<'a> {
+    id: &'a str,
+    name: &'a str,
+    poi_type: &'a str,
+    weight: Weight,
+    visible: bool,
+}
+
+impl Model {
+    /// Builds the pyramid of tiles of the POIs, keeping in each tile the
+    /// `max_pois_per_tile` POIs of highest weight, ties being broken by id.
+    ///
+    /// POIs with invalid coordinates, or beyond the latitudes covered by
+    /// Web Mercator, are left out. Empty tiles are not part of the pyramid.
+    pub fn tile_pyramid(&self, options: &PyramidOptions) -> Pyramid<'_> {
+        let traced = Traced::start("tile_pyramid", "");
+        let max_zoom = options.max_zoom.min(MAX_ZOOM);
+        let mut level: BTreeMap<TileId, Vec<&Poi>> = BTreeMap::new();
+        for poi in self.pois.values().filter(|poi| poi.coord.is_valid()) {
+            if let Some(tile) = TileId::containing(&poi.coord, max_zoom) {
+                level.entry(tile).or_default().push(poi);
+            }
+        }
+        let mut tiles = BTreeMap::new();
+        for z in (options.min_zoom..=max_zoom).rev() {
+            if z < max_zoom {
+                let mut parents: BTreeMap<TileId, Vec<&Poi>> = BTreeMap::new();
+                for (tile, pois) in level {
+                    if let Some(parent) = tile.parent() {
+                        parents.entry(parent).or_default().extend(pois);
+                    }
+                }
+                level = parents;
+            }
+            for pois in level.values_mut() {
+                pois.sort_by(|lhs, rhs| {
+                    rhs.weight
+                        .value()
+                        .total_cmp(&lhs.weight.value())
+                        .then_with(|| lhs.id.cmp(&rhs.id))
+                });
+                pois.truncate(options.max_pois_per_tile);
+            }
+            tiles.extend(level.iter().map(|(tile, pois)| (*tile, pois.clone())));
+        }
+        traced.finish(tiles.len());
+        Pyramid { tiles }
+    }
+}
+
+/// Projects a coordinate in Web Mercator, in tile units at zoom `z`
+pub(crate) fn project(coord: &Coord, z: u8) -> (f64, f64) {
+    let n = 2f64.powi(i32::from(z));
+    let lat = coord.lat().to_radians();
+    let x = (coord.lon() + 180.) / 360. * n;
+    let y = (1. - (lat.tan() + 1. / lat.cos()).ln() / PI) / 2. * n;
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(z: u8, x: u32, y: u32) -> TileId {
+        TileId { z, x, y }
+    }
+
+    fn poi(id: &str, lon: f64, lat: f64, weight: u32) -> Poi {
+        Poi {
+            id: id.to_string(),
+            coord: Coord::new(lon, lat),
+            weight: Weight::from(weight),
+            ..Poi::default()
+        }
+    }
+
+    fn ids<'a>(pyramid: &'a Pyramid<'_>, tile: TileId) -> Vec<&'a str> {
+        pyramid
+            .pois(tile)
+            .iter()
+            .map(|poi| poi.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn antimeridian() {
+        let east = Coord::new(180., 0.);
+        let west = Coord::new(-180., 0.);
+        assert_eq!(TileId::containing(&east, 0), Some(tile(0, 0, 0)));
+        assert_eq!(TileId::containing(&east, 3), Some(tile(3, 7, 4)));
+        assert_eq!(TileId::containing(&west, 3), Some(tile(3, 0, 4)));
+        let last = TileId::containing(&east, MAX_ZOOM).unwrap();
+        assert_eq!(last.x, (1 << MAX_ZOOM) - 1);
+    }
+
+    #[test]
+    fn web_mercator_cutoff() {
+        // 85.05° is in the first row up to zoom 14
+        for z in [0, 1, 14, MAX_ZOOM].iter() {
+            let n = 1u32 << z;
+            let north = TileId::containing(&Coord::new(2., 85.05), *z).unwrap();
+            let south = TileId::containing(&Coord::new(2., -85.05), *z).unwrap();
+            if *z <= 14 {
+                assert_eq!((north.y, south.y), (0, n - 1), "zoom {}", z);
+            }
+            assert_eq!(north.y, n - 1 - south.y, "zoom {}", z);
+            assert_eq!(TileId::containing(&Coord::new(2., 85.06), *z), None);
+            assert_eq!(TileId::containing(&Coord::new(2., -85.06), *z), None);
+            assert_eq!(TileId::containing(&Coord::new(2., 90.), *z), None);
+        }
+    }
+
+    #[test]
+    fn parent() {
+        assert_eq!(tile(3, 5, 6).parent(), Some(tile(2, 2, 3)));
+        assert_eq!(tile(1, 1, 0).parent(), Some(tile(0, 0, 0)));
+        assert_eq!(tile(0, 0, 0).parent(), None);
+        let coord = Coord::new(2.35, 48.85);
+        for z in 1..=MAX_ZOOM {
+            let child = TileId::containing(&coord, z).unwrap();
+            assert_eq!(child.parent(), TileId::containing(&coord, z - 1));
+        }
+    }
+
+    #[test]
+    fn top_pois_of_each_tile() {
+        let model: Model = vec![
+            poi("nw", -90., 45., 1),
+            poi("ne", 90., 45., 4),
+            poi("ne:tie", 90., 45.1, 4),
+            poi("sw", -90., -45., 3),
+            poi("se", 90., -45., 2),
+            poi("se:light", 90.1, -45., 0),
+        ]
+        .into_iter()
+        .collect();
+        let pyramid = model.tile_pyramid(&PyramidOptions {
+            min_zoom: 0,
+            max_zoom: 2,
+            max_pois_per_tile: 2,
+        });
+
+        assert_eq!(ids(&pyramid, tile(0, 0, 0)), vec!["ne", "ne:tie"]);
+        assert_eq!(ids(&pyramid, tile(1, 0, 0)), vec!["nw"]);
+        assert_eq!(ids(&pyramid, tile(1, 1, 0)), vec!["ne", "ne:tie"]);
+        assert_eq!(ids(&pyramid, tile(1, 0, 1)), vec!["sw"]);
+        assert_eq!(ids(&pyramid, tile(1, 1, 1)), vec!["se", "se:light"]);
+        assert_eq!(ids(&pyramid, tile(2, 3, 1)), vec!["ne", "ne:tie"]);
+        assert_eq!(pyramid.tiles().filter(|tile| tile.z == 2).count(), 4);
+        assert!(pyramid.tiles().all(|tile| pyramid.pois(tile).len() <= 2));
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Instrumentation of the long operations with [tracing], behind the
+//! `tracing` feature
+//!
+//! Each operation (`load`, `save`, `merge`, ...) runs in a `poi_model` span,
+//! and ends with an event giving its number of records and its duration, or
+//! its error. Without the feature, only the [Metrics](crate::io::Metrics)
+//! of the operation, if any, are reported.
+
+use crate::io::metrics::{SharedMetrics, DURATION_SECONDS, ERRORS};
+use crate::Result;
+use std::fmt::Display;
+use std::time::Instant;
+
+/// An operation running in its span, until it is finished
+pub(crate) struct Traced {
+    operation: &'static str,
+    started: Instant,
+    metrics: Option<SharedMetrics>,
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+impl Traced {
+    /// Enters the span of `operation` on `subject`, like the path of an
+    /// archive, or an empty string
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn start(operation: &'static str, subject: impl Display) -> Traced {
+        Traced {
+            operation,
+            started: Instant::now(),
+            metrics: None,
+            #[cfg(feature = "tracing")]
+            _span: tracing::info_span!("poi_model", operation, subject = %subject).entered(),
+        }
+    }
+
+    /// Reports the duration of the operation, or its failure, to `metrics`
+    /// as well, if set
+    pub(crate) fn with_metrics(self, metrics: Option<&SharedMetrics>) -> Traced {
+        Traced {
+            metrics: metrics.cloned(),
+            ..self
+        }
+    }
+
+    /// Ends the operation, after `records` records
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn finish(self, records: usize) {
+        let duration = self.started.elapsed();
+        if let Some(metrics) = &self.metrics {
+            let labels = [("operation", self.operation)];
+            metrics.histogram(DURATION_SECONDS, &labels, duration.as_secs_f64());
+        }
+        #[cfg(feature = "tracing")]
+        tracing::info!(records, duration_ms = duration.as_millis() as u64, "done");
+    }
+
+    /// Ends the operation with its `result`, the number of records being
+    /// counted by `records` on success, and returns the result
+    pub(crate) fn finish_with<T, F>(self, result: Result<T>, records: F) -> Result<T>
+    where
+        F: FnOnce(&T) -> usize,
+    {
+        match &result {
+            Ok(value) => self.finish(records(value)),
+            Err(error) => self.fail(error),
+        }
+        result
+    }
+
+    /// Ends the operation with `error`
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    fn fail(self, error: &crate::Error) {
+        let duration = self.started.elapsed();
+        if let Some(metrics) = &self.metrics {
+            metrics.counter(ERRORS, &[("operation", self.operation)], 1);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::warn!(duration_ms = duration.as_millis() as u64, error = %error, "failed");
+    }
+}
@@ -0,0 +1,410 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Atomic changes to a model, see [Model::transaction]
+//!
+//! The changes made through a [Transaction] are staged aside from the model,
+//! which is left untouched until they are all checked: the POIs must have
+//! valid coordinates and a known POI type, the relations must link known
+//! POIs, and the POI types removed must not be used any longer, neither by
+//! a POI nor as the parent of another POI type. The model is then changed
+//! at once, or not at all.
+//!
+//! ```
+//! use navitia_poi_model::{Coord, Model, Poi, PoiType};
+//!
+//! let mut model = Model::default();
+//! let result = model.transaction(|tx| {
+//!     tx.insert_poi_type(PoiType {
+//!         id: "amenity:parking".to_string(),
+//!         name: "Parking".to_string(),
+//!         ..PoiType::default()
+//!     })?;
+//!     tx.insert_poi(Poi {
+//!         id: "poi:1".to_string(),
+//!         name: "Parking Gare".to_string(),
+//!         poi_type_id: "amenity:parking".to_string(),
+//!         coord: Coord::new(2.37, 48.84),
+//!         ..Poi::default()
+//!     })?;
+//!     tx.insert_poi(Poi {
+//!         id: "poi:2".to_string(),
+//!         name: "Nowhere".to_string(),
+//!         poi_type_id: "amenity:parking".to_string(),
+//!         ..Poi::default()
+//!     })
+//! });
+//! assert!(result.is_err());
+//! assert!(model.pois.is_empty() && model.poi_types.is_empty());
+//! ```
+
+use crate::{Model, Poi, PoiRelation, PoiType, Result};
+use anyhow::{anyhow, bail};
+use chrono::Utc;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Changes staged on a [Model], applied by [Model::transaction] if they are
+/// all valid
+///
+/// The getters see the model as it would be once the changes applied.
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    model: &'a Model,
+
+    /// POIs inserted or updated, or removed (`None`), by id
+    pois: BTreeMap<String, Option<Poi>>,
+
+    /// POI types inserted or updated, or removed (`None`), by id
+    poi_types: BTreeMap<String, Option<PoiType>>,
+
+    /// Relations added
+    relations: BTreeSet<PoiRelation>,
+}
+
+impl<'a> Transaction<'a> {
+    fn new(model: &'a Model) -> Transaction<'a> {
+        Transaction {
+            model,
+            pois: BTreeMap::new(),
+            poi_types: BTreeMap::new(),
+            relations: BTreeSet::new(),
+        }
+    }
+
+    /// Returns the POI with the given id, with the changes staged
+    pub fn get_poi(&self, id: &str) -> Option<&Poi> {
+        match self.pois.get(id) {
+            Some(staged) => staged.as_ref(),
+            None => self.model.pois.get(id),
+        }
+    }
+
+    /// Returns the POI type with the given id, with the changes staged
+    pub fn get_poi_type(&self, id: &str) -> Option<&PoiType> {
+        match self.poi_types.get(id) {
+            Some(staged) => staged.as_ref(),
+            None => self.model.poi_types.get(id),
+        }
+    }
+
+    /// Stages the insertion of `poi`, failing if a POI with the same id
+    /// exists
+    pub fn insert_poi(&mut self, poi: Poi) -> Result<()> {
+        if self.get_poi(&poi.id).is_some() {
+            bail!("poi '{}' already exists", poi.id);
+        }
+        self.pois.insert(poi.id.clone(), Some(poi));
+        Ok(())
+    }
+
+    /// Stages `update` of the POI with the given id, failing if there is no
+    /// such POI. As with [Model::update_pois_where], the id of the POI is
+    /// restored after `update` if needed.
+    pub fn update_poi<F>(&mut self, id: &str, update: F) -> Result<()>
+    where
+        F: FnOnce(&mut Poi),
+    {
+        let mut poi = self
+            .get_poi(id)
+            .cloned()
+            .ok_or_else(|| anyhow!("unknown poi '{}'", id))?;
+        update(&mut poi);
+        poi.id = id.to_string();
+        self.pois.insert(id.to_string(), Some(poi));
+        Ok(())
+    }
+
+    /// Stages the removal of the POI with the given id, along with its
+    /// relations, and returns it. Fails if there is no such POI.
+    pub fn remove_poi(&mut self, id: &str) -> Result<Poi> {
+        let poi = self
+            .get_poi(id)
+            .cloned()
+            .ok_or_else(|| anyhow!("unknown poi '{}'", id))?;
+        self.pois.insert(id.to_string(), None);
+        Ok(poi)
+    }
+
+    /// Stages the insertion of `poi_type`, failing if a POI type with the
+    /// same id exists
+    pub fn insert_poi_type(&mut self, poi_type: PoiType) -> Result<()> {
+        if self.get_poi_type(&poi_type.id).is_some() {
+            bail!("poi type '{}' already exists", poi_type.id);
+        }
+        self.poi_types.insert(poi_type.id.clone(), Some(poi_type));
+        Ok(())
+    }
+
+    /// Stages the removal of the POI type with the given id, and returns
+    /// it. Fails if there is no such POI type.
+    pub fn remove_poi_type(&mut self, id: &str) -> Result<PoiType> {
+        let poi_type = self
+            .get_poi_type(id)
+            .cloned()
+            .ok_or_else(|| anyhow!("unknown poi type '{}'", id))?;
+        self.poi_types.insert(id.to_string(), None);
+        Ok(poi_type)
+    }
+
+    /// Stages a link between the POI `child_id` and its parent `parent_id`
+    pub fn add_relation<T: Into<String>>(
+        &mut self,
+        parent_id: &str,
+        child_id: &str,
+        relation_type: T,
+    ) {
+        self.relations.insert(PoiRelation {
+            parent_id: parent_id.to_string(),
+            child_id: child_id.to_string(),
+            relation_type: relation_type.into(),
+        });
+    }
+
+    /// Checks the model as it would be once the changes applied
+    fn validate(&self) -> Result<()> {
+        for poi in self.pois.values().flatten() {
+            if !poi.coord.is_valid() {
+                bail!(
+                    "poi '{}' has invalid coordinates (lon: {}, lat: {})",
+                    poi.id,
+                    poi.coord.lon(),
+                    poi.coord.lat()
+                );
+            }
+            if self.get_poi_type(&poi.poi_type_id).is_none() {
+                bail!("poi '{}' is of unknown type '{}'", poi.id, poi.poi_type_id);
+            }
+        }
+        let removed_types: BTreeSet<&String> = self
+            .poi_types
+            .iter()
+            .filter(|(_, poi_type)| poi_type.is_none())
+            .map(|(id, _)| id)
+            .collect();
+        if !removed_types.is_empty() {
+            let unchanged = self
+                .model
+                .pois
+                .values()
+                .filter(|poi| !self.pois.contains_key(&poi.id));
+            let pois = unchanged.chain(self.pois.values().flatten());
+            for poi in pois {
+                if removed_types.contains(&poi.poi_type_id) {
+                    bail!(
+                        "poi type '{}' is removed but still used by poi '{}'",
+                        poi.poi_type_id,
+                        poi.id
+                    );
+                }
+            }
+            let unchanged = self
+                .model
+                .poi_types
+                .values()
+                .filter(|poi_type| !self.poi_types.contains_key(&poi_type.id));
+            let poi_types = unchanged.chain(self.poi_types.values().flatten());
+            for poi_type in poi_types {
+                if let Some(parent_id) = poi_type
+                    .parent_id
+                    .as_ref()
+                    .filter(|parent_id| removed_types.contains(parent_id))
+                {
+                    bail!(
+                        "poi type '{}' is removed but still the parent of poi type '{}'",
+                        parent_id,
+                        poi_type.id
+                    );
+                }
+            }
+        }
+        for relation in &self.relations {
+            for id in &[&relation.parent_id, &relation.child_id] {
+                if self.get_poi(id).is_none() {
+                    bail!("cannot find poi '{}' for relation", id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The changes, once the borrow of the model is released
+    fn into_changes(self) -> Changes {
+        Changes {
+            pois: self.pois,
+            poi_types: self.poi_types,
+            relations: self.relations,
+        }
+    }
+}
+
+/// The changes of a valid [Transaction]
+struct Changes {
+    pois: BTreeMap<String, Option<Poi>>,
+    poi_types: BTreeMap<String, Option<PoiType>>,
+    relations: BTreeSet<PoiRelation>,
+}
+
+impl Model {
+    /// Runs `changes` on a [Transaction], then applies the changes it
+    /// staged if they are all valid (see the [module
+    /// documentation](crate::transaction)), and returns the result of
+    /// `changes`.
+    ///
+    /// If `changes` fails, or if the staged changes are not valid, the
+    /// model is left untouched and the error is returned. The `updated_at`
+    /// of the POIs inserted or updated is set to the current time, as well
+    /// as the `created_at` of the POIs inserted without one.
+    pub fn transaction<T, F>(&mut self, changes: F) -> Result<T>
+    where
+        F: FnOnce(&mut Transaction<'_>) -> Result<T>,
+    {
+        let mut transaction = Transaction::new(self);
+        let value = changes(&mut transaction)?;
+        transaction.validate()?;
+        let changes = transaction.into_changes();
+
+        let now = Utc::now();
+        for (id, poi_type) in changes.poi_types {
+            match poi_type {
+                Some(poi_type) => self.poi_types.insert(id, poi_type),
+                None => self.poi_types.remove(&id),
+            };
+        }
+        for (id, poi) in changes.pois {
+            match poi {
+                Some(mut poi) => {
+                    if !self.pois.contains_key(&id) && poi.created_at.is_none() {
+                        poi.created_at = Some(now);
+                    }
+                    poi.updated_at = Some(now);
+                    self.pois.insert(id, poi);
+                }
+                None => {
+                    self.pois.remove(&id);
+                }
+            }
+        }
+        self.relations.extend(changes.relations);
+        self.drop_dangling_relations();
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Coord, Model, Poi, PoiRelation, PoiType};
+    use anyhow::bail;
+
+    /// A model with the types `amenity` > `amenity:parking`, and a parking
+    fn model() -> Model {
+        let mut model = Model::default();
+        for (id, parent_id) in &[("amenity", None), ("amenity:parking", Some("amenity"))] {
+            model.poi_types.insert(
+                id.to_string(),
+                PoiType {
+                    id: id.to_string(),
+                    parent_id: parent_id.map(str::to_string),
+                    ..PoiType::default()
+                },
+            );
+        }
+        model.pois.insert("poi:1".to_string(), poi("poi:1"));
+        model
+    }
+
+    fn poi(id: &str) -> Poi {
+        Poi {
+            id: id.to_string(),
+            poi_type_id: "amenity:parking".to_string(),
+            coord: Coord::new(2.37, 48.84),
+            ..Poi::default()
+        }
+    }
+
+    #[test]
+    fn failures_leave_the_model_untouched() {
+        let mut model = model();
+        let result: crate::Result<()> = model.transaction(|tx| {
+            tx.insert_poi(poi("poi:2"))?;
+            tx.remove_poi("poi:1")?;
+            bail!("cancelled")
+        });
+        assert_eq!(result.unwrap_err().to_string(), "cancelled");
+        assert_eq!(model, self::model());
+
+        let result = model.transaction(|tx| {
+            tx.insert_poi(poi("poi:2"))?;
+            tx.update_poi("poi:1", |poi| poi.coord = Coord::new(200.0, 48.84))
+        });
+        assert!(result.is_err());
+        assert_eq!(model, self::model());
+    }
+
+    #[test]
+    fn removed_poi_types_must_be_unused() {
+        let mut model = model();
+        let result = model.transaction(|tx| tx.remove_poi_type("amenity:parking").map(drop));
+        assert!(result.is_err());
+        let error = model
+            .transaction(|tx| tx.remove_poi_type("amenity").map(drop))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "poi type 'amenity' is removed but still the parent of poi type 'amenity:parking'"
+        );
+        assert_eq!(model, self::model());
+
+        model
+            .transaction(|tx| {
+                tx.remove_poi("poi:1")?;
+                tx.remove_poi_type("amenity:parking")?;
+                tx.remove_poi_type("amenity").map(drop)
+            })
+            .unwrap();
+        assert!(model.pois.is_empty() && model.poi_types.is_empty());
+    }
+
+    #[test]
+    fn relations_must_link_known_pois() {
+        let mut model = model();
+        let error = model
+            .transaction(|tx| {
+                tx.add_relation("poi:1", "poi:2", "entrance");
+                Ok(())
+            })
+            .unwrap_err();
+        assert_eq!(error.to_string(), "cannot find poi 'poi:2' for relation");
+        let result = model.transaction(|tx| {
+            tx.insert_poi(poi("poi:2"))?;
+            tx.add_relation("poi:1", "poi:2", "entrance");
+            tx.remove_poi("poi:1").map(drop)
+        });
+        assert!(result.is_err());
+        assert_eq!(model, self::model());
+
+        model
+            .transaction(|tx| {
+                tx.insert_poi(poi("poi:2"))?;
+                tx.add_relation("poi:1", "poi:2", "entrance");
+                Ok(())
+            })
+            .unwrap();
+        let relation = PoiRelation {
+            parent_id: "poi:1".to_string(),
+            child_id: "poi:2".to_string(),
+            relation_type: "entrance".to_string(),
+        };
+        assert_eq!(model.relations.into_iter().collect::<Vec<_>>(), [relation]);
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Non-fatal warnings about the quality of the data
+//!
+//! Loads, saves and merges go on when the data is usable but suspicious: a
+//! header under a deprecated name, a property given twice, a coordinate
+//! clamped, ... Each such case is reported as a [Warning] to the
+//! [WarningCallback] of the operation, if any (see
+//! [LoadOptions::warnings](crate::io::LoadOptions::warnings),
+//! [WriteOptions::warnings](crate::io::WriteOptions::warnings) and
+//! [Model::try_merge_with_warnings](crate::Model::try_merge_with_warnings)).
+//!
+//! ```
+//! use navitia_poi_model::warnings::{Warning, WarningCallback};
+//! use std::sync::{Arc, Mutex};
+//!
+//! let warnings = Arc::new(Mutex::new(Vec::new()));
+//! let collected = warnings.clone();
+//! let callback = WarningCallback::new(move |warning: Warning| {
+//!     collected.lock().unwrap().push(warning);
+//! });
+//! callback.warn(Warning::MetadataDropped);
+//! assert_eq!(warnings.lock().unwrap().len(), 1);
+//! ```
+
+use crate::Coord;
+use std::sync::Arc;
+
+/// Something suspicious noticed on the data, that did not stop the
+/// operation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// A column of `file` was read under the deprecated name `header`, in
+    /// place of `current`
+    DeprecatedHeader {
+        /// Name of the file, in the archive
+        file: String,
+        /// Name of the column in the file
+        header: String,
+        /// Current name of the column
+        current: String,
+    },
+
+    /// A property of a POI was given several values, only the last one
+    /// being kept
+    PropertyOverwritten {
+        /// Id of the POI
+        poi_id: String,
+        /// Key of the property
+        key: String,
+        /// The value overwritten
+        previous: String,
+    },
+
+    /// The coordinates of a POI were out of range, and have been clamped
+    CoordinatesClamped {
+        /// Id of the POI
+        poi_id: String,
+        /// The coordinates as read
+        from: Coord,
+        /// The coordinates kept
+        to: Coord,
+    },
+
+    /// The value of a property violated its constraint, and has been fixed
+    /// (see [ViolationPolicy::Truncate](crate::constraints::ViolationPolicy::Truncate))
+    PropertyFixed {
+        /// Id of the POI
+        poi_id: String,
+        /// Key of the property
+        key: String,
+        /// The violation, as a message
+        violation: String,
+    },
+
+    /// A POI of the model was replaced by the POI of the same id of the
+    /// model merged into it
+    PoiReplaced {
+        /// Id of the POI
+        poi_id: String,
+    },
+
+    /// A POI of the model merged was dropped, the model already having a
+    /// different POI of the same id
+    PoiDropped {
+        /// Id of the POI
+        poi_id: String,
+    },
+
+    /// A POI of the model merged was renamed, the model already having a
+    /// different POI of the same id
+    PoiRenamed {
+        /// Id of the POI in the model merged
+        from: String,
+        /// Id of the POI in the merged model
+        to: String,
+    },
+
+    /// The metadata of the model merged was dropped, in favor of the
+    /// metadata of the model
+    MetadataDropped,
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::DeprecatedHeader {
+                file,
+                header,
+                current,
+            } => write!(
+                f,
+                "{}: deprecated header '{}' read as '{}'",
+                file, header, current
+            ),
+            Warning::PropertyOverwritten {
+                poi_id,
+                key,
+                previous,
+            } => write!(
+                f,
+                "property '{}' of poi '{}' given several times, value '{}' overwritten",
+                key, poi_id, previous
+            ),
+            Warning::CoordinatesClamped { poi_id, from, to } => write!(
+                f,
+                "coordinates of poi '{}' clamped from (lon: {}, lat: {}) to (lon: {}, lat: {})",
+                poi_id,
+                from.lon(),
+                from.lat(),
+                to.lon(),
+                to.lat()
+            ),
+            Warning::PropertyFixed {
+                poi_id,
+                key,
+                violation,
+            } => write!(
+                f,
+                "property '{}' of poi '{}': {}, fixed",
+                key, poi_id, violation
+            ),
+            Warning::PoiReplaced { poi_id } => write!(f, "poi '{}' replaced", poi_id),
+            Warning::PoiDropped { poi_id } => write!(f, "poi '{}' dropped", poi_id),
+            Warning::PoiRenamed { from, to } => write!(f, "poi '{}' renamed to '{}'", from, to),
+            Warning::MetadataDropped => f.write_str("metadata of the merged model dropped"),
+        }
+    }
+}
+
+/// A function called with each [Warning] of an operation
+#[derive(Clone)]
+pub struct WarningCallback(Arc<dyn Fn(Warning) + Send + Sync>);
+
+impl WarningCallback {
+    /// Creates a callback from a function
+    pub fn new<F>(f: F) -> WarningCallback
+    where
+        F: Fn(Warning) + Send + Sync + 'static,
+    {
+        WarningCallback(Arc::new(f))
+    }
+
+    /// Reports a warning
+    pub fn warn(&self, warning: Warning) {
+        (self.0)(warning)
+    }
+}
+
+impl std::fmt::Debug for WarningCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WarningCallback")
+    }
+}
+
+/// Reports a warning if there is a callback
+pub(crate) fn warn(callback: Option<&WarningCallback>, warning: Warning) {
+    if let Some(callback) = callback {
+        callback.warn(warning);
+    }
+}